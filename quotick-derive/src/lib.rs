@@ -0,0 +1,479 @@
+//! `#[derive(Tick)]` for quotick tick types.
+//!
+//! ```ignore
+//! #[derive(Tick, Serialize, Deserialize, Debug, Clone)]
+//! #[tick(epoch = "day", version = 1)]
+//! struct Trade {
+//!     size: u32,
+//!     price: u32,
+//! }
+//! ```
+//!
+//! Supported container attributes:
+//!
+//! - `epoch = "hour" | "day" | "week" | "month"`: epoch rule, defaults to `day`
+//! - `epoch = "session", tz = "America/Chicago", open = "17:00", close = "16:00"`:
+//!   exchange session epochs, see `quotick::epoch_rule::SessionRule`
+//! - `version = N`: schema version, defaults to `0`
+//! - `name = "..."`: type name recorded in the symbol metadata
+//! - `upgrades = "path::to::fn"`: `fn(&mut SchemaUpgrades<Self>)` registering
+//!   upgrades from older schema versions
+//!
+//! Besides `epoch_rule`, the derive generates the field metadata of the type, which
+//! the schema fingerprint of `Tick` is hashed from. Fields marked `#[serde(skip)]`
+//! are not stored and left out; `skip_serializing_if`, `with` and `flatten` change
+//! how fields are stored in ways the metadata can't describe and are rejected.
+//!
+//! Attributes are checked at compile time, so these fail to build:
+//!
+//! ```compile_fail
+//! # use quotick::Tick;
+//! # use serde_derive::{Deserialize, Serialize};
+//! #[derive(Tick, Serialize, Deserialize, Debug, Clone)]
+//! #[tick(epoch = "session", tz = "America/Chicago", open = "9:3O", close = "16:00")]
+//! struct Trade {
+//!     size: u32,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! # use quotick::Tick;
+//! # use serde_derive::{Deserialize, Serialize};
+//! #[derive(Tick, Serialize, Deserialize, Debug, Clone)]
+//! #[tick(epoch = "session", tz = "America/Chikago", open = "09:30", close = "16:00")]
+//! struct Trade {
+//!     size: u32,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! # use quotick::Tick;
+//! # use quotick::price::Price;
+//! # use serde_derive::{Deserialize, Serialize};
+//! const SCALE: u32 = 4;
+//!
+//! #[derive(Tick, Serialize, Deserialize, Debug, Clone)]
+//! struct Trade {
+//!     price: Price<SCALE>,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! # use quotick::Tick;
+//! # use serde_derive::{Deserialize, Serialize};
+//! #[derive(Tick, Serialize, Deserialize, Debug, Clone)]
+//! struct Trade {
+//!     #[serde(skip_serializing_if = "Option::is_none")]
+//!     venue: Option<String>,
+//! }
+//! ```
+
+extern crate proc_macro;
+
+use std::str::FromStr;
+
+use chrono::NaiveTime;
+use chrono_tz::Tz;
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{quote, ToTokens};
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Expr, ExprLit, Field, Fields,
+    GenericArgument, Lit, LitStr, Meta, NestedMeta, Path, PathArguments, Type,
+};
+
+struct TickAttrs {
+    epoch: String,
+    tz: Option<LitStr>,
+    open: Option<LitStr>,
+    close: Option<LitStr>,
+    version: u32,
+    name: Option<String>,
+    upgrades: Option<Path>,
+}
+
+#[proc_macro_derive(Tick, attributes(tick))]
+pub fn derive_tick(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let attrs = parse_attrs(&input)?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields =
+        match &input.data {
+            Data::Struct(data) => match &data.fields {
+                Fields::Named(fields) => fields
+                    .named
+                    .iter()
+                    .map(|field| (field.ident.as_ref().unwrap().to_string(), field))
+                    .collect::<Vec<_>>(),
+                Fields::Unnamed(fields) => fields
+                    .unnamed
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, field)| (idx.to_string(), field))
+                    .collect::<Vec<_>>(),
+                Fields::Unit => Vec::new(),
+            },
+            _ => {
+                return Err(
+                    Error::new_spanned(
+                        &input.ident,
+                        "#[derive(Tick)] is only supported on structs",
+                    ),
+                );
+            }
+        };
+
+    let epoch_rule = epoch_rule(&attrs, input.generics.params.is_empty())?;
+
+    let version = attrs.version;
+
+    let mut field_entries = Vec::with_capacity(fields.len());
+
+    for (name, field) in &fields {
+        if is_skipped(field)? {
+            continue;
+        }
+
+        let kind = field_kind(&field.ty)?;
+
+        field_entries.push(quote! { ::quotick::tick::TickField::new(#name, #kind) });
+    }
+
+    let type_name =
+        attrs.name
+            .as_ref()
+            .map(|name| quote! {
+                #[inline(always)]
+                fn type_name() -> &'static str {
+                    #name
+                }
+            });
+
+    let upgrades =
+        attrs.upgrades
+            .as_ref()
+            .map(|path| quote! {
+                #[inline(always)]
+                fn register_upgrades(upgrades: &mut ::quotick::schema::SchemaUpgrades<Self>) {
+                    #path(upgrades)
+                }
+            });
+
+    Ok(
+        quote! {
+            impl #impl_generics ::quotick::Tick for #ident #ty_generics #where_clause {
+                #[inline(always)]
+                fn epoch_rule() -> ::quotick::epoch_rule::EpochRule {
+                    #epoch_rule
+                }
+
+                #type_name
+
+                #[inline(always)]
+                fn fields() -> ::std::vec::Vec<::quotick::tick::TickField> {
+                    ::std::vec![#(#field_entries),*]
+                }
+
+                #[inline(always)]
+                fn schema_version() -> u32 {
+                    #version
+                }
+
+                #upgrades
+            }
+        },
+    )
+}
+
+fn parse_attrs(input: &DeriveInput) -> Result<TickAttrs, Error> {
+    let mut attrs =
+        TickAttrs {
+            epoch: "day".to_string(),
+            tz: None,
+            open: None,
+            close: None,
+            version: 0,
+            name: None,
+            upgrades: None,
+        };
+
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("tick")) {
+        let list =
+            match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(Error::new_spanned(meta, "expected #[tick(..)]")),
+            };
+
+        for nested in list.nested {
+            let name_value =
+                match nested {
+                    NestedMeta::Meta(Meta::NameValue(name_value)) => name_value,
+                    other => return Err(Error::new_spanned(other, "expected `key = value`")),
+                };
+
+            let key =
+                name_value.path
+                    .get_ident()
+                    .map(|ident| ident.to_string())
+                    .unwrap_or_default();
+
+            match (key.as_str(), &name_value.lit) {
+                ("epoch", Lit::Str(lit)) => attrs.epoch = lit.value(),
+                ("tz", Lit::Str(lit)) => attrs.tz = Some(lit.clone()),
+                ("open", Lit::Str(lit)) => attrs.open = Some(lit.clone()),
+                ("close", Lit::Str(lit)) => attrs.close = Some(lit.clone()),
+                ("version", Lit::Int(lit)) => attrs.version = lit.base10_parse()?,
+                ("name", Lit::Str(lit)) => attrs.name = Some(lit.value()),
+                ("upgrades", Lit::Str(lit)) => attrs.upgrades = Some(lit.parse()?),
+                _ => return Err(Error::new_spanned(name_value, "unknown tick attribute")),
+            }
+        }
+    }
+
+    Ok(attrs)
+}
+
+/// A `session` rule is parsed once per type, which a `static` in a generic impl
+/// can't do, as it is shared by all instantiations.
+fn epoch_rule(
+    attrs: &TickAttrs,
+    cache: bool,
+) -> Result<proc_macro2::TokenStream, Error> {
+    if attrs.epoch != "session" {
+        if let Some(lit) = attrs.tz.as_ref().or(attrs.open.as_ref()).or(attrs.close.as_ref()) {
+            return Err(Error::new_spanned(lit, "`tz`, `open` and `close` only apply to `epoch = \"session\"`"));
+        }
+    }
+
+    let rule =
+        match attrs.epoch.as_str() {
+            "hour" => quote! { Hour },
+            "day" => quote! { Day },
+            "week" => quote! { Week },
+            "month" => quote! { Month },
+            "session" => {
+                let (tz, open, close) =
+                    match (&attrs.tz, &attrs.open, &attrs.close) {
+                        (Some(tz), Some(open), Some(close)) => (tz, open, close),
+                        _ => {
+                            return Err(
+                                Error::new(
+                                    Span::call_site(),
+                                    "`epoch = \"session\"` requires `tz`, `open` and `close`",
+                                ),
+                            );
+                        }
+                    };
+
+                // checked here the way `EpochRule::session` parses them, so
+                // a typo fails the build instead of the first insert.
+                if Tz::from_str(&tz.value()).is_err() {
+                    return Err(Error::new_spanned(tz, "unknown time zone, expected an IANA name like `America/Chicago`"));
+                }
+
+                for time in [open, close] {
+                    let valid =
+                        NaiveTime::parse_from_str(&time.value(), "%H:%M")
+                            .or_else(|_| NaiveTime::parse_from_str(&time.value(), "%H:%M:%S"))
+                            .is_ok();
+
+                    if !valid {
+                        return Err(Error::new_spanned(time, "expected a time as `HH:MM` or `HH:MM:SS`"));
+                    }
+                }
+
+                let rule =
+                    quote! {
+                        ::quotick::epoch_rule::EpochRule::session(#tz, #open, #close)
+                            .expect("invalid #[tick] session")
+                    };
+
+                if !cache {
+                    return Ok(rule);
+                }
+
+                // parsed once; `epoch_rule` is called for every insert.
+                return Ok(
+                    quote! {
+                        static RULE: ::std::sync::OnceLock<::quotick::epoch_rule::EpochRule> =
+                            ::std::sync::OnceLock::new();
+
+                        *RULE.get_or_init(|| #rule)
+                    },
+                );
+            }
+            other => {
+                return Err(
+                    Error::new(
+                        Span::call_site(),
+                        format!(
+                            "unknown epoch rule `{}`, expected `hour`, `day`, `week`, `month` or `session`",
+                            other,
+                        ),
+                    ),
+                );
+            }
+        };
+
+    Ok(quote! { ::quotick::epoch_rule::EpochRule::#rule })
+}
+
+fn type_string(ty: &Type) -> String {
+    ty.to_token_stream()
+        .to_string()
+        .replace(' ', "")
+}
+
+/// Whether `#[serde(..)]` leaves `field` out of the stored layout, rejecting
+/// attributes that change the layout otherwise.
+fn is_skipped(field: &Field) -> Result<bool, Error> {
+    let mut skipped = false;
+
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("serde")) {
+        for meta in serde_metas(attr)? {
+            let key =
+                meta.path()
+                    .get_ident()
+                    .map(|ident| ident.to_string())
+                    .unwrap_or_default();
+
+            match key.as_str() {
+                "skip" => skipped = true,
+                "skip_serializing" | "skip_deserializing" | "skip_serializing_if" => {
+                    return Err(
+                        Error::new_spanned(
+                            meta,
+                            "#[derive(Tick)] can't store fields skipped one way only, use `#[serde(skip)]`",
+                        ),
+                    );
+                }
+                "with" | "serialize_with" | "deserialize_with" | "flatten" => {
+                    return Err(
+                        Error::new_spanned(
+                            meta,
+                            "#[derive(Tick)] can't describe fields stored through `with` or `flatten`",
+                        ),
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(skipped)
+}
+
+fn serde_metas(attr: &Attribute) -> Result<Vec<Meta>, Error> {
+    match attr.parse_meta()? {
+        Meta::List(list) => Ok(
+            list.nested
+                .into_iter()
+                .filter_map(|nested| match nested {
+                    NestedMeta::Meta(meta) => Some(meta),
+                    NestedMeta::Lit(_) => None,
+                })
+                .collect(),
+        ),
+        _ => Ok(Vec::new()),
+    }
+}
+
+fn field_kind(ty: &Type) -> Result<proc_macro2::TokenStream, Error> {
+    if let Type::Array(array) = ty {
+        let kind = field_kind(&array.elem)?;
+        let len = &array.len;
+
+        return Ok(quote! { ::quotick::tick::FieldKind::Array(::std::boxed::Box::new(#kind), #len) });
+    }
+
+    let segment =
+        match ty {
+            Type::Path(path) if path.qself.is_none() => path.path.segments.last(),
+            _ => None,
+        };
+
+    let ident =
+        match segment {
+            Some(segment) if segment.ident == "Price" => {
+                let scale = price_scale(&segment.arguments)?;
+
+                return Ok(quote! { ::quotick::tick::FieldKind::Price(#scale) });
+            }
+            Some(segment) if segment.ident == "Vec" => {
+                if let Some(elem) = type_argument(&segment.arguments) {
+                    let kind = field_kind(elem)?;
+
+                    return Ok(quote! { ::quotick::tick::FieldKind::Seq(::std::boxed::Box::new(#kind)) });
+                }
+
+                String::new()
+            }
+            Some(segment) if segment.arguments.is_empty() => segment.ident.to_string(),
+            _ => String::new(),
+        };
+
+    let kind =
+        match ident.as_str() {
+            "bool" => quote! { ::quotick::tick::FieldKind::Bool },
+            "u8" => quote! { ::quotick::tick::FieldKind::U8 },
+            "u16" => quote! { ::quotick::tick::FieldKind::U16 },
+            "u32" => quote! { ::quotick::tick::FieldKind::U32 },
+            "u64" => quote! { ::quotick::tick::FieldKind::U64 },
+            "i8" => quote! { ::quotick::tick::FieldKind::I8 },
+            "i16" => quote! { ::quotick::tick::FieldKind::I16 },
+            "i32" => quote! { ::quotick::tick::FieldKind::I32 },
+            "i64" => quote! { ::quotick::tick::FieldKind::I64 },
+            "f32" => quote! { ::quotick::tick::FieldKind::F32 },
+            "f64" => quote! { ::quotick::tick::FieldKind::F64 },
+            "String" => quote! { ::quotick::tick::FieldKind::String },
+            "NaiveDate" => quote! { ::quotick::tick::FieldKind::Date },
+            _ => {
+                let ty = type_string(ty);
+
+                quote! { ::quotick::tick::FieldKind::Other(#ty.to_string()) }
+            }
+        };
+
+    Ok(kind)
+}
+
+fn type_argument(arguments: &PathArguments) -> Option<&Type> {
+    match arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .find_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            }),
+        _ => None,
+    }
+}
+
+/// Scale of `Price` or `Price<N>`; must match the default of `quotick::price::Price`.
+///
+/// Only integer literals can be read here, so any other scale, i.e. a named
+/// constant, is rejected rather than recorded wrongly.
+fn price_scale(arguments: &PathArguments) -> Result<u32, Error> {
+    let args =
+        match arguments {
+            PathArguments::AngleBracketed(args) => args,
+            _ => return Ok(8),
+        };
+
+    match args.args.iter().next() {
+        Some(GenericArgument::Const(Expr::Lit(ExprLit { lit: Lit::Int(lit), .. }))) => lit.base10_parse(),
+        Some(arg) => Err(Error::new_spanned(arg, "the scale of `Price` must be an integer literal")),
+        None => Ok(8),
+    }
+}
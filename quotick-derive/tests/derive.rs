@@ -0,0 +1,116 @@
+use serde_derive::{Deserialize, Serialize};
+
+use quotick::epoch_rule::EpochRule;
+use quotick::price::Price;
+use quotick::schema::SchemaUpgrades;
+use quotick::tick::{FieldKind, TickField};
+use quotick::Tick;
+
+#[derive(Tick, Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct DailyTrade {
+    size: u32,
+    price: f64,
+    venue: String,
+}
+
+#[derive(Tick, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[tick(epoch = "hour", version = 2, name = "test::HourlyTrade", upgrades = "register")]
+struct HourlyTrade {
+    size: u32,
+    conditions: Vec<u8>,
+    price: Price,
+    bid: Price<4>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HourlyTradeV1 {
+    size: u32,
+}
+
+fn register(upgrades: &mut SchemaUpgrades<HourlyTrade>) {
+    upgrades.register(1, |old: HourlyTradeV1| HourlyTrade { size: old.size, conditions: Vec::new(), price: Price::ZERO, bid: Price::ZERO });
+}
+
+#[derive(Tick, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[tick(epoch = "session", tz = "America/Chicago", open = "17:00", close = "16:00")]
+struct FuturesTrade {
+    size: u32,
+}
+
+#[derive(Tick, Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct SpelledOut {
+    size: u32,
+    conditions: ::std::vec::Vec<u8>,
+    price: quotick::price::Price<8>,
+    bid: Price<4>,
+}
+
+#[derive(Tick, Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Skipping {
+    size: u32,
+    #[serde(skip)]
+    cached: Option<u64>,
+    #[serde(rename = "v")]
+    venue: String,
+}
+
+#[derive(Tick, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[tick(epoch = "session", tz = "Asia/Tokyo", open = "08:45", close = "15:15")]
+struct Generic<T: Clone + std::fmt::Debug> {
+    value: T,
+}
+
+#[test]
+fn epoch_rule() {
+    assert_eq!(DailyTrade::epoch_rule().epoch(86_400_000_000_000 * 3 + 1), 3);
+    assert_eq!(DailyTrade::epoch_rule(), EpochRule::Day);
+
+    assert_eq!(HourlyTrade::epoch_rule().epoch(3_600_000_000_000 * 5), 5);
+    assert_eq!(HourlyTrade::epoch_rule(), EpochRule::Hour);
+
+    assert_eq!(
+        FuturesTrade::epoch_rule(),
+        EpochRule::session("America/Chicago", "17:00", "16:00").unwrap(),
+    );
+
+    assert_eq!(
+        Generic::<u64>::epoch_rule(),
+        EpochRule::session("Asia/Tokyo", "08:45", "15:15").unwrap(),
+    );
+}
+
+#[test]
+fn schema_metadata() {
+    assert_eq!(
+        DailyTrade::fields(),
+        vec![
+            TickField::new("size", FieldKind::U32),
+            TickField::new("price", FieldKind::F64),
+            TickField::new("venue", FieldKind::String),
+        ],
+    );
+
+    assert_eq!(HourlyTrade::fields()[1].kind, FieldKind::Seq(Box::new(FieldKind::U8)));
+    assert_eq!(HourlyTrade::fields()[2].kind, FieldKind::Price(8));
+    assert_eq!(HourlyTrade::fields()[3].kind, FieldKind::Price(4));
+
+    assert_eq!(DailyTrade::schema_version(), 0);
+    assert_eq!(HourlyTrade::schema_version(), 2);
+    assert_eq!(HourlyTrade::type_name(), "test::HourlyTrade");
+    assert!(DailyTrade::type_name().ends_with("DailyTrade"));
+
+    assert_ne!(DailyTrade::schema_fingerprint(), HourlyTrade::schema_fingerprint());
+
+    // hashed from the field kinds, not how their types are spelled.
+    assert_eq!(SpelledOut::fields(), HourlyTrade::fields());
+    assert_eq!(SpelledOut::schema_fingerprint(), HourlyTrade::schema_fingerprint());
+
+    // skipped fields are not stored, so they are not part of the schema.
+    assert_eq!(Skipping::fields(), vec![TickField::new("size", FieldKind::U32), TickField::new("venue", FieldKind::String)]);
+
+    let mut upgrades = SchemaUpgrades::new();
+
+    HourlyTrade::register_upgrades(&mut upgrades);
+
+    assert!(upgrades.contains(1));
+}
@@ -0,0 +1,403 @@
+use std::convert::TryInto;
+use std::fs::{self, File};
+use std::io;
+use std::io::Write;
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+use std::slice;
+
+use memmap::Mmap;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::epoch::Epoch;
+use super::frame::FrameRef;
+use super::Tick;
+
+const ARCHIVE_MAGIC: &[u8] = b"QTAR";
+const ARCHIVE_VERSION: u32 = 1;
+
+/// magic, version, schema version, tick size, fingerprint, frame count.
+const HEADER_LEN: usize = 32;
+
+/// Largest tick alignment an archive supports; timestamps and ticks are
+/// laid out at multiples of 8 bytes from the page aligned mapping.
+const MAX_ALIGN: usize = 8;
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    IoError(io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    /// The archive was written for a different tick type or schema.
+    FingerprintMismatch {
+        expected: u64,
+        found: u64,
+    },
+    /// The archive was written with a differently sized tick type.
+    LayoutMismatch {
+        expected: usize,
+        found: usize,
+    },
+    /// The tick type's alignment exceeds 8 bytes, or the platform is
+    /// big endian.
+    UnsupportedLayout,
+    /// Archives hold plaintext ticks, so encrypted symbols have none.
+    Encrypted,
+    /// Archives are memory mapped, so symbols stored on a backend other
+    /// than the local filesystem have none.
+    NotLocal,
+    Truncated,
+    /// Timestamps are not strictly ascending.
+    Unsorted,
+}
+
+impl From<io::Error> for ArchiveError {
+    #[inline(always)]
+    fn from(err: io::Error) -> Self {
+        ArchiveError::IoError(err)
+    }
+}
+
+/// Tick types whose in-memory representation can be stored and read
+/// back verbatim.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` (or `#[repr(transparent)]`) plain old
+/// data: no padding bytes, no pointers or references, and every bit
+/// pattern must be a valid value. Integers, floats, `Price` and arrays
+/// or structs made of those qualify; `bool`, `char`, enums, `String` and
+/// `Vec` do not.
+pub unsafe trait FixedLayout: Tick + Copy + 'static {}
+
+/// An epoch stored as plain arrays of timestamps and ticks, memory
+/// mapped and accessed in place.
+///
+/// The header, the file size and the ordering of the timestamps are
+/// validated once when opening; reading a tick afterwards is a pointer
+/// offset, with no deserialization. Archives are derived from the epoch
+/// files, see `Quotick::archived_epoch`, and removed whenever their epoch
+/// is persisted again.
+pub struct ArchivedEpoch<T: FixedLayout> {
+    mmap: Mmap,
+    epoch: u64,
+    len: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: FixedLayout> ArchivedEpoch<T> {
+    /// Writes the frames of `epoch` to an archive at `path`, atomically
+    /// replacing any existing one.
+    pub fn write<P: AsRef<Path>>(
+        path: P,
+        epoch: &Epoch<T>,
+    ) -> Result<(), ArchiveError>
+        where T: Serialize + DeserializeOwned
+    {
+        check_layout::<T>()?;
+
+        let len = epoch.len();
+
+        let mut buf = Vec::with_capacity(HEADER_LEN + len * (8 + size_of::<T>()));
+
+        buf.extend_from_slice(ARCHIVE_MAGIC);
+        buf.extend_from_slice(&ARCHIVE_VERSION.to_le_bytes());
+        buf.extend_from_slice(&T::schema_version().to_le_bytes());
+        buf.extend_from_slice(&(size_of::<T>() as u32).to_le_bytes());
+        buf.extend_from_slice(&T::schema_fingerprint().to_le_bytes());
+        buf.extend_from_slice(&(len as u64).to_le_bytes());
+
+        epoch.iter()
+            .for_each(|frame| buf.extend_from_slice(&frame.time().to_le_bytes()));
+
+        epoch.iter()
+            .for_each(|frame| {
+                // Safety: `FixedLayout` guarantees there are no padding
+                // bytes, so all `size_of::<T>()` bytes are initialized.
+                let bytes =
+                    unsafe {
+                        slice::from_raw_parts(
+                            frame.tick() as *const T as *const u8,
+                            size_of::<T>(),
+                        )
+                    };
+
+                buf.extend_from_slice(bytes);
+            });
+
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("qta.tmp");
+
+        {
+            let mut file = File::create(&tmp_path)?;
+
+            file.write_all(&buf)?;
+            file.sync_all()?;
+        }
+
+        fs::rename(&tmp_path, path)?;
+
+        Ok(())
+    }
+
+    /// Maps and validates the archive of `epoch` at `path`.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        epoch: u64,
+    ) -> Result<ArchivedEpoch<T>, ArchiveError> {
+        check_layout::<T>()?;
+
+        let file = File::open(path)?;
+
+        // Safety: archives are only ever replaced by renaming a new file
+        // over them, never modified in place.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        if mmap.len() < HEADER_LEN {
+            return Err(ArchiveError::Truncated);
+        }
+
+        if &mmap[..4] != ARCHIVE_MAGIC {
+            return Err(ArchiveError::BadMagic);
+        }
+
+        let u32_at = |offset: usize| u32::from_le_bytes(mmap[offset..offset + 4].try_into().unwrap());
+        let u64_at = |offset: usize| u64::from_le_bytes(mmap[offset..offset + 8].try_into().unwrap());
+
+        let version = u32_at(4);
+
+        if version != ARCHIVE_VERSION {
+            return Err(ArchiveError::UnsupportedVersion(version));
+        }
+
+        let fingerprint = u64_at(16);
+
+        if u32_at(8) != T::schema_version() || fingerprint != T::schema_fingerprint() {
+            return Err(
+                ArchiveError::FingerprintMismatch {
+                    expected: T::schema_fingerprint(),
+                    found: fingerprint,
+                },
+            );
+        }
+
+        let tick_size = u32_at(12) as usize;
+
+        if tick_size != size_of::<T>() {
+            return Err(
+                ArchiveError::LayoutMismatch {
+                    expected: size_of::<T>(),
+                    found: tick_size,
+                },
+            );
+        }
+
+        let len = u64_at(24) as usize;
+
+        let expected_len =
+            len.checked_mul(8 + size_of::<T>())
+                .and_then(|body| body.checked_add(HEADER_LEN));
+
+        if expected_len != Some(mmap.len()) {
+            return Err(ArchiveError::Truncated);
+        }
+
+        let archive =
+            ArchivedEpoch {
+                mmap,
+                epoch,
+                len,
+                _phantom: PhantomData,
+            };
+
+        if archive.times().windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(ArchiveError::Unsorted);
+        }
+
+        Ok(archive)
+    }
+
+    #[inline(always)]
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Frame times, ascending.
+    #[inline(always)]
+    pub fn times(&self) -> &[u64] {
+        // Safety: `open` checked the length; the mapping is page aligned
+        // and the header is 8 bytes aligned, and the platform is little
+        // endian, see `check_layout`.
+        unsafe {
+            slice::from_raw_parts(
+                self.mmap.as_ptr().add(HEADER_LEN) as *const u64,
+                self.len,
+            )
+        }
+    }
+
+    /// Ticks, in the order of `times`.
+    #[inline(always)]
+    pub fn ticks(&self) -> &[T] {
+        // Safety: as for `times`; `FixedLayout` guarantees any bytes are a
+        // valid `T` and `check_layout` that the offset is aligned for it.
+        unsafe {
+            slice::from_raw_parts(
+                self.mmap.as_ptr().add(HEADER_LEN + self.len * 8) as *const T,
+                self.len,
+            )
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(
+        &self,
+        time: u64,
+    ) -> Option<FrameRef<'_, T>> {
+        self.times()
+            .binary_search(&time)
+            .ok()
+            .map(|idx| FrameRef::new(time, &self.ticks()[idx]))
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> impl Iterator<Item=FrameRef<'_, T>> + '_ {
+        self.times()
+            .iter()
+            .zip(self.ticks().iter())
+            .map(|(&time, tick)| FrameRef::new(time, tick))
+    }
+
+    /// Frames with times in `range`, located by binary search.
+    #[inline(always)]
+    pub fn range(
+        &self,
+        range: impl RangeBounds<u64>,
+    ) -> impl Iterator<Item=FrameRef<'_, T>> + '_ {
+        let times = self.times();
+
+        let start =
+            match range.start_bound() {
+                Bound::Included(start) => times.partition_point(|time| time < start),
+                Bound::Excluded(start) => times.partition_point(|time| time <= start),
+                Bound::Unbounded => 0,
+            };
+
+        let end =
+            match range.end_bound() {
+                Bound::Included(end) => times.partition_point(|time| time <= end),
+                Bound::Excluded(end) => times.partition_point(|time| time < end),
+                Bound::Unbounded => times.len(),
+            };
+
+        times[start..end.max(start)]
+            .iter()
+            .zip(self.ticks()[start..end.max(start)].iter())
+            .map(|(&time, tick)| FrameRef::new(time, tick))
+    }
+}
+
+#[inline(always)]
+fn check_layout<T: FixedLayout>() -> Result<(), ArchiveError> {
+    if align_of::<T>() > MAX_ALIGN || cfg!(target_endian = "big") {
+        return Err(ArchiveError::UnsupportedLayout);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::super::backing::storage::MemoryBackend;
+    use super::super::options::QuotickOptions;
+    use super::super::quotick::{Quotick, QuotickError};
+    use super::super::test_util::TempPath;
+    use super::super::tick::{Quote, Trade};
+    use super::super::{Frame, Price};
+    use super::*;
+
+    #[test]
+    fn archives_are_validated_and_invalidated() {
+        let base_path = TempPath::new("archive");
+        let mut quotick = Quotick::<Trade>::new("SYMBL", &base_path).unwrap();
+
+        for time in [30, 10, 20] {
+            quotick
+                .insert(&Frame::new(time, Trade { size: time, price: Price::from_int(time as i64).unwrap() }))
+                .unwrap();
+        }
+
+        quotick.persist().unwrap();
+
+        let archive = quotick.archived_epoch(0).unwrap();
+
+        assert_eq!(archive.times(), &[10, 20, 30]);
+        assert_eq!(archive.ticks()[1].size, 20);
+        assert_eq!(archive.get(30).unwrap().tick().price, Price::from_int(30).unwrap());
+        assert_eq!(archive.range(15..=30).map(|frame| frame.time()).collect::<Vec<_>>(), vec![20, 30]);
+        assert!(archive.get(15).is_none());
+
+        // the mapping must go before the file is truncated below it.
+        drop(archive);
+
+        let path = quotick.path_builder().archive_file(0);
+
+        match ArchivedEpoch::<Quote>::open(&path, 0) {
+            Err(ArchiveError::FingerprintMismatch { .. }) => {}
+            other => panic!("unexpected {:?}", other.map(|archive| archive.len())),
+        }
+
+        let bytes = fs::read(&path).unwrap();
+
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+
+        assert!(matches!(ArchivedEpoch::<Trade>::open(&path, 0), Err(ArchiveError::Truncated)));
+
+        // persisting the epoch again removes the stale archive.
+        quotick
+            .insert(&Frame::new(40, Trade::default()))
+            .unwrap();
+
+        quotick.persist().unwrap();
+
+        assert!(!path.exists());
+        assert_eq!(quotick.archived_epoch(0).unwrap().len(), 4);
+
+        assert!(matches!(quotick.archived_epoch(1), Err(QuotickError::EpochNotFound(1))));
+    }
+
+    #[test]
+    fn memory_backends_have_no_archives() {
+        let options =
+            QuotickOptions {
+                backend: Arc::new(MemoryBackend::new()),
+                ..QuotickOptions::default()
+            };
+
+        let mut quotick = Quotick::<Trade>::with_options("SYMBL", "/quotick-archive", options).unwrap();
+
+        quotick
+            .insert(&Frame::new(1, Trade::default()))
+            .unwrap();
+
+        quotick.persist().unwrap();
+
+        assert!(matches!(quotick.archived_epoch(0), Err(QuotickError::Archive(ArchiveError::NotLocal))));
+        assert!(matches!(quotick.archive_all(), Err(QuotickError::Archive(ArchiveError::NotLocal))));
+    }
+}
@@ -0,0 +1,288 @@
+use std::ops::RangeBounds;
+use std::panic;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task;
+
+use super::frame::Frame;
+use super::options::QuotickOptions;
+use super::quotick::{time_bounds, Quotick, QuotickError};
+use super::Tick;
+
+/// Frames a `FrameStream` reads ahead of its consumer.
+const STREAM_BUFFER: usize = 1024;
+
+/// An async front-end to `Quotick`, for use within a tokio runtime.
+///
+/// File I/O, i.e. switching epochs on insert and persisting, runs on the
+/// blocking pool of the runtime. Inserts into the loaded epoch only touch
+/// memory and complete on the calling task.
+///
+/// Clones share the same symbol. It is persisted when the last clone is
+/// dropped, which blocks; use `close` to do so on the blocking pool.
+pub struct AsyncQuotick<T: Tick + Serialize + DeserializeOwned> {
+    inner: Arc<Mutex<Quotick<T>>>,
+}
+
+impl<T: Tick + Serialize + DeserializeOwned> Clone for AsyncQuotick<T> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        AsyncQuotick {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Tick + Serialize + DeserializeOwned> From<Quotick<T>> for AsyncQuotick<T> {
+    #[inline(always)]
+    fn from(quotick: Quotick<T>) -> Self {
+        AsyncQuotick {
+            inner: Arc::new(Mutex::new(quotick)),
+        }
+    }
+}
+
+impl<T> AsyncQuotick<T>
+    where T: Tick + Serialize + DeserializeOwned + Send + 'static
+{
+    #[inline(always)]
+    pub async fn new(
+        asset: &str,
+        base_path: impl Into<PathBuf>,
+    ) -> Result<AsyncQuotick<T>, QuotickError> {
+        AsyncQuotick::with_options(
+            asset,
+            base_path,
+            QuotickOptions::default(),
+        ).await
+    }
+
+    pub async fn with_options(
+        asset: &str,
+        base_path: impl Into<PathBuf>,
+        options: QuotickOptions,
+    ) -> Result<AsyncQuotick<T>, QuotickError> {
+        let asset = asset.to_string();
+        let base_path = base_path.into();
+
+        let quotick =
+            blocking(move || {
+                Quotick::with_options(
+                    &asset,
+                    &base_path,
+                    options,
+                )
+            }).await?;
+
+        Ok(AsyncQuotick::from(quotick))
+    }
+
+    #[inline(always)]
+    pub async fn insert(
+        &self,
+        frame: Frame<T>,
+    ) -> Result<(), QuotickError> {
+        self.insert_opt(
+            frame,
+            false,
+        ).await
+    }
+
+    pub async fn insert_opt(
+        &self,
+        frame: Frame<T>,
+        force_overwrite: bool,
+    ) -> Result<(), QuotickError> {
+        let mut quotick = self.inner.clone().lock_owned().await;
+
+        if !quotick.needs_epoch_update(frame.epoch()) {
+            return quotick.insert_opt(&frame, force_overwrite);
+        }
+
+        // persists the current epoch and loads the one of `frame`.
+        blocking(move || quotick.insert_opt(&frame, force_overwrite)).await
+    }
+
+    pub async fn persist(&self) -> Result<(), QuotickError> {
+        let mut quotick = self.inner.clone().lock_owned().await;
+
+        blocking(move || quotick.persist()).await
+    }
+
+    /// Runs `f` on the blocking pool, i.e. to call methods of `Quotick`
+    /// that have no async counterpart.
+    pub async fn run_blocking<F, R>(
+        &self,
+        f: F,
+    ) -> R
+        where
+            F: FnOnce(&mut Quotick<T>) -> R + Send + 'static,
+            R: Send + 'static,
+    {
+        let mut quotick = self.inner.clone().lock_owned().await;
+
+        blocking(move || f(&mut quotick)).await
+    }
+
+    /// Streams the frames with times in `range`, oldest first, including
+    /// frames not persisted yet.
+    ///
+    /// Epochs are read one at a time on the blocking pool, so inserts
+    /// proceed while the stream is consumed; frames inserted into epochs
+    /// not read yet show up in the stream. Must be called within a tokio
+    /// runtime.
+    pub fn range(
+        &self,
+        range: impl RangeBounds<u64>,
+    ) -> FrameStream<T> {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+
+        let inner = self.inner.clone();
+        let bounds = time_bounds(range);
+
+        task::spawn_blocking(move || {
+            let (start, end) =
+                match bounds {
+                    Some(bounds) => bounds,
+                    None => return,
+                };
+
+            let epochs =
+                inner.blocking_lock()
+                    .epochs_within(start, end)
+                    .copied()
+                    .collect::<Vec<_>>();
+
+            for epoch in epochs {
+                let frames = inner.blocking_lock().epoch_frames_within(epoch, start, end);
+
+                let frames =
+                    match frames {
+                        Ok(frames) => frames,
+                        Err(err) => {
+                            let _ = tx.blocking_send(Err(err));
+
+                            return;
+                        }
+                    };
+
+                for frame in frames {
+                    // the stream was dropped.
+                    if tx.blocking_send(Ok(frame)).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        FrameStream {
+            rx,
+        }
+    }
+
+    /// Persists the symbol and drops this handle on the blocking pool.
+    pub async fn close(self) -> Result<(), QuotickError> {
+        let inner = self.inner;
+
+        blocking(move || inner.blocking_lock().persist()).await
+    }
+}
+
+/// Frames read by `AsyncQuotick::range`. Stops after the first error.
+pub struct FrameStream<T: Tick> {
+    rx: mpsc::Receiver<Result<Frame<T>, QuotickError>>,
+}
+
+impl<T: Tick> FrameStream<T> {
+    /// The next frame, without requiring a `StreamExt`.
+    #[inline(always)]
+    pub async fn next(&mut self) -> Option<Result<Frame<T>, QuotickError>> {
+        self.rx.recv().await
+    }
+}
+
+impl<T: Tick> Stream for FrameStream<T> {
+    type Item = Result<Frame<T>, QuotickError>;
+
+    #[inline(always)]
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// Runs `f` on the blocking pool, resuming its panic, if any.
+async fn blocking<F, R>(
+    f: F,
+) -> R
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+{
+    match task::spawn_blocking(f).await {
+        Ok(res) => res,
+        Err(err) => panic::resume_unwind(err.into_panic()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::quotick::Quotick;
+    use super::super::test_util::TempPath;
+    use super::super::tick::Trade;
+    use super::*;
+
+    const DAY: u64 = 86_400_000_000_000;
+
+    #[tokio::test]
+    async fn inserts_persists_and_streams() {
+        let path = TempPath::new("async");
+
+        let quotick = AsyncQuotick::<Trade>::new("SYMBL", &*path).await.unwrap();
+
+        for day in 0..3 {
+            for hour in 0..2 {
+                quotick
+                    .insert(Frame::new(day * DAY + hour, Trade { size: day * 10 + hour, ..Trade::default() }))
+                    .await
+                    .unwrap();
+            }
+        }
+
+        quotick.persist().await.unwrap();
+
+        let mut stream = quotick.range(1..=2 * DAY);
+        let mut sizes = Vec::new();
+
+        while let Some(frame) = stream.next().await {
+            sizes.push(frame.unwrap().tick().size);
+        }
+
+        assert_eq!(sizes, vec![1, 10, 11, 20]);
+
+        // dropping a stream early stops its reader.
+        let mut stream = quotick.range(..);
+
+        assert_eq!(stream.next().await.unwrap().unwrap().time(), 0);
+
+        drop(stream);
+
+        assert_eq!(quotick.run_blocking(|quotick| quotick.epoch_index().len()).await, 3);
+        assert!(quotick.range(10 * DAY..).next().await.is_none());
+
+        quotick.close().await.unwrap();
+
+        let reopened = Quotick::<Trade>::new("SYMBL", &path).unwrap();
+
+        assert_eq!(reopened.newest_frame().unwrap().tick().size, 21);
+    }
+}
@@ -1,23 +1,37 @@
-use std::fs::{File, OpenOptions};
 use std::io;
-use std::io::{Read, Seek, SeekFrom, Write};
 use std::marker::PhantomData;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use miniz_oxide::deflate::compress_to_vec;
 use miniz_oxide::inflate::{decompress_to_vec, TINFLStatus};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use super::encryption::{self, KeyProvider, ENCRYPTED_MAGIC};
+use super::storage::{FsBackend, StorageBackend};
+
+const HEADER_MAGIC: &[u8] = b"QTBF";
+
 pub enum BackingFileError {
     External(Box<dyn std::error::Error>),
     IoError(io::Error),
     InflateError(TINFLStatus),
     BadData,
+    /// The file is encrypted with a key the provider does not know.
+    MissingKey(u32),
+    /// The file was tampered with or encrypted with a different key.
+    DecryptionFailed,
+    /// The file is not encrypted although the provider requires it, see
+    /// `KeyProvider::accepts_plaintext`.
+    Plaintext,
 }
 
 pub struct BackingFile<T> {
-    file: File,
+    backend: Arc<dyn StorageBackend>,
+    keys: Option<Arc<dyn KeyProvider>>,
+    path: PathBuf,
+    compression_level: u8,
     _phantom: PhantomData<T>,
 }
 
@@ -28,22 +42,60 @@ impl<T> BackingFile<T>
     pub fn new<P: AsRef<Path>>(
         path: P,
     ) -> Result<BackingFile<T>, BackingFileError> {
-        let file =
-            OpenOptions::new()
-                .read(true)
-                .write(true)
-                .create(true)
-                .open(path.as_ref())
-                .map_err(|err| BackingFileError::IoError(err))?;
+        Self::with_backend(
+            Arc::new(FsBackend),
+            None,
+            path,
+        )
+    }
+
+    /// Opens the file at `path` in `backend`, creating it if missing.
+    ///
+    /// With `keys`, the file is written encrypted with the current key of
+    /// the provider. Encrypted files can't be read without `keys`, and
+    /// plaintext files only with keys accepting them, see
+    /// `KeyProvider::accepts_plaintext`.
+    #[inline(always)]
+    pub fn with_backend<P: AsRef<Path>>(
+        backend: Arc<dyn StorageBackend>,
+        keys: Option<Arc<dyn KeyProvider>>,
+        path: P,
+    ) -> Result<BackingFile<T>, BackingFileError> {
+        let path = path.as_ref().to_path_buf();
+
+        backend
+            .open(&path)
+            .map_err(BackingFileError::IoError)?;
 
         Ok(
             BackingFile {
-                file,
+                backend,
+                keys,
+                path,
+                compression_level: 3,
                 _phantom: PhantomData,
             },
         )
     }
 
+    #[inline(always)]
+    pub fn set_compression_level(
+        &mut self,
+        compression_level: u8,
+    ) {
+        self.compression_level = compression_level;
+    }
+
+    #[inline(always)]
+    pub fn is_empty(
+        &self,
+    ) -> Result<bool, BackingFileError> {
+        self.backend
+            .len(&self.path)
+            .map(|len| len == 0)
+            .map_err(BackingFileError::IoError)
+    }
+
     #[inline(always)]
     pub fn read(
         &mut self,
@@ -55,31 +107,29 @@ impl<T> BackingFile<T>
     pub fn try_read(
         &mut self,
     ) -> Result<T, BackingFileError> {
-        self.file
-            .seek(
-                SeekFrom::Start(0),
-            )
-            .map_err(|err| BackingFileError::IoError(err))?;
-
-        let mut buf = Vec::new();
+        let (_, buf) = self.try_read_raw()?;
 
-        self.file
-            .read_to_end(
-                &mut buf,
-            )
-            .map_err(|err| BackingFileError::IoError(err))?;
+        bincode::deserialize::<T>(
+            &buf,
+        )
+            .map_err(|err| BackingFileError::External(err))
+    }
 
-        let decompressed_buf =
-            decompress_to_vec(
-                &buf,
-            )
-                .map_err(|err| BackingFileError::InflateError(err))?;
+    /// Reads the decompressed payload along with the version it was
+    /// written with. Files written before versioning was introduced
+    /// report version 0.
+    #[inline(always)]
+    pub fn try_read_raw(
+        &mut self,
+    ) -> Result<(u32, Vec<u8>), BackingFileError> {
+        let buf =
+            self.backend
+                .read(&self.path)
+                .map_err(BackingFileError::IoError)?;
 
-        Ok(
-            bincode::deserialize::<T>(
-                &decompressed_buf,
-            )
-                .map_err(|err| BackingFileError::External(err))?
+        decode_raw(
+            &buf,
+            self.keys.as_deref(),
         )
     }
 
@@ -88,30 +138,134 @@ impl<T> BackingFile<T>
         &mut self,
         item: &T,
     ) -> Result<(), BackingFileError> {
-        self.file
-            .seek(
-                SeekFrom::Start(0),
-            )
-            .map_err(|err| BackingFileError::IoError(err))?;
+        self.write_versioned(
+            item,
+            0,
+        )
+    }
 
-        let buf =
-            bincode::serialize(
+    #[inline(always)]
+    pub fn write_versioned(
+        &mut self,
+        item: &T,
+        version: u32,
+    ) -> Result<(), BackingFileError> {
+        let file_buf =
+            encode_versioned(
                 item,
+                version,
+                self.compression_level,
+                self.keys.as_deref(),
+            )?;
+
+        // replaced rather than written in place, so readers, i.e. backups,
+        // never see a partial write.
+        self.backend
+            .replace(
+                &self.path,
+                &file_buf,
             )
-                .map_err(|err| BackingFileError::External(err))?;
+            .map_err(BackingFileError::IoError)
+    }
+}
+
+/// Encodes `item` the way `BackingFile::write_versioned` stores it, i.e.
+/// to be written by other means, see `decode_raw`.
+pub fn encode_versioned<T: Serialize>(
+    item: &T,
+    version: u32,
+    compression_level: u8,
+    keys: Option<&dyn KeyProvider>,
+) -> Result<Vec<u8>, BackingFileError> {
+    let buf =
+        bincode::serialize(
+            item,
+        )
+            .map_err(|err| BackingFileError::External(err))?;
+
+    encode_raw(
+        &buf,
+        version,
+        compression_level,
+        keys,
+    )
+}
+
+/// Compresses `payload` and, with `keys`, encrypts the result.
+pub fn encode_raw(
+    payload: &[u8],
+    version: u32,
+    compression_level: u8,
+    keys: Option<&dyn KeyProvider>,
+) -> Result<Vec<u8>, BackingFileError> {
+    let compressed_buf =
+        compress_to_vec(
+            payload,
+            compression_level,
+        );
+
+    if let Some(keys) = keys {
+        return encryption::encrypt(
+            keys,
+            version,
+            &compressed_buf,
+        );
+    }
+
+    let mut file_buf =
+        Vec::with_capacity(
+            HEADER_MAGIC.len() + 4 + compressed_buf.len(),
+        );
+
+    file_buf.extend_from_slice(HEADER_MAGIC);
+    file_buf.extend_from_slice(&version.to_le_bytes());
+    file_buf.extend_from_slice(&compressed_buf);
+
+    Ok(file_buf)
+}
 
-        let compressed_buf =
-            compress_to_vec(
-                &buf,
-                3,
-            );
+/// Decodes the contents of a backing file read by other means, i.e. from
+/// a pack, see `BackingFile::try_read_raw`.
+///
+/// With `keys`, plaintext contents are rejected unless the provider
+/// accepts them, so a file swapped for an unencrypted one is not read.
+pub fn decode_raw(
+    buf: &[u8],
+    keys: Option<&dyn KeyProvider>,
+) -> Result<(u32, Vec<u8>), BackingFileError> {
+    if buf.starts_with(ENCRYPTED_MAGIC) {
+        let (version, compressed_buf) = encryption::decrypt(keys, buf)?;
 
-        self.file
-            .write(
+        let decompressed_buf =
+            decompress_to_vec(
                 &compressed_buf,
             )
-            .map_err(|err| BackingFileError::IoError(err))?;
+                .map_err(BackingFileError::InflateError)?;
 
-        Ok(())
+        return Ok((version, decompressed_buf));
     }
+
+    if keys.is_some_and(|keys| !keys.accepts_plaintext()) {
+        return Err(BackingFileError::Plaintext);
+    }
+
+    let (version, payload) =
+        match buf.strip_prefix(HEADER_MAGIC) {
+            Some(rest) if rest.len() >= 4 => {
+                let mut version = [0u8; 4];
+
+                version.copy_from_slice(&rest[..4]);
+
+                (u32::from_le_bytes(version), &rest[4..])
+            }
+            _ => (0, buf),
+        };
+
+    let decompressed_buf =
+        decompress_to_vec(
+            payload,
+        )
+            .map_err(BackingFileError::InflateError)?;
+
+    Ok((version, decompressed_buf))
 }
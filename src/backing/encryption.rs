@@ -0,0 +1,292 @@
+use std::convert::TryInto;
+use std::fmt;
+use std::fmt::Debug;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+
+use super::backing_file::BackingFileError;
+
+pub(crate) const ENCRYPTED_MAGIC: &[u8] = b"QTBE";
+
+/// magic, version, key id.
+const HEADER_LEN: usize = 12;
+
+const NONCE_LEN: usize = 24;
+
+/// 256 bit key of `XChaCha20Poly1305`.
+pub type EncryptionKey = [u8; 32];
+
+/// Supplies the keys backing files are encrypted with, see
+/// `QuotickOptions::encryption`.
+///
+/// Every encrypted file records the id of its key, so keys can be rotated
+/// by changing `current_key_id` while still providing the old keys.
+pub trait KeyProvider: Debug + Send + Sync {
+    /// Id of the key new files are encrypted with.
+    fn current_key_id(&self) -> u32;
+
+    /// The key with `id`, if known.
+    fn key(
+        &self,
+        id: u32,
+    ) -> Option<EncryptionKey>;
+
+    /// Whether plaintext files, written before encryption was enabled,
+    /// may still be read. Off by default, as they are not authenticated;
+    /// wrap the provider in `AcceptPlaintext` while migrating a symbol.
+    #[inline(always)]
+    fn accepts_plaintext(&self) -> bool {
+        false
+    }
+}
+
+/// Reads plaintext files along with encrypted ones, to migrate symbols
+/// written before encryption was enabled. Rewritten files are encrypted
+/// with `K`; once every file is, drop the wrapper.
+#[derive(Debug, Clone)]
+pub struct AcceptPlaintext<K>(pub K);
+
+impl<K: KeyProvider> KeyProvider for AcceptPlaintext<K> {
+    #[inline(always)]
+    fn current_key_id(&self) -> u32 {
+        self.0.current_key_id()
+    }
+
+    #[inline(always)]
+    fn key(
+        &self,
+        id: u32,
+    ) -> Option<EncryptionKey> {
+        self.0.key(id)
+    }
+
+    #[inline(always)]
+    fn accepts_plaintext(&self) -> bool {
+        true
+    }
+}
+
+/// A single key known up front, i.e. for tests and local use.
+#[derive(Clone)]
+pub struct StaticKey {
+    id: u32,
+    key: EncryptionKey,
+}
+
+impl StaticKey {
+    #[inline(always)]
+    pub fn new(
+        key: EncryptionKey,
+    ) -> StaticKey {
+        StaticKey::with_id(0, key)
+    }
+
+    #[inline(always)]
+    pub fn with_id(
+        id: u32,
+        key: EncryptionKey,
+    ) -> StaticKey {
+        StaticKey {
+            id,
+            key,
+        }
+    }
+}
+
+impl Debug for StaticKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // never print the key itself.
+        f.debug_struct("StaticKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KeyProvider for StaticKey {
+    #[inline(always)]
+    fn current_key_id(&self) -> u32 {
+        self.id
+    }
+
+    #[inline(always)]
+    fn key(
+        &self,
+        id: u32,
+    ) -> Option<EncryptionKey> {
+        match id == self.id {
+            true => Some(self.key),
+            false => None,
+        }
+    }
+}
+
+/// Encrypts the compressed payload of a backing file.
+///
+/// The header, made of the magic, `version` and the key id, is
+/// authenticated along with the payload and followed by a random nonce
+/// and the ciphertext.
+pub(crate) fn encrypt(
+    keys: &dyn KeyProvider,
+    version: u32,
+    compressed: &[u8],
+) -> Result<Vec<u8>, BackingFileError> {
+    let key_id = keys.current_key_id();
+
+    let key =
+        keys.key(key_id)
+            .ok_or(BackingFileError::MissingKey(key_id))?;
+
+    let mut nonce = [0u8; NONCE_LEN];
+
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut buf = Vec::with_capacity(HEADER_LEN + NONCE_LEN + compressed.len() + 16);
+
+    buf.extend_from_slice(ENCRYPTED_MAGIC);
+    buf.extend_from_slice(&version.to_le_bytes());
+    buf.extend_from_slice(&key_id.to_le_bytes());
+
+    let ciphertext =
+        XChaCha20Poly1305::new(&key.into())
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: compressed,
+                    aad: &buf,
+                },
+            )
+            .map_err(|_| BackingFileError::DecryptionFailed)?;
+
+    buf.extend_from_slice(&nonce);
+    buf.extend_from_slice(&ciphertext);
+
+    Ok(buf)
+}
+
+/// Decrypts a file written by `encrypt`, returning its version and the
+/// compressed payload.
+pub(crate) fn decrypt(
+    keys: Option<&dyn KeyProvider>,
+    buf: &[u8],
+) -> Result<(u32, Vec<u8>), BackingFileError> {
+    if buf.len() < HEADER_LEN + NONCE_LEN {
+        return Err(BackingFileError::BadData);
+    }
+
+    let (header, rest) = buf.split_at(HEADER_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    let key_id = u32::from_le_bytes(header[8..12].try_into().unwrap());
+
+    let key =
+        keys.and_then(|keys| keys.key(key_id))
+            .ok_or(BackingFileError::MissingKey(key_id))?;
+
+    let compressed =
+        XChaCha20Poly1305::new(&key.into())
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| BackingFileError::DecryptionFailed)?;
+
+    Ok((version, compressed))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use super::super::super::options::QuotickOptions;
+    use super::super::super::quotick::Quotick;
+    use super::super::super::tick::Trade;
+    use super::super::super::Frame;
+    use super::super::backing_file::{decode_raw, encode_versioned};
+    use super::super::storage::{MemoryBackend, StorageBackend};
+    use super::*;
+
+    #[test]
+    fn authenticates_and_rotates_keys() {
+        let old = StaticKey::with_id(1, [1; 32]);
+        let new = StaticKey::with_id(2, [2; 32]);
+
+        let buf = encode_versioned(&vec![1u64, 2, 3], 7, 3, Some(&old)).ok().unwrap();
+
+        assert_eq!(&buf[..4], ENCRYPTED_MAGIC);
+        assert!(matches!(decode_raw(&buf, None), Err(BackingFileError::MissingKey(1))));
+        assert!(matches!(decode_raw(&buf, Some(&new)), Err(BackingFileError::MissingKey(1))));
+
+        let (version, payload) = decode_raw(&buf, Some(&old)).ok().unwrap();
+
+        assert_eq!(version, 7);
+        assert_eq!(bincode::deserialize::<Vec<u64>>(&payload).unwrap(), vec![1, 2, 3]);
+
+        // the header is authenticated, too.
+        for idx in [5, buf.len() - 1] {
+            let mut tampered = buf.clone();
+
+            tampered[idx] ^= 1;
+
+            assert!(matches!(decode_raw(&tampered, Some(&old)), Err(BackingFileError::DecryptionFailed)));
+        }
+
+        assert!(matches!(decode_raw(&buf, Some(&StaticKey::with_id(1, [9; 32]))), Err(BackingFileError::DecryptionFailed)));
+
+        // plaintext, with or without a header, only with keys accepting it.
+        let plain = encode_versioned(&vec![1u64, 2, 3], 7, 3, None).ok().unwrap();
+        let legacy = plain[8..].to_vec();
+
+        for buf in [&plain, &legacy] {
+            assert!(matches!(decode_raw(buf, Some(&old)), Err(BackingFileError::Plaintext)));
+            assert!(decode_raw(buf, Some(&AcceptPlaintext(old.clone()))).is_ok());
+            assert!(decode_raw(buf, None).is_ok());
+        }
+    }
+
+    #[test]
+    fn encrypts_symbols_at_rest() {
+        let backend = MemoryBackend::new();
+        let base_path = Path::new("/quotick-encryption");
+
+        let options =
+            |keys: Option<StaticKey>| {
+                QuotickOptions {
+                    backend: Arc::new(backend.clone()),
+                    encryption: keys.map(|keys| Arc::new(keys) as Arc<dyn KeyProvider>),
+                    ..QuotickOptions::default()
+                }
+            };
+
+        let mut quotick = Quotick::<Trade>::with_options("SYMBL", base_path, options(Some(StaticKey::new([7; 32])))).unwrap();
+
+        quotick
+            .insert(&Frame::new(1, Trade { size: 42, ..Trade::default() }))
+            .unwrap();
+
+        quotick.persist().unwrap();
+
+        let path_builder = quotick.path_builder().clone();
+
+        drop(quotick);
+
+        for path in [path_builder.index_backing_file(0), path_builder.epoch_index_backing_file(), path_builder.meta_backing_file()] {
+            assert_eq!(&backend.read(&path).unwrap()[..4], ENCRYPTED_MAGIC);
+        }
+
+        let quotick = Quotick::<Trade>::with_options("SYMBL", base_path, options(Some(StaticKey::new([7; 32])))).unwrap();
+
+        assert_eq!(quotick.newest_frame().unwrap().tick().size, 42);
+
+        drop(quotick);
+
+        assert!(Quotick::<Trade>::with_options("SYMBL", base_path, options(None)).is_err());
+        assert!(Quotick::<Trade>::with_options("SYMBL", base_path, options(Some(StaticKey::new([8; 32])))).is_err());
+    }
+}
@@ -1 +1,3 @@
 pub mod backing_file;
+pub mod encryption;
+pub mod storage;
@@ -0,0 +1,729 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::fs::{self, File};
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+use serde_derive::{Deserialize, Serialize};
+
+/// Where `BackingFile`s, epoch files and packs are stored.
+///
+/// Paths are those laid out by `QuotickPathBuilder`; a backend is free to
+/// treat them as plain keys. Set through `QuotickOptions::backend`, which
+/// defaults to `FsBackend`. Zero-copy archives are memory mapped and
+/// therefore only exist for backends that are `is_local`, see
+/// `ArchivedEpoch`.
+pub trait StorageBackend: Debug + Send + Sync {
+    /// Creates an empty file at `path` unless one exists.
+    fn open(
+        &self,
+        path: &Path,
+    ) -> io::Result<()>;
+
+    /// Reads the whole file at `path`.
+    fn read(
+        &self,
+        path: &Path,
+    ) -> io::Result<Vec<u8>>;
+
+    /// Reads `len` bytes at `offset` of the file at `path`.
+    fn read_at(
+        &self,
+        path: &Path,
+        offset: u64,
+        len: usize,
+    ) -> io::Result<Vec<u8>> {
+        let buf = self.read(path)?;
+
+        let end =
+            (offset as usize)
+                .checked_add(len)
+                .filter(|&end| end <= buf.len())
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+
+        Ok(buf[offset as usize..end].to_vec())
+    }
+
+    /// Creates or overwrites the file at `path` in place.
+    fn write(
+        &self,
+        path: &Path,
+        data: &[u8],
+    ) -> io::Result<()>;
+
+    /// Creates or replaces the file at `path`, such that readers see
+    /// either the old or the new contents, never a mix.
+    fn replace(
+        &self,
+        path: &Path,
+        data: &[u8],
+    ) -> io::Result<()>;
+
+    /// Appends `data` to the file at `path`, creating it if missing.
+    fn append(
+        &self,
+        path: &Path,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let mut buf =
+            match self.read(path) {
+                Ok(buf) => buf,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+                Err(err) => return Err(err),
+            };
+
+        buf.extend_from_slice(data);
+
+        self.write(path, &buf)
+    }
+
+    /// Moves the file at `src` to `dst` like `replace`, i.e. once it was
+    /// written with `append`.
+    fn rename(
+        &self,
+        src: &Path,
+        dst: &Path,
+    ) -> io::Result<()> {
+        self.replace(dst, &self.read(src)?)?;
+        self.delete(src)
+    }
+
+    /// Makes `dst` refer to the contents of `src` without copying them,
+    /// i.e. a hard link, replacing any file at `dst`. Backends that cannot
+    /// link fail with `io::ErrorKind::Unsupported` and callers copy
+    /// instead.
+    ///
+    /// Linked files must only be replaced, never written in place.
+    fn link(
+        &self,
+        _src: &Path,
+        _dst: &Path,
+    ) -> io::Result<()> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+
+    /// Size of the file at `path`.
+    fn len(
+        &self,
+        path: &Path,
+    ) -> io::Result<u64>;
+
+    fn is_file(
+        &self,
+        path: &Path,
+    ) -> bool;
+
+    fn is_dir(
+        &self,
+        path: &Path,
+    ) -> bool;
+
+    fn create_dir_all(
+        &self,
+        path: &Path,
+    ) -> io::Result<()>;
+
+    /// Paths of the files and directories directly within `dir`, sorted.
+    fn list(
+        &self,
+        dir: &Path,
+    ) -> io::Result<Vec<PathBuf>>;
+
+    /// Removes the file, or the directory and everything below it, at
+    /// `path`.
+    fn delete(
+        &self,
+        path: &Path,
+    ) -> io::Result<()>;
+
+    /// Identifies the current contents of the file at `path` without
+    /// reading them: a file written or replaced since has another stamp.
+    /// `None` where the backend can't tell, so callers read the file.
+    fn stamp(
+        &self,
+        _path: &Path,
+    ) -> io::Result<Option<FileStamp>> {
+        Ok(None)
+    }
+
+    /// Whether paths are files on the local filesystem, which zero-copy
+    /// archives require.
+    #[inline(always)]
+    fn is_local(&self) -> bool {
+        false
+    }
+}
+
+/// Identifies the contents of a file, see `StorageBackend::stamp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FileStamp {
+    /// Nanoseconds since the unix epoch for `FsBackend`.
+    pub modified: u64,
+    /// The inode for `FsBackend`, which changes as files are only ever
+    /// replaced, and the count of writes for `MemoryBackend`.
+    pub id: u64,
+}
+
+/// Stores everything on the local filesystem.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsBackend;
+
+impl StorageBackend for FsBackend {
+    #[inline(always)]
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    #[inline(always)]
+    fn open(
+        &self,
+        path: &Path,
+    ) -> io::Result<()> {
+        fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map(|_| ())
+    }
+
+    #[inline(always)]
+    fn read(
+        &self,
+        path: &Path,
+    ) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    #[inline(always)]
+    fn read_at(
+        &self,
+        path: &Path,
+        offset: u64,
+        len: usize,
+    ) -> io::Result<Vec<u8>> {
+        let mut file = File::open(path)?;
+        let mut buf = vec![0u8; len];
+
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buf)?;
+
+        Ok(buf)
+    }
+
+    #[inline(always)]
+    fn write(
+        &self,
+        path: &Path,
+        data: &[u8],
+    ) -> io::Result<()> {
+        fs::write(path, data)
+    }
+
+    #[inline(always)]
+    fn replace(
+        &self,
+        path: &Path,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let mut tmp_path = path.as_os_str().to_owned();
+
+        tmp_path.push(".tmp");
+
+        {
+            let mut file = File::create(&tmp_path)?;
+
+            file.write_all(data)?;
+            file.sync_all()?;
+        }
+
+        fs::rename(&tmp_path, path)
+    }
+
+    #[inline(always)]
+    fn append(
+        &self,
+        path: &Path,
+        data: &[u8],
+    ) -> io::Result<()> {
+        fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)?
+            .write_all(data)
+    }
+
+    #[inline(always)]
+    fn rename(
+        &self,
+        src: &Path,
+        dst: &Path,
+    ) -> io::Result<()> {
+        File::open(src)?.sync_all()?;
+
+        fs::rename(src, dst)
+    }
+
+    #[inline(always)]
+    fn link(
+        &self,
+        src: &Path,
+        dst: &Path,
+    ) -> io::Result<()> {
+        if dst.is_file() {
+            fs::remove_file(dst)?;
+        }
+
+        fs::hard_link(src, dst)
+    }
+
+    #[inline(always)]
+    fn len(
+        &self,
+        path: &Path,
+    ) -> io::Result<u64> {
+        fs::metadata(path)
+            .map(|metadata| metadata.len())
+    }
+
+    #[cfg(unix)]
+    #[inline(always)]
+    fn stamp(
+        &self,
+        path: &Path,
+    ) -> io::Result<Option<FileStamp>> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = fs::metadata(path)?;
+
+        let modified =
+            metadata.modified()?
+                .duration_since(UNIX_EPOCH)
+                .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?
+                .as_nanos();
+
+        Ok(
+            Some(
+                FileStamp {
+                    modified: u64::try_from(modified).unwrap_or(u64::MAX),
+                    id: metadata.ino(),
+                },
+            ),
+        )
+    }
+
+    #[inline(always)]
+    fn is_file(
+        &self,
+        path: &Path,
+    ) -> bool {
+        path.is_file()
+    }
+
+    #[inline(always)]
+    fn is_dir(
+        &self,
+        path: &Path,
+    ) -> bool {
+        path.is_dir()
+    }
+
+    #[inline(always)]
+    fn create_dir_all(
+        &self,
+        path: &Path,
+    ) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    #[inline(always)]
+    fn list(
+        &self,
+        dir: &Path,
+    ) -> io::Result<Vec<PathBuf>> {
+        let mut paths =
+            fs::read_dir(dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+
+        paths.sort();
+
+        Ok(paths)
+    }
+
+    #[inline(always)]
+    fn delete(
+        &self,
+        path: &Path,
+    ) -> io::Result<()> {
+        if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        }
+    }
+}
+
+/// Keeps everything in memory, i.e. for tests and short-lived
+/// simulations. Clones share their contents.
+///
+/// Writing a file implicitly creates its parent directories.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBackend {
+    inner: Arc<Mutex<MemoryStorage>>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryStorage {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+    dirs: BTreeSet<PathBuf>,
+    /// Count of writes at the last write of each file, see `stamp`.
+    stamps: BTreeMap<PathBuf, u64>,
+    writes: u64,
+}
+
+impl MemoryStorage {
+    #[inline(always)]
+    fn touch(
+        &mut self,
+        path: &Path,
+    ) {
+        self.writes += 1;
+        self.stamps.insert(path.to_path_buf(), self.writes);
+    }
+
+    #[inline(always)]
+    fn add_parents(
+        &mut self,
+        path: &Path,
+    ) {
+        for dir in path.ancestors().skip(1) {
+            if dir.as_os_str().is_empty() || !self.dirs.insert(dir.to_path_buf()) {
+                break;
+            }
+        }
+    }
+}
+
+impl MemoryBackend {
+    #[inline(always)]
+    pub fn new() -> MemoryBackend {
+        MemoryBackend::default()
+    }
+
+    /// Total size of all files.
+    #[inline(always)]
+    pub fn size(&self) -> u64 {
+        self.storage()
+            .files
+            .values()
+            .map(|file| file.len() as u64)
+            .sum()
+    }
+
+    #[inline(always)]
+    fn storage(&self) -> std::sync::MutexGuard<'_, MemoryStorage> {
+        // the storage is consistent after every operation, so a panic
+        // elsewhere does not poison it.
+        self.inner
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[inline(always)]
+fn not_found() -> io::Error {
+    io::Error::from(io::ErrorKind::NotFound)
+}
+
+impl StorageBackend for MemoryBackend {
+    #[inline(always)]
+    fn open(
+        &self,
+        path: &Path,
+    ) -> io::Result<()> {
+        let mut storage = self.storage();
+
+        if !storage.files.contains_key(path) {
+            storage.add_parents(path);
+            storage.files.insert(path.to_path_buf(), Vec::new());
+            storage.touch(path);
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn read(
+        &self,
+        path: &Path,
+    ) -> io::Result<Vec<u8>> {
+        self.storage()
+            .files
+            .get(path)
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    #[inline(always)]
+    fn write(
+        &self,
+        path: &Path,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let mut storage = self.storage();
+
+        storage.add_parents(path);
+        storage.files.insert(path.to_path_buf(), data.to_vec());
+        storage.touch(path);
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn replace(
+        &self,
+        path: &Path,
+        data: &[u8],
+    ) -> io::Result<()> {
+        // writes happen under the lock, so they are atomic already.
+        self.write(path, data)
+    }
+
+    #[inline(always)]
+    fn append(
+        &self,
+        path: &Path,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let mut storage = self.storage();
+
+        storage.add_parents(path);
+        storage.files
+            .entry(path.to_path_buf())
+            .or_default()
+            .extend_from_slice(data);
+
+        storage.touch(path);
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn rename(
+        &self,
+        src: &Path,
+        dst: &Path,
+    ) -> io::Result<()> {
+        let mut storage = self.storage();
+
+        let data =
+            storage.files
+                .remove(src)
+                .ok_or_else(not_found)?;
+
+        storage.add_parents(dst);
+        storage.files.insert(dst.to_path_buf(), data);
+
+        if let Some(stamp) = storage.stamps.remove(src) {
+            storage.stamps.insert(dst.to_path_buf(), stamp);
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn len(
+        &self,
+        path: &Path,
+    ) -> io::Result<u64> {
+        self.storage()
+            .files
+            .get(path)
+            .map(|file| file.len() as u64)
+            .ok_or_else(not_found)
+    }
+
+    #[inline(always)]
+    fn stamp(
+        &self,
+        path: &Path,
+    ) -> io::Result<Option<FileStamp>> {
+        let storage = self.storage();
+
+        storage.stamps
+            .get(path)
+            .filter(|_| storage.files.contains_key(path))
+            .map(|&writes| Some(FileStamp { modified: 0, id: writes }))
+            .ok_or_else(not_found)
+    }
+
+    #[inline(always)]
+    fn is_file(
+        &self,
+        path: &Path,
+    ) -> bool {
+        self.storage()
+            .files
+            .contains_key(path)
+    }
+
+    #[inline(always)]
+    fn is_dir(
+        &self,
+        path: &Path,
+    ) -> bool {
+        self.storage()
+            .dirs
+            .contains(path)
+    }
+
+    #[inline(always)]
+    fn create_dir_all(
+        &self,
+        path: &Path,
+    ) -> io::Result<()> {
+        let mut storage = self.storage();
+
+        if storage.files.contains_key(path) {
+            return Err(io::Error::from(io::ErrorKind::AlreadyExists));
+        }
+
+        storage.add_parents(path);
+        storage.dirs.insert(path.to_path_buf());
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn list(
+        &self,
+        dir: &Path,
+    ) -> io::Result<Vec<PathBuf>> {
+        let storage = self.storage();
+
+        if !storage.dirs.contains(dir) {
+            return Err(not_found());
+        }
+
+        let is_child = |path: &&PathBuf| path.parent() == Some(dir);
+
+        let mut paths =
+            storage.files
+                .keys()
+                .filter(is_child)
+                .chain(storage.dirs.iter().filter(is_child))
+                .cloned()
+                .collect::<Vec<_>>();
+
+        paths.sort();
+
+        Ok(paths)
+    }
+
+    #[inline(always)]
+    fn delete(
+        &self,
+        path: &Path,
+    ) -> io::Result<()> {
+        let mut storage = self.storage();
+
+        if storage.files.remove(path).is_some() {
+            storage.stamps.remove(path);
+
+            return Ok(());
+        }
+
+        if !storage.dirs.contains(path) {
+            return Err(not_found());
+        }
+
+        storage.files.retain(|file, _| !file.starts_with(path));
+        storage.stamps.retain(|file, _| !file.starts_with(path));
+        storage.dirs.retain(|dir| !dir.starts_with(path));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::db::QuotickDb;
+    use super::super::super::options::QuotickOptions;
+    use super::super::super::tick::Trade;
+    use super::super::super::Frame;
+    use super::*;
+
+    #[test]
+    fn memory_backend_never_touches_disk() {
+        let backend = MemoryBackend::new();
+        let base_path = Path::new("/quotick-memory-backend");
+
+        let options =
+            QuotickOptions {
+                backend: Arc::new(backend.clone()),
+                ..QuotickOptions::default()
+            };
+
+        let db = QuotickDb::with_options(base_path, options).unwrap();
+
+        let mut quotick = db.create::<Trade>("SYMBL").unwrap();
+
+        for time in [30, 10, 20] {
+            quotick
+                .insert(&Frame::new(time, Trade { size: time, ..Trade::default() }))
+                .unwrap();
+        }
+
+        quotick.persist().unwrap();
+        quotick.compact_all().unwrap();
+
+        drop(quotick);
+
+        assert!(!base_path.exists());
+        assert!(backend.size() > 0);
+
+        let quotick = db.open::<Trade>("SYMBL").unwrap();
+
+        let mut sizes = Vec::new();
+
+        quotick.for_each_frame(|frame| sizes.push(frame.tick().size)).unwrap();
+
+        assert_eq!(sizes, vec![10, 20, 30]);
+        assert_eq!(db.symbols().unwrap().len(), 1);
+        assert!(db.stats("SYMBL").unwrap().bytes > 0);
+
+        drop(quotick);
+
+        db.drop_symbol("SYMBL").unwrap();
+
+        assert!(db.symbols().unwrap().is_empty());
+    }
+
+    #[test]
+    fn memory_backend_lists_and_deletes() {
+        let backend = MemoryBackend::new();
+
+        backend.write(Path::new("/a/b/c"), b"abc").unwrap();
+        backend.create_dir_all(Path::new("/a/d")).unwrap();
+
+        assert_eq!(backend.list(Path::new("/a")).unwrap(), vec![PathBuf::from("/a/b"), PathBuf::from("/a/d")]);
+        assert_eq!(backend.read_at(Path::new("/a/b/c"), 1, 2).unwrap(), b"bc".to_vec());
+        assert!(backend.read_at(Path::new("/a/b/c"), 2, 2).is_err());
+
+        backend.append(Path::new("/a/b/c"), b"de").unwrap();
+        backend.rename(Path::new("/a/b/c"), Path::new("/a/b/e")).unwrap();
+
+        assert!(!backend.is_file(Path::new("/a/b/c")));
+        assert_eq!(backend.read(Path::new("/a/b/e")).unwrap(), b"abcde".to_vec());
+
+        backend.delete(Path::new("/a/b")).unwrap();
+
+        assert!(!backend.is_file(Path::new("/a/b/c")));
+        assert!(backend.is_dir(Path::new("/a/d")));
+    }
+}
@@ -0,0 +1,591 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::backing::backing_file::{decode_raw, encode_versioned};
+use super::backing::storage::{FileStamp, StorageBackend};
+use super::epoch_index::EpochIndex;
+use super::path_builder::QuotickPathBuilder;
+use super::symbol::Symbol;
+use super::timestamp::Timestamp;
+
+/// Version of the `BackupManifest` layout in `manifest.qtf`.
+pub const BACKUP_MANIFEST_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.qtf";
+
+#[derive(Debug)]
+pub enum BackupError {
+    IoError(io::Error),
+    MissingManifest,
+    BadManifest,
+    UnsupportedVersion(u32),
+    /// A file of the backup does not match its manifest entry.
+    ChecksumMismatch(PathBuf),
+    /// The epoch index of a symbol does not decode.
+    BadIndex(PathBuf),
+}
+
+impl From<io::Error> for BackupError {
+    #[inline(always)]
+    fn from(err: io::Error) -> Self {
+        BackupError::IoError(err)
+    }
+}
+
+/// A file of a backup, keyed by its path relative to the backup in
+/// `BackupManifest::files`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupEntry {
+    pub len: u64,
+    /// CRC-32 of the contents.
+    pub checksum: u32,
+    /// Sealed epoch files are hard linked where the backend supports it.
+    pub sealed: bool,
+    /// Stamp of the file as it was read, see `StorageBackend::stamp`.
+    pub stamp: Option<FileStamp>,
+}
+
+impl BackupEntry {
+    #[inline(always)]
+    fn new(
+        bytes: &[u8],
+        sealed: bool,
+        stamp: Option<FileStamp>,
+    ) -> BackupEntry {
+        BackupEntry {
+            len: bytes.len() as u64,
+            checksum: crc32fast::hash(bytes),
+            sealed,
+            stamp,
+        }
+    }
+
+    /// Whether both entries describe the same contents.
+    #[inline(always)]
+    fn same_contents(
+        &self,
+        other: &BackupEntry,
+    ) -> bool {
+        self.len == other.len
+            && self.checksum == other.checksum
+            && self.sealed == other.sealed
+    }
+}
+
+/// The files of a backup, as stored in its `manifest.qtf`.
+///
+/// The backup mirrors the layout below the base path of the database,
+/// with every epoch in the hot tier. The manifest is written last; files
+/// replaced by a backup that did not finish fail `verify`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Nanoseconds since the unix epoch.
+    pub created: u64,
+    pub files: BTreeMap<PathBuf, BackupEntry>,
+}
+
+impl BackupManifest {
+    /// Reads the manifest of the backup at `root`, if any.
+    pub fn read(
+        backend: &dyn StorageBackend,
+        root: &Path,
+    ) -> Result<Option<BackupManifest>, BackupError> {
+        let path = root.join(MANIFEST_FILE);
+
+        if !backend.is_file(&path) {
+            return Ok(None);
+        }
+
+        let (version, buf) =
+            decode_raw(&backend.read(&path)?, None)
+                .map_err(|_| BackupError::BadManifest)?;
+
+        if version != BACKUP_MANIFEST_VERSION {
+            return Err(BackupError::UnsupportedVersion(version));
+        }
+
+        bincode::deserialize::<BackupManifest>(&buf)
+            .map(Some)
+            .map_err(|_| BackupError::BadManifest)
+    }
+
+    /// Atomically replaces the manifest of the backup at `root`.
+    pub fn write(
+        &self,
+        backend: &dyn StorageBackend,
+        root: &Path,
+    ) -> Result<(), BackupError> {
+        let buf =
+            encode_versioned(
+                self,
+                BACKUP_MANIFEST_VERSION,
+                3,
+                None,
+            )
+                .map_err(|_| BackupError::BadManifest)?;
+
+        backend.replace(&root.join(MANIFEST_FILE), &buf)?;
+
+        Ok(())
+    }
+
+    /// Symbols with files in the backup.
+    pub fn symbols(&self) -> BTreeSet<Symbol> {
+        self.files
+            .keys()
+            .filter_map(|path| path.iter().next()?.to_str())
+            .filter_map(|dir_name| Symbol::from_dir_name(dir_name).ok())
+            .collect()
+    }
+
+    /// Reads every file of the backup at `root` and checks it against its
+    /// entry.
+    pub fn verify(
+        &self,
+        backend: &dyn StorageBackend,
+        root: &Path,
+    ) -> Result<(), BackupError> {
+        for (path, entry) in &self.files {
+            let bytes =
+                backend.read(&root.join(path))
+                    .map_err(|_| BackupError::ChecksumMismatch(path.clone()))?;
+
+            if !BackupEntry::new(&bytes, entry.sealed, None).same_contents(entry) {
+                return Err(BackupError::ChecksumMismatch(path.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Outcome of `QuotickDb::backup` and `QuotickDb::restore`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BackupReport {
+    pub files_copied: usize,
+    pub files_linked: usize,
+    /// Files matching the previous manifest, which were left alone.
+    pub files_unchanged: usize,
+    pub bytes_copied: u64,
+}
+
+/// Backs up the symbols of `path_builders` to `dest`, copying only files
+/// that changed since the previous manifest there. Files are only ever
+/// replaced, never written in place, so each is read once and backed up
+/// as one complete write.
+///
+/// With `only`, entries of other symbols in the previous manifest are
+/// kept; otherwise files of symbols no longer backed up are removed.
+pub(crate) fn backup(
+    backend: &dyn StorageBackend,
+    path_builders: &[QuotickPathBuilder],
+    dest: &Path,
+    only: Option<&Symbol>,
+) -> Result<BackupReport, BackupError> {
+    backend.create_dir_all(dest)?;
+
+    let previous =
+        BackupManifest::read(backend, dest)?
+            .unwrap_or_default();
+
+    let mut backup =
+        Backup {
+            backend,
+            dest,
+            manifest: BackupManifest {
+                created: Timestamp::now().as_nanos(),
+                files: BTreeMap::new(),
+            },
+            previous,
+            report: BackupReport::default(),
+        };
+
+    if let Some(symbol) = only {
+        backup.manifest.files =
+            backup.previous.files
+                .iter()
+                .filter(|(path, _)| !path.starts_with(symbol.dir_name()))
+                .map(|(path, entry)| (path.clone(), *entry))
+                .collect();
+    }
+
+    for path_builder in path_builders {
+        backup.symbol(path_builder)?;
+    }
+
+    backup.manifest.write(backend, dest)?;
+
+    // files gone from the database since the previous backup.
+    for path in backup.previous.files.keys() {
+        if !backup.manifest.files.contains_key(path) {
+            let _ = backend.delete(&dest.join(path));
+        }
+    }
+
+    Ok(backup.report)
+}
+
+/// Writes the files of `symbol` in the backup at `src` below `base_path`.
+/// The caller verifies the backup and removes the files of the symbol
+/// beforehand.
+pub(crate) fn restore(
+    backend: &dyn StorageBackend,
+    manifest: &BackupManifest,
+    src: &Path,
+    base_path: &Path,
+    symbol: &Symbol,
+) -> Result<BackupReport, BackupError> {
+    let mut report = BackupReport::default();
+
+    let files =
+        manifest.files
+            .iter()
+            .filter(|(path, _)| path.starts_with(symbol.dir_name()));
+
+    for (path, entry) in files {
+        let src_path = src.join(path);
+        let dst_path = base_path.join(path);
+
+        if let Some(parent) = dst_path.parent() {
+            backend.create_dir_all(parent)?;
+        }
+
+        if entry.sealed && backend.link(&src_path, &dst_path).is_ok() {
+            report.files_linked += 1;
+            continue;
+        }
+
+        backend.replace(&dst_path, &backend.read(&src_path)?)?;
+
+        report.files_copied += 1;
+        report.bytes_copied += entry.len;
+    }
+
+    Ok(report)
+}
+
+struct Backup<'a> {
+    backend: &'a dyn StorageBackend,
+    dest: &'a Path,
+    previous: BackupManifest,
+    manifest: BackupManifest,
+    report: BackupReport,
+}
+
+impl<'a> Backup<'a> {
+    fn symbol(
+        &mut self,
+        path_builder: &QuotickPathBuilder,
+    ) -> Result<(), BackupError> {
+        let backend = self.backend;
+
+        // the index is read first and backed up as read, so it never
+        // lists epochs missing from the backup. Epochs created since are
+        // picked up by the next backup.
+        let index_path = path_builder.epoch_index_backing_file();
+        let index_stamp = backend.stamp(&index_path)?;
+        let index_bytes = backend.read(&index_path)?;
+
+        let index =
+            match index_bytes.is_empty() {
+                true => EpochIndex::default(),
+                false => {
+                    decode_raw(&index_bytes, path_builder.keys().map(Arc::as_ref))
+                        .ok()
+                        .and_then(|(version, buf)| EpochIndex::decode(version, &buf).ok())
+                        .ok_or_else(|| BackupError::BadIndex(index_path.clone()))?
+                }
+            };
+
+        for &epoch in &index.epochs {
+            let path = path_builder.index_backing_file(epoch);
+
+            // packed epochs are backed up along with their pack.
+            if backend.is_file(&path) {
+                self.file(
+                    path_builder,
+                    &path,
+                    &path_builder.hot_index_backing_file(epoch),
+                    index.is_sealed(epoch),
+                )?;
+            }
+        }
+
+        // packs are only ever replaced, never written in place.
+        let pack_path = path_builder.pack_path();
+
+        if backend.is_dir(&pack_path) {
+            for path in backend.list(&pack_path)? {
+                if path.extension().is_some_and(|extension| extension == "qtp") {
+                    self.file(path_builder, &path, &path, true)?;
+                }
+            }
+        }
+
+        let optional = [
+            path_builder.counters_backing_file(),
+            path_builder.retention_backing_file(),
+        ];
+
+        for path in optional.iter().filter(|path| backend.is_file(path)) {
+            self.file(path_builder, path, path, false)?;
+        }
+
+        let meta_path = path_builder.meta_backing_file();
+
+        self.file(path_builder, &meta_path, &meta_path, false)?;
+
+        let relative = self.relative(path_builder, &index_path);
+
+        let entry = BackupEntry::new(&index_bytes, false, index_stamp);
+
+        self.store(&index_path, relative, index_bytes, entry)
+    }
+
+    /// Backs up the file at `path`, which is stored in the backup like
+    /// `layout_path` is below the base path.
+    fn file(
+        &mut self,
+        path_builder: &QuotickPathBuilder,
+        path: &Path,
+        layout_path: &Path,
+        sealed: bool,
+    ) -> Result<(), BackupError> {
+        let relative = self.relative(path_builder, layout_path);
+
+        // taken before the file is read, so a file replaced in between
+        // is read again by the next backup.
+        let stamp = self.backend.stamp(path)?;
+
+        // a file with the stamp it had in the previous backup was not
+        // replaced since, so it is unchanged and not read.
+        let unchanged =
+            self.previous.files
+                .get(&relative)
+                .copied()
+                .filter(|previous| {
+                    stamp.is_some()
+                        && previous.stamp == stamp
+                        && previous.sealed == sealed
+                        && self.backend.is_file(&self.dest.join(&relative))
+                });
+
+        if let Some(entry) = unchanged {
+            self.report.files_unchanged += 1;
+            self.manifest.files.insert(relative, entry);
+
+            return Ok(());
+        }
+
+        let bytes = self.backend.read(path)?;
+        let entry = BackupEntry::new(&bytes, sealed, stamp);
+
+        self.store(path, relative, bytes, entry)
+    }
+
+    fn store(
+        &mut self,
+        path: &Path,
+        relative: PathBuf,
+        bytes: Vec<u8>,
+        mut entry: BackupEntry,
+    ) -> Result<(), BackupError> {
+        let dst_path = self.dest.join(&relative);
+
+        let unchanged =
+            self.previous.files
+                .get(&relative)
+                .is_some_and(|previous| previous.same_contents(&entry));
+
+        if unchanged && self.backend.is_file(&dst_path) {
+            self.report.files_unchanged += 1;
+            self.manifest.files.insert(relative, entry);
+
+            return Ok(());
+        }
+
+        if let Some(parent) = dst_path.parent() {
+            self.backend.create_dir_all(parent)?;
+        }
+
+        if entry.sealed && self.backend.link(path, &dst_path).is_ok() {
+            // the file may have been replaced since it was read, i.e. by
+            // `Quotick::unseal`, so the entry describes what was linked.
+            entry = BackupEntry::new(&self.backend.read(&dst_path)?, entry.sealed, entry.stamp);
+
+            self.report.files_linked += 1;
+        } else {
+            self.backend.replace(&dst_path, &bytes)?;
+
+            self.report.files_copied += 1;
+            self.report.bytes_copied += entry.len;
+        }
+
+        self.manifest.files.insert(relative, entry);
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn relative(
+        &self,
+        path_builder: &QuotickPathBuilder,
+        path: &Path,
+    ) -> PathBuf {
+        path.strip_prefix(path_builder.base_path())
+            .unwrap_or(path)
+            .to_path_buf()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::super::backing::storage::MemoryBackend;
+    use super::super::db::QuotickDb;
+    use super::super::options::QuotickOptions;
+    use super::super::test_util::{sizes, TempPath};
+    use super::super::tick::Trade;
+    use super::super::Frame;
+    use super::*;
+
+    const DAY: u64 = 86_400_000_000_000;
+
+    #[test]
+    fn backs_up_incrementally_and_restores() {
+        let base_path = TempPath::new("backup-src");
+        let dest = TempPath::new("backup-dest");
+        let restored_path = TempPath::new("backup-restored");
+
+        let db = QuotickDb::new(&base_path).unwrap();
+
+        let mut quotick = db.create::<Trade>("SYMBL").unwrap();
+
+        for day in 0..4 {
+            quotick
+                .insert(&Frame::new(day * DAY, Trade { size: day, ..Trade::default() }))
+                .unwrap();
+        }
+
+        quotick.seal(0).unwrap();
+        quotick.seal(1).unwrap();
+
+        let report = db.backup(&dest).unwrap();
+
+        assert_eq!(report.files_linked, 2);
+        assert_eq!(report.files_unchanged, 0);
+
+        // only the epoch written to since is copied again.
+        quotick
+            .insert(&Frame::new(3 * DAY + 1, Trade { size: 4, ..Trade::default() }))
+            .unwrap();
+
+        quotick.persist().unwrap();
+
+        let report = db.backup(&dest).unwrap();
+
+        assert_eq!(report.files_linked, 0);
+        assert_eq!(report.files_copied, 1);
+        assert!(report.files_unchanged >= 5);
+
+        // unsealing does not write through the hard link into the backup.
+        quotick.unseal(0).unwrap();
+
+        quotick
+            .insert(&Frame::new(1, Trade { size: 9, ..Trade::default() }))
+            .unwrap();
+
+        quotick.persist().unwrap();
+
+        let manifest = BackupManifest::read(&*db.options().backend, &dest).unwrap().unwrap();
+
+        manifest.verify(&*db.options().backend, &dest).unwrap();
+
+        assert_eq!(manifest.symbols().len(), 1);
+
+        drop(quotick);
+
+        let restored = QuotickDb::new(&restored_path).unwrap();
+
+        restored.restore(&dest).unwrap();
+
+        assert_eq!(sizes(&restored.open::<Trade>("SYMBL").unwrap()), vec![0, 1, 2, 3, 4]);
+
+        // corrupted backups are refused before anything is touched.
+        let path = dest.join(manifest.files.keys().last().unwrap());
+
+        fs::write(&path, b"corrupt").unwrap();
+
+        assert!(restored.restore(&dest).is_err());
+        assert_eq!(sizes(&restored.open::<Trade>("SYMBL").unwrap()), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn skips_reading_unchanged_sealed_files() {
+        let backend = MemoryBackend::new();
+
+        let options =
+            QuotickOptions {
+                backend: Arc::new(backend.clone()),
+                ..QuotickOptions::default()
+            };
+
+        let db = QuotickDb::with_options("/backup-sealed", options).unwrap();
+        let dest = Path::new("/backup-sealed-dest");
+
+        let mut quotick = db.create::<Trade>("SYMBL").unwrap();
+
+        for day in 0..2 {
+            quotick
+                .insert(&Frame::new(day * DAY, Trade { size: day + 1, ..Trade::default() }))
+                .unwrap();
+        }
+
+        quotick.seal(0).unwrap();
+
+        let path = quotick.path_builder().index_backing_file(0);
+
+        drop(quotick);
+
+        // the backend can't link, so sealed files are copied.
+        assert_eq!(db.backup(dest).unwrap().files_copied, 5);
+
+        // files not replaced since are taken as unchanged without reading
+        // them.
+        let report = db.backup(dest).unwrap();
+
+        assert_eq!(report.files_copied, 0);
+        assert_eq!(report.files_unchanged, 5);
+
+        // resealing replaces the file, even where its length stays the
+        // same, so it is read and copied again.
+        let len = backend.len(&path).unwrap();
+
+        let mut quotick = db.open::<Trade>("SYMBL").unwrap();
+
+        quotick.unseal(0).unwrap();
+        quotick.insert_opt(&Frame::new(0, Trade { size: 2, ..Trade::default() }), true).unwrap();
+        quotick.seal(0).unwrap();
+
+        drop(quotick);
+
+        assert_eq!(backend.len(&path).unwrap(), len);
+
+        let report = db.backup(dest).unwrap();
+
+        assert_eq!(report.files_copied, 2);
+
+        let manifest = BackupManifest::read(&backend, dest).unwrap().unwrap();
+
+        manifest.verify(&backend, dest).unwrap();
+
+        let entry = manifest.files.values().find(|entry| entry.sealed).unwrap();
+
+        assert_eq!(entry.checksum, crc32fast::hash(&backend.read(&path).unwrap()));
+    }
+}
@@ -0,0 +1,464 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+use chrono_tz::Tz;
+
+use super::epoch_rule::{local_to_nanos, nanos_to_datetime, EpochRule, SessionRule};
+
+#[derive(Debug)]
+pub enum CalendarError {
+    IoError(io::Error),
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// A required key, `timezone`, `open` or `close`, is not set.
+    MissingKey(&'static str),
+}
+
+impl From<io::Error> for CalendarError {
+    #[inline(always)]
+    fn from(err: io::Error) -> Self {
+        CalendarError::IoError(err)
+    }
+}
+
+/// A single trading session in nanosecond UTC timestamps, `[open, close)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Session {
+    pub date: NaiveDate,
+    pub open: u64,
+    pub close: u64,
+}
+
+impl Session {
+    #[inline(always)]
+    pub fn contains(
+        &self,
+        time: u64,
+    ) -> bool {
+        self.open <= time && time < self.close
+    }
+}
+
+/// Trading days and hours of an exchange.
+///
+/// Session times are in the local time of the exchange; conversions to
+/// and from the nanosecond UTC timestamps used by `Frame` follow the DST
+/// rules of `tz`. Sessions whose `open` is later than their `close` run
+/// overnight and belong to the date they close on.
+///
+/// Calendars can be loaded from a simple `key = value` file:
+///
+/// ```text
+/// # NYSE
+/// name = XNYS
+/// timezone = America/New_York
+/// open = 09:30
+/// close = 16:00
+/// weekend = sat, sun
+/// holiday = 2021-12-24
+/// half_day = 2021-11-26 13:00
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradingCalendar {
+    pub name: String,
+    pub tz: Tz,
+    pub open: NaiveTime,
+    pub close: NaiveTime,
+    pub weekend: Vec<Weekday>,
+    pub holidays: BTreeSet<NaiveDate>,
+    /// Early closes, by date.
+    pub half_days: BTreeMap<NaiveDate, NaiveTime>,
+}
+
+impl TradingCalendar {
+    #[inline(always)]
+    pub fn new(
+        name: &str,
+        tz: Tz,
+        open: NaiveTime,
+        close: NaiveTime,
+    ) -> TradingCalendar {
+        TradingCalendar {
+            name: name.to_string(),
+            tz,
+            open,
+            close,
+            weekend: vec![Weekday::Sat, Weekday::Sun],
+            holidays: BTreeSet::new(),
+            half_days: BTreeMap::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn load(
+        path: impl AsRef<Path>,
+    ) -> Result<TradingCalendar, CalendarError> {
+        TradingCalendar::parse(
+            &fs::read_to_string(path)?,
+        )
+    }
+
+    pub fn parse(
+        source: &str,
+    ) -> Result<TradingCalendar, CalendarError> {
+        let mut calendar =
+            TradingCalendar::new(
+                "",
+                Tz::UTC,
+                NaiveTime::MIN,
+                NaiveTime::MIN,
+            );
+
+        let mut has_tz = false;
+        let mut has_open = false;
+        let mut has_close = false;
+
+        for (idx, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let err =
+                |message: &str|
+                    CalendarError::Parse {
+                        line: idx + 1,
+                        message: message.to_string(),
+                    };
+
+            let mut parts = line.splitn(2, '=');
+
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next().ok_or_else(|| err("expected `key = value`"))?.trim();
+
+            let parse_time =
+                |time: &str|
+                    NaiveTime::parse_from_str(time, "%H:%M")
+                        .map_err(|_| err("expected a time as HH:MM"));
+
+            let parse_date =
+                |date: &str|
+                    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                        .map_err(|_| err("expected a date as YYYY-MM-DD"));
+
+            match key {
+                "name" => calendar.name = value.to_string(),
+                "timezone" => {
+                    calendar.tz = Tz::from_str(value).map_err(|_| err("unknown time zone"))?;
+                    has_tz = true;
+                }
+                "open" => {
+                    calendar.open = parse_time(value)?;
+                    has_open = true;
+                }
+                "close" => {
+                    calendar.close = parse_time(value)?;
+                    has_close = true;
+                }
+                "weekend" => {
+                    calendar.weekend =
+                        value
+                            .split(',')
+                            .map(|day| day.trim())
+                            .filter(|day| !day.is_empty())
+                            .map(|day| Weekday::from_str(day).map_err(|_| err("unknown weekday")))
+                            .collect::<Result<_, _>>()?;
+                }
+                "holiday" => {
+                    calendar.holidays.insert(parse_date(value)?);
+                }
+                "half_day" => {
+                    let mut parts = value.split_whitespace();
+
+                    let date = parse_date(parts.next().unwrap_or(""))?;
+                    let close = parse_time(parts.next().ok_or_else(|| err("expected a close time"))?)?;
+
+                    if parts.next().is_some() {
+                        return Err(err("expected `YYYY-MM-DD HH:MM`"));
+                    }
+
+                    calendar.half_days.insert(date, close);
+                }
+                _ => return Err(err("unknown key")),
+            }
+        }
+
+        if !has_tz {
+            return Err(CalendarError::MissingKey("timezone"));
+        }
+
+        if !has_open {
+            return Err(CalendarError::MissingKey("open"));
+        }
+
+        if !has_close {
+            return Err(CalendarError::MissingKey("close"));
+        }
+
+        Ok(calendar)
+    }
+
+    #[inline(always)]
+    pub fn is_overnight(&self) -> bool {
+        self.open > self.close
+    }
+
+    #[inline(always)]
+    pub fn is_trading_day(
+        &self,
+        date: NaiveDate,
+    ) -> bool {
+        !self.weekend.contains(&date.weekday())
+            && !self.holidays.contains(&date)
+    }
+
+    /// The session closing on `date`, if the exchange trades that day.
+    #[inline(always)]
+    pub fn session(
+        &self,
+        date: NaiveDate,
+    ) -> Option<Session> {
+        if !self.is_trading_day(date) {
+            return None;
+        }
+
+        let open_date =
+            if self.is_overnight() {
+                date - Duration::days(1)
+            } else {
+                date
+            };
+
+        let close =
+            self.half_days
+                .get(&date)
+                .copied()
+                .unwrap_or(self.close);
+
+        Some(
+            Session {
+                date,
+                open: self.local_to_utc(open_date.and_time(self.open)),
+                close: self.local_to_utc(date.and_time(close)),
+            },
+        )
+    }
+
+    /// The session the market is in at `time`, if it is open.
+    #[inline(always)]
+    pub fn session_at(
+        &self,
+        time: u64,
+    ) -> Option<Session> {
+        let date = self.session_rule().session_date(time);
+
+        self.session(date)
+            .filter(|session| session.contains(time))
+    }
+
+    #[inline(always)]
+    pub fn is_open(
+        &self,
+        time: u64,
+    ) -> bool {
+        self.session_at(time).is_some()
+    }
+
+    /// The first session closing after `time`, looking at most a year ahead.
+    #[inline(always)]
+    pub fn next_session(
+        &self,
+        time: u64,
+    ) -> Option<Session> {
+        let date = self.session_rule().session_date(time);
+
+        (0..=366)
+            .filter_map(|days| self.session(date + Duration::days(days)))
+            .find(|session| session.close > time)
+    }
+
+    /// All sessions overlapping `[start, end)`.
+    #[inline(always)]
+    pub fn sessions(
+        &self,
+        start: u64,
+        end: u64,
+    ) -> Vec<Session> {
+        let rule = self.session_rule();
+
+        let first = rule.session_date(start);
+        let last = rule.session_date(end);
+
+        let days = (last - first).num_days().max(0);
+
+        (0..=days)
+            .filter_map(|days| self.session(first + Duration::days(days)))
+            .filter(|session| session.open < end && session.close > start)
+            .collect()
+    }
+
+    /// Nanoseconds the market was open within `[start, end)`.
+    ///
+    /// Useful to tell whether a pause between two ticks is an actual gap in
+    /// the data or just the market being closed.
+    #[inline(always)]
+    pub fn open_nanos_between(
+        &self,
+        start: u64,
+        end: u64,
+    ) -> u64 {
+        self.sessions(start, end)
+            .iter()
+            .map(|session| session.close.min(end) - session.open.max(start))
+            .sum()
+    }
+
+    /// Converts exchange local time to nanoseconds since the unix epoch.
+    #[inline(always)]
+    pub fn local_to_utc(
+        &self,
+        local: NaiveDateTime,
+    ) -> u64 {
+        local_to_nanos(
+            self.tz,
+            local,
+        )
+    }
+
+    /// Converts nanoseconds since the unix epoch to exchange local time.
+    #[inline(always)]
+    pub fn utc_to_local(
+        &self,
+        time: u64,
+    ) -> NaiveDateTime {
+        self.tz
+            .from_utc_datetime(&nanos_to_datetime(time))
+            .naive_local()
+    }
+
+    #[inline(always)]
+    fn session_rule(&self) -> SessionRule {
+        SessionRule::new(
+            self.tz,
+            self.open,
+            self.close,
+        )
+    }
+
+    /// An epoch rule storing one session of this calendar per epoch.
+    #[inline(always)]
+    pub fn epoch_rule(&self) -> EpochRule {
+        EpochRule::Session(
+            self.session_rule(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::epoch_rule::datetime_to_nanos;
+    use super::*;
+
+    const XNYS: &str = "
+        # New York Stock Exchange
+        name = XNYS
+        timezone = America/New_York
+        open = 09:30
+        close = 16:00
+        weekend = sat, sun
+        holiday = 2021-11-25 # Thanksgiving
+        half_day = 2021-11-26 13:00
+    ";
+
+    fn utc(datetime: &str) -> u64 {
+        datetime_to_nanos(
+            NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M").unwrap(),
+        )
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn parses_calendar_files() {
+        let calendar = TradingCalendar::parse(XNYS).unwrap();
+
+        assert_eq!(calendar.name, "XNYS");
+        assert_eq!(calendar.tz, Tz::America__New_York);
+        assert!(calendar.holidays.contains(&date("2021-11-25")));
+
+        match TradingCalendar::parse("timezone = UTC\nopen = 9") {
+            Err(CalendarError::Parse { line: 2, .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+
+        assert!(matches!(TradingCalendar::parse("open = 09:30"), Err(CalendarError::MissingKey("timezone"))));
+        assert!(matches!(TradingCalendar::parse("timezone = UTC\nopen = 09:30"), Err(CalendarError::MissingKey("close"))));
+
+        match TradingCalendar::parse("timezone = UTC\nopen = 09:30\nclose = 16:00\nhalf_day = 2021-11-26 13:00 14:00") {
+            Err(CalendarError::Parse { line: 4, .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sessions_follow_dst_holidays_and_half_days() {
+        let calendar = TradingCalendar::parse(XNYS).unwrap();
+
+        // EDT until 2021-11-07, EST afterwards.
+        assert_eq!(calendar.session(date("2021-11-05")).unwrap().open, utc("2021-11-05 13:30"));
+        assert_eq!(calendar.session(date("2021-11-08")).unwrap().open, utc("2021-11-08 14:30"));
+
+        assert!(calendar.session(date("2021-11-25")).is_none());
+        assert!(calendar.session(date("2021-11-27")).is_none());
+        assert_eq!(calendar.session(date("2021-11-26")).unwrap().close, utc("2021-11-26 18:00"));
+
+        assert!(calendar.is_open(utc("2021-11-24 15:00")));
+        assert!(!calendar.is_open(utc("2021-11-25 15:00")));
+        assert!(!calendar.is_open(utc("2021-11-26 18:30")));
+
+        assert_eq!(
+            calendar.next_session(utc("2021-11-24 22:00")).unwrap().date,
+            date("2021-11-26"),
+        );
+
+        // Wednesday, half day Friday, Monday
+        assert_eq!(
+            calendar.open_nanos_between(utc("2021-11-24 00:00"), utc("2021-11-30 00:00")),
+            (6 * 60 + 30 + 3 * 60 + 30 + 6 * 60 + 30) * 60 * 1_000_000_000,
+        );
+    }
+
+    #[test]
+    fn overnight_sessions() {
+        let mut calendar =
+            TradingCalendar::new(
+                "CME",
+                Tz::America__Chicago,
+                NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+            );
+
+        calendar.weekend = vec![Weekday::Sat, Weekday::Sun];
+
+        // Sunday evening belongs to Monday's session.
+        let session = calendar.session_at(utc("2021-03-07 23:30")).unwrap();
+
+        assert_eq!(session.date, date("2021-03-08"));
+        assert_eq!(session.close, utc("2021-03-08 22:00"));
+        assert!(!calendar.is_open(utc("2021-03-08 22:30")));
+
+        assert_eq!(
+            calendar.epoch_rule().epoch(session.open),
+            calendar.epoch_rule().epoch(session.close - 1),
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use super::price::Price;
+use super::tick::{Bar, Conditions, ConsolidatedQuote, ConsolidatedTrade, Quote, Tape, Trade, Venue};
+use super::Tick;
+
+/// Receives the frames of a bulk read, see `Quotick::read_columns_into`.
+///
+/// Implement it to scatter ticks straight into your own buffers, i.e.
+/// an arrow builder or preallocated ndarrays.
+pub trait ColumnSink<T: Tick> {
+    /// Called with the number of frames of an epoch before they are pushed;
+    /// an upper bound if the epoch is only partially in range.
+    #[inline(always)]
+    fn reserve(&mut self, _additional: usize) {}
+
+    fn push(&mut self, time: u64, tick: &T);
+}
+
+impl<T: Tick, F: FnMut(u64, &T)> ColumnSink<T> for F {
+    #[inline(always)]
+    fn push(&mut self, time: u64, tick: &T) {
+        self(time, tick)
+    }
+}
+
+/// A tick type with a struct-of-arrays representation, returned by
+/// `Quotick::read_columns`.
+pub trait Columnar: Tick + Sized {
+    type Columns: ColumnSink<Self> + Default;
+}
+
+/// Declares a struct holding a `time` column and one column per (`Copy`)
+/// field, and implements `ColumnSink` and `Columnar` for it.
+macro_rules! columns {
+    (
+        $(#[$meta:meta])*
+        $name:ident for $tick:ty {
+            $($field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Default)]
+        pub struct $name {
+            pub time: Vec<u64>,
+            $(pub $field: Vec<$ty>,)*
+        }
+
+        impl $name {
+            #[inline(always)]
+            pub fn len(&self) -> usize {
+                self.time.len()
+            }
+
+            #[inline(always)]
+            pub fn is_empty(&self) -> bool {
+                self.time.is_empty()
+            }
+        }
+
+        impl ColumnSink<$tick> for $name {
+            #[inline(always)]
+            fn reserve(&mut self, additional: usize) {
+                self.time.reserve(additional);
+                $(self.$field.reserve(additional);)*
+            }
+
+            #[inline(always)]
+            fn push(&mut self, time: u64, tick: &$tick) {
+                self.time.push(time);
+                $(self.$field.push(tick.$field);)*
+            }
+        }
+
+        impl Columnar for $tick {
+            type Columns = $name;
+        }
+    };
+}
+
+columns! {
+    TradeColumns for Trade {
+        size: u64,
+        price: Price,
+    }
+}
+
+columns! {
+    QuoteColumns for Quote {
+        size: u64,
+        ask_price: Price,
+        bid_price: Price,
+    }
+}
+
+columns! {
+    ConsolidatedTradeColumns for ConsolidatedTrade {
+        id: u64,
+        size: u64,
+        price: Price,
+        venue: Venue,
+        conditions: Conditions,
+        tape: Tape,
+        participant_time: u64,
+        sip_time: u64,
+    }
+}
+
+columns! {
+    ConsolidatedQuoteColumns for ConsolidatedQuote {
+        bid_price: Price,
+        bid_size: u64,
+        bid_venue: Venue,
+        ask_price: Price,
+        ask_size: u64,
+        ask_venue: Venue,
+        conditions: Conditions,
+        tape: Tape,
+        participant_time: u64,
+        sip_time: u64,
+    }
+}
+
+columns! {
+    BarColumns for Bar {
+        open: Price,
+        high: Price,
+        low: Price,
+        close: Price,
+        volume: u64,
+        vwap: Price,
+        trades: u64,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::quotick::Quotick;
+    use super::super::test_util::TempPath;
+    use super::super::Frame;
+    use super::*;
+
+    #[test]
+    fn reads_ranges_as_columns() {
+        let base_path = TempPath::new("columns");
+        let mut quotick = Quotick::<Quote>::new("SYMBL", &base_path).unwrap();
+
+        let day = 86_400_000_000_000;
+
+        for idx in 0..6u64 {
+            quotick
+                .insert(
+                    &Frame::new(
+                        idx * day / 2,
+                        Quote {
+                            size: idx,
+                            ask_price: Price::from_int(idx as i64 + 1).unwrap(),
+                            bid_price: Price::from_int(idx as i64).unwrap(),
+                        },
+                    ),
+                )
+                .unwrap();
+        }
+
+        let quotes = quotick.read_columns(day / 2..2 * day).unwrap();
+
+        assert_eq!(quotes.time, vec![day / 2, day, 3 * day / 2]);
+        assert_eq!(quotes.size, vec![1, 2, 3]);
+        assert_eq!(
+            quotes.ask_price.iter().zip(quotes.bid_price.iter()).map(|(ask, bid)| *ask - *bid).sum::<Price>(),
+            Price::from_int(3).unwrap(),
+        );
+
+        assert_eq!(quotick.read_columns(..).unwrap().len(), 6);
+        assert_eq!(quotick.read_columns(2 * day..=2 * day).unwrap().size, vec![4]);
+        assert!(quotick.read_columns(..0).unwrap().is_empty());
+
+        let mut sizes = 0;
+
+        let count =
+            quotick
+                .read_columns_into(
+                    5 * day / 2..,
+                    &mut |_time: u64, quote: &Quote| sizes += quote.size,
+                )
+                .unwrap();
+
+        assert_eq!(count, 1);
+        assert_eq!(sizes, 5);
+    }
+}
@@ -0,0 +1,940 @@
+use std::io;
+use std::ops::RangeBounds;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::backing::backing_file::{decode_raw, encode_raw, BackingFile};
+use super::backing::storage::StorageBackend;
+use super::backup::{self, BackupError, BackupManifest, BackupReport};
+use super::dyn_tick::DynQuotick;
+use super::epoch_index::EpochIndex;
+use super::export::{ExportError, ExportFile, ExportHeader};
+use super::meta::SymbolMeta;
+use super::options::QuotickOptions;
+use super::path_builder::QuotickPathBuilder;
+use super::quotick::{read_meta, write_meta, Quotick, QuotickError};
+use super::retention::{self, RetentionJob, RetentionPolicy, RetentionReport};
+use super::symbol::{Symbol, SymbolError};
+use super::timestamp::Timestamp;
+use super::Tick;
+
+#[derive(Debug)]
+pub enum QuotickDbError {
+    Quotick(QuotickError),
+    InvalidSymbol(SymbolError),
+    IoError(io::Error),
+    Backup(BackupError),
+    Export(ExportError),
+    BackingFileFailure,
+    MetaFileFailure,
+    SymbolNotFound,
+    SymbolExists,
+}
+
+impl From<QuotickError> for QuotickDbError {
+    #[inline(always)]
+    fn from(err: QuotickError) -> Self {
+        QuotickDbError::Quotick(err)
+    }
+}
+
+impl From<SymbolError> for QuotickDbError {
+    #[inline(always)]
+    fn from(err: SymbolError) -> Self {
+        QuotickDbError::InvalidSymbol(err)
+    }
+}
+
+impl From<BackupError> for QuotickDbError {
+    #[inline(always)]
+    fn from(err: BackupError) -> Self {
+        QuotickDbError::Backup(err)
+    }
+}
+
+impl From<ExportError> for QuotickDbError {
+    #[inline(always)]
+    fn from(err: ExportError) -> Self {
+        QuotickDbError::Export(err)
+    }
+}
+
+impl From<io::Error> for QuotickDbError {
+    #[inline(always)]
+    fn from(err: io::Error) -> Self {
+        QuotickDbError::IoError(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolStats {
+    pub symbol: Symbol,
+    pub epochs: usize,
+    pub sealed: usize,
+    pub first_epoch: Option<u64>,
+    pub last_epoch: Option<u64>,
+    pub bytes: u64,
+}
+
+/// A catalog of symbols sharing a base path and a set of options.
+///
+/// Every symbol is an independent `Quotick` database living in its own
+/// directory below the base path, as laid out by `QuotickPathBuilder`.
+#[derive(Debug, Clone)]
+pub struct QuotickDb {
+    base_path: PathBuf,
+    options: QuotickOptions,
+}
+
+impl QuotickDb {
+    #[inline(always)]
+    pub fn new(
+        base_path: impl AsRef<Path>,
+    ) -> Result<QuotickDb, QuotickDbError> {
+        QuotickDb::with_options(
+            base_path,
+            QuotickOptions::default(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn with_options(
+        base_path: impl AsRef<Path>,
+        options: QuotickOptions,
+    ) -> Result<QuotickDb, QuotickDbError> {
+        let base_path =
+            base_path
+                .as_ref()
+                .to_path_buf();
+
+        options.backend
+            .create_dir_all(
+                &base_path,
+            )?;
+
+        Ok(
+            QuotickDb {
+                base_path,
+                options,
+            },
+        )
+    }
+
+    #[inline(always)]
+    pub fn base_path(&self) -> &Path {
+        &self.base_path
+    }
+
+    #[inline(always)]
+    pub fn options(&self) -> &QuotickOptions {
+        &self.options
+    }
+
+    #[inline(always)]
+    fn path_builder(
+        &self,
+        symbol: &Symbol,
+    ) -> QuotickPathBuilder {
+        QuotickPathBuilder::new(
+            symbol,
+            &self.base_path,
+        )
+            .with_tiering(self.options.tiering.as_ref())
+            .with_backend(self.options.backend.clone())
+            .with_keys(self.options.encryption.clone())
+    }
+
+    /// Lists all symbols of the database in lexicographic order.
+    ///
+    /// Directories whose names are not a valid symbol encoding are skipped.
+    #[inline(always)]
+    pub fn symbols(&self) -> Result<Vec<Symbol>, QuotickDbError> {
+        let mut symbols = Vec::new();
+
+        let backend = &self.options.backend;
+
+        for path in backend.list(&self.base_path)? {
+            if !backend.is_dir(&path) {
+                continue;
+            }
+
+            let symbol =
+                match path.file_name().and_then(|name| name.to_str()).map(Symbol::from_dir_name) {
+                    Some(Ok(symbol)) => symbol,
+                    _ => continue,
+                };
+
+            if backend.is_file(&self.path_builder(&symbol).epoch_index_backing_file()) {
+                symbols.push(symbol);
+            }
+        }
+
+        symbols.sort();
+
+        Ok(symbols)
+    }
+
+    #[inline(always)]
+    pub fn contains(
+        &self,
+        symbol: &str,
+    ) -> Result<bool, QuotickDbError> {
+        let path_builder = self.path_builder(&Symbol::new(symbol)?);
+
+        Ok(
+            path_builder
+                .backend()
+                .is_file(&path_builder.epoch_index_backing_file()),
+        )
+    }
+
+    /// Opens a symbol using the options of the database.
+    ///
+    /// Fails with `QuotickError::SymbolNotFound` if the symbol does not
+    /// exist and `create_if_missing` is disabled.
+    #[inline(always)]
+    pub fn open<T: Tick + Serialize + DeserializeOwned>(
+        &self,
+        symbol: &str,
+    ) -> Result<Quotick<T>, QuotickDbError> {
+        Ok(
+            Quotick::<T>::with_options(
+                symbol,
+                &self.base_path,
+                self.options.clone(),
+            )?,
+        )
+    }
+
+    /// Opens a symbol for reading without knowing its tick type, see
+    /// `DynQuotick`. Never creates the symbol.
+    #[inline(always)]
+    pub fn open_dyn(
+        &self,
+        symbol: &str,
+    ) -> Result<DynQuotick, QuotickDbError> {
+        Ok(
+            DynQuotick::with_options(
+                symbol,
+                &self.base_path,
+                &self.options,
+            )?,
+        )
+    }
+
+    /// Opens a symbol, creating it regardless of `create_if_missing`.
+    #[inline(always)]
+    pub fn create<T: Tick + Serialize + DeserializeOwned>(
+        &self,
+        symbol: &str,
+    ) -> Result<Quotick<T>, QuotickDbError> {
+        let mut options = self.options.clone();
+
+        options.create_if_missing = true;
+
+        Ok(
+            Quotick::<T>::with_options(
+                symbol,
+                &self.base_path,
+                options,
+            )?,
+        )
+    }
+
+    #[inline(always)]
+    pub fn stats(
+        &self,
+        symbol: &str,
+    ) -> Result<SymbolStats, QuotickDbError> {
+        if !self.contains(symbol)? {
+            return Err(QuotickDbError::SymbolNotFound);
+        }
+
+        let symbol = Symbol::new(symbol)?;
+        let path_builder = self.path_builder(&symbol);
+
+        let mut epoch_index_backing =
+            BackingFile::<EpochIndex>::with_backend(
+                path_builder.backend().clone(),
+                path_builder.keys().cloned(),
+                path_builder.epoch_index_backing_file(),
+            )
+                .map_err(|_| QuotickDbError::BackingFileFailure)?;
+
+        let epoch_index =
+            EpochIndex::read(&mut epoch_index_backing)
+                .unwrap_or_default();
+
+        let backend = path_builder.backend().as_ref();
+
+        let cold_bytes =
+            match path_builder.cold_epoch_path() {
+                Some(cold_epoch_path) if backend.is_dir(&cold_epoch_path) => dir_size(backend, &cold_epoch_path)?,
+                _ => 0,
+            };
+
+        Ok(
+            SymbolStats {
+                symbol,
+                epochs: epoch_index.epochs.len(),
+                sealed: epoch_index.sealed.len(),
+                first_epoch: epoch_index.epochs.first().copied(),
+                last_epoch: epoch_index.epochs.last().copied(),
+                bytes: dir_size(backend, &path_builder.asset_path())? + cold_bytes,
+            },
+        )
+    }
+
+    /// Reads the metadata of a symbol without checking it against a tick type.
+    #[inline(always)]
+    pub fn meta(
+        &self,
+        symbol: &str,
+    ) -> Result<SymbolMeta, QuotickDbError> {
+        if !self.contains(symbol)? {
+            return Err(QuotickDbError::SymbolNotFound);
+        }
+
+        let path_builder = self.path_builder(&Symbol::new(symbol)?);
+
+        let mut meta_backing =
+            BackingFile::<SymbolMeta>::with_backend(
+                path_builder.backend().clone(),
+                path_builder.keys().cloned(),
+                path_builder.meta_backing_file(),
+            )
+                .map_err(|_| QuotickDbError::MetaFileFailure)?;
+
+        Ok(read_meta(&mut meta_backing)?)
+    }
+
+    /// Removes a symbol and all of its epochs from storage.
+    ///
+    /// The symbol must not be open, otherwise dropping the open
+    /// `Quotick` will recreate its epoch index.
+    #[inline(always)]
+    pub fn drop_symbol(
+        &self,
+        symbol: &str,
+    ) -> Result<(), QuotickDbError> {
+        if !self.contains(symbol)? {
+            return Err(QuotickDbError::SymbolNotFound);
+        }
+
+        remove_symbol_files(
+            &self.path_builder(&Symbol::new(symbol)?),
+        )?;
+
+        Ok(())
+    }
+
+    /// Backs up all symbols to `dest`, outside of the base path, see
+    /// `BackupManifest`.
+    ///
+    /// Symbols may be written to meanwhile: every file is backed up as a
+    /// complete version of itself, and the epoch index of a symbol is read
+    /// before its epochs, so it never lists epochs missing from the
+    /// backup. The backup is not a snapshot, though; files written during
+    /// it may be backed up as of different persists. Persist and stop
+    /// writing a symbol first for a point-in-time copy.
+    ///
+    /// Sealed epochs and packs are hard linked where the backend supports
+    /// it. A manifest with checksums of all files is written last; later
+    /// backups to the same `dest` only copy files that changed since, and
+    /// don't read sealed files whose length is unchanged.
+    #[inline(always)]
+    pub fn backup(
+        &self,
+        dest: impl AsRef<Path>,
+    ) -> Result<BackupReport, QuotickDbError> {
+        let path_builders =
+            self.symbols()?
+                .iter()
+                .map(|symbol| self.path_builder(symbol))
+                .collect::<Vec<_>>();
+
+        Ok(
+            backup::backup(
+                self.options.backend.as_ref(),
+                &path_builders,
+                dest.as_ref(),
+                None,
+            )?,
+        )
+    }
+
+    /// Backs up a single symbol to `dest`, see `backup`. Other symbols
+    /// already backed up there are kept.
+    #[inline(always)]
+    pub fn backup_symbol(
+        &self,
+        symbol: &str,
+        dest: impl AsRef<Path>,
+    ) -> Result<BackupReport, QuotickDbError> {
+        if !self.contains(symbol)? {
+            return Err(QuotickDbError::SymbolNotFound);
+        }
+
+        let symbol = Symbol::new(symbol)?;
+
+        Ok(
+            backup::backup(
+                self.options.backend.as_ref(),
+                &[self.path_builder(&symbol)],
+                dest.as_ref(),
+                Some(&symbol),
+            )?,
+        )
+    }
+
+    /// Restores all symbols of the backup at `src`, replacing those of the
+    /// database with the same name. Other symbols are left alone.
+    ///
+    /// The backup is verified against its manifest before anything is
+    /// replaced. Restored epochs are all in the hot tier. The symbols must
+    /// not be open, see `drop_symbol`.
+    #[inline(always)]
+    pub fn restore(
+        &self,
+        src: impl AsRef<Path>,
+    ) -> Result<BackupReport, QuotickDbError> {
+        self.restore_from(
+            src.as_ref(),
+            None,
+        )
+    }
+
+    /// Restores a single symbol of the backup at `src`, see `restore`.
+    #[inline(always)]
+    pub fn restore_symbol(
+        &self,
+        symbol: &str,
+        src: impl AsRef<Path>,
+    ) -> Result<BackupReport, QuotickDbError> {
+        self.restore_from(
+            src.as_ref(),
+            Some(&Symbol::new(symbol)?),
+        )
+    }
+
+    /// Exports the frames of a symbol with times in `range` to a single
+    /// file at `dest`, see `Quotick::export`.
+    #[inline(always)]
+    pub fn export<T: Tick + Serialize + DeserializeOwned>(
+        &self,
+        symbol: &str,
+        range: impl RangeBounds<u64>,
+        dest: impl AsRef<Path>,
+    ) -> Result<ExportHeader, QuotickDbError> {
+        if !self.contains(symbol)? {
+            return Err(QuotickDbError::SymbolNotFound);
+        }
+
+        Ok(self.open::<T>(symbol)?.export(range, dest)?)
+    }
+
+    /// Like `export`, but writes the epochs unencrypted, see
+    /// `Quotick::export_unencrypted`.
+    #[inline(always)]
+    pub fn export_unencrypted<T: Tick + Serialize + DeserializeOwned>(
+        &self,
+        symbol: &str,
+        range: impl RangeBounds<u64>,
+        dest: impl AsRef<Path>,
+    ) -> Result<ExportHeader, QuotickDbError> {
+        if !self.contains(symbol)? {
+            return Err(QuotickDbError::SymbolNotFound);
+        }
+
+        Ok(self.open::<T>(symbol)?.export_unencrypted(range, dest)?)
+    }
+
+    /// Imports an export file as the symbol it was exported from, see
+    /// `import_as`.
+    #[inline(always)]
+    pub fn import(
+        &self,
+        src: impl AsRef<Path>,
+    ) -> Result<Symbol, QuotickDbError> {
+        self.import_from(
+            src.as_ref(),
+            None,
+        )
+    }
+
+    /// Imports an export file as a new symbol, verifying each epoch
+    /// against its checksum as it is copied. A failed import removes what
+    /// it wrote, and the epoch index is written last, so a symbol whose
+    /// import did not finish does not show up in `symbols`.
+    #[inline(always)]
+    pub fn import_as(
+        &self,
+        symbol: &str,
+        src: impl AsRef<Path>,
+    ) -> Result<Symbol, QuotickDbError> {
+        self.import_from(
+            src.as_ref(),
+            Some(Symbol::new(symbol)?),
+        )
+    }
+
+    fn import_from(
+        &self,
+        src: &Path,
+        symbol: Option<Symbol>,
+    ) -> Result<Symbol, QuotickDbError> {
+        let export = ExportFile::open(self.options.backend.as_ref(), src)?;
+
+        let symbol =
+            match symbol {
+                Some(symbol) => symbol,
+                None => Symbol::new(&export.header().symbol)?,
+            };
+
+        if self.contains(symbol.as_str())? {
+            return Err(QuotickDbError::SymbolExists);
+        }
+
+        let path_builder = self.path_builder(&symbol);
+
+        // leftovers of an import that did not finish.
+        remove_symbol_files(&path_builder)?;
+
+        if let Err(err) = self.import_epochs(&export, &path_builder) {
+            remove_symbol_files(&path_builder)?;
+
+            return Err(err);
+        }
+
+        Ok(symbol)
+    }
+
+    /// Copies the epochs of `export` one at a time, verifying each before
+    /// it is written. The epoch index is written last.
+    fn import_epochs(
+        &self,
+        export: &ExportFile<'_>,
+        path_builder: &QuotickPathBuilder,
+    ) -> Result<(), QuotickDbError> {
+        let backend = path_builder.backend();
+
+        backend.create_dir_all(&path_builder.epoch_path())?;
+
+        let mut meta_backing =
+            BackingFile::<SymbolMeta>::with_backend(
+                backend.clone(),
+                path_builder.keys().cloned(),
+                path_builder.meta_backing_file(),
+            )
+                .map_err(|_| QuotickDbError::MetaFileFailure)?;
+
+        write_meta(&mut meta_backing, &export.header().meta)?;
+
+        let mut epoch_index = EpochIndex::default();
+
+        let keys = path_builder.keys().map(Arc::as_ref);
+
+        // epochs exported encrypted need the keys of this database, while
+        // unencrypted ones were asked for explicitly on export.
+        let export_keys =
+            match export.header().encrypted {
+                true => keys,
+                false => None,
+            };
+
+        for epoch in &export.header().epochs {
+            let bytes = export.read_epoch(epoch)?;
+
+            // encrypt the epoch files with the keys of this database, if any.
+            let bytes =
+                decode_raw(&bytes, export_keys)
+                    .and_then(|(version, buf)| encode_raw(&buf, version, self.options.compression_level, keys))
+                    .map_err(|_| QuotickDbError::BackingFileFailure)?;
+
+            backend.replace(&path_builder.hot_index_backing_file(epoch.epoch), &bytes)?;
+
+            epoch_index.epochs.push(epoch.epoch);
+        }
+
+        epoch_index.epochs.sort_unstable();
+        epoch_index.epochs.dedup();
+
+        let mut epoch_index_backing =
+            BackingFile::<EpochIndex>::with_backend(
+                backend.clone(),
+                path_builder.keys().cloned(),
+                path_builder.epoch_index_backing_file(),
+            )
+                .map_err(|_| QuotickDbError::BackingFileFailure)?;
+
+        epoch_index_backing
+            .set_compression_level(
+                self.options.compression_level,
+            );
+
+        epoch_index
+            .write(&mut epoch_index_backing)
+            .map_err(|_| QuotickDbError::BackingFileFailure)
+    }
+
+    fn restore_from(
+        &self,
+        src: &Path,
+        only: Option<&Symbol>,
+    ) -> Result<BackupReport, QuotickDbError> {
+        let backend = self.options.backend.as_ref();
+
+        let manifest =
+            BackupManifest::read(backend, src)?
+                .ok_or(BackupError::MissingManifest)?;
+
+        manifest.verify(backend, src)?;
+
+        let symbols =
+            manifest.symbols()
+                .into_iter()
+                .filter(|symbol| only.is_none_or(|only| only == symbol))
+                .collect::<Vec<_>>();
+
+        if only.is_some() && symbols.is_empty() {
+            return Err(QuotickDbError::SymbolNotFound);
+        }
+
+        let mut report = BackupReport::default();
+
+        for symbol in symbols {
+            remove_symbol_files(&self.path_builder(&symbol))?;
+
+            let restored =
+                backup::restore(
+                    backend,
+                    &manifest,
+                    src,
+                    &self.base_path,
+                    &symbol,
+                )?;
+
+            report.files_copied += restored.files_copied;
+            report.files_linked += restored.files_linked;
+            report.bytes_copied += restored.bytes_copied;
+        }
+
+        Ok(report)
+    }
+
+    /// The retention policy of a symbol, if any.
+    #[inline(always)]
+    pub fn retention(
+        &self,
+        symbol: &str,
+    ) -> Result<Option<RetentionPolicy>, QuotickDbError> {
+        if !self.contains(symbol)? {
+            return Err(QuotickDbError::SymbolNotFound);
+        }
+
+        Ok(
+            retention::read_policy(
+                &self.path_builder(&Symbol::new(symbol)?),
+            )?,
+        )
+    }
+
+    /// Stores or clears the retention policy of a symbol, see
+    /// `enforce_retention`.
+    #[inline(always)]
+    pub fn set_retention(
+        &self,
+        symbol: &str,
+        policy: Option<RetentionPolicy>,
+    ) -> Result<(), QuotickDbError> {
+        if !self.contains(symbol)? {
+            return Err(QuotickDbError::SymbolNotFound);
+        }
+
+        Ok(
+            retention::write_policy(
+                &self.path_builder(&Symbol::new(symbol)?),
+                policy.as_ref(),
+            )?,
+        )
+    }
+
+    /// Removes the epochs expired under their retention policy from
+    /// every symbol that has one.
+    ///
+    /// Works on the files of closed symbols directly. Symbols open in this
+    /// process enforce their policy on the next `Quotick::persist` instead
+    /// and are not part of the report. Symbols must not be open in other
+    /// processes.
+    pub fn enforce_retention(&self) -> Result<RetentionReport, QuotickDbError> {
+        let now = Timestamp::now().as_nanos();
+
+        let mut report = RetentionReport::default();
+
+        for symbol in self.symbols()? {
+            let path_builder = self.path_builder(&symbol);
+
+            let policy =
+                match retention::read_policy(&path_builder)? {
+                    Some(policy) => policy,
+                    None => continue,
+                };
+
+            let removed =
+                retention::with_closed_symbol(
+                    &path_builder,
+                    || self.enforce_symbol_retention(&path_builder, &policy, now),
+                );
+
+            if let Some(removed) = removed {
+                report.add(removed?);
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn enforce_symbol_retention(
+        &self,
+        path_builder: &QuotickPathBuilder,
+        policy: &RetentionPolicy,
+        now: u64,
+    ) -> Result<RetentionReport, QuotickDbError> {
+        let mut meta_backing =
+            BackingFile::<SymbolMeta>::with_backend(
+                path_builder.backend().clone(),
+                path_builder.keys().cloned(),
+                path_builder.meta_backing_file(),
+            )
+                .map_err(|_| QuotickDbError::MetaFileFailure)?;
+
+        let meta = read_meta(&mut meta_backing)?;
+
+        let mut epoch_index_backing =
+            BackingFile::<EpochIndex>::with_backend(
+                path_builder.backend().clone(),
+                path_builder.keys().cloned(),
+                path_builder.epoch_index_backing_file(),
+            )
+                .map_err(|_| QuotickDbError::BackingFileFailure)?;
+
+        epoch_index_backing
+            .set_compression_level(
+                self.options.compression_level,
+            );
+
+        let mut epoch_index =
+            EpochIndex::read(&mut epoch_index_backing)
+                .map_err(|_| QuotickDbError::BackingFileFailure)?;
+
+        let removed =
+            retention::enforce(
+                path_builder,
+                policy,
+                &meta.epoch_rule,
+                &mut epoch_index.epochs,
+                now,
+            )?;
+
+        if removed.epochs_removed > 0 {
+            epoch_index.retain_sealed();
+
+            epoch_index
+                .write(&mut epoch_index_backing)
+                .map_err(|_| QuotickDbError::BackingFileFailure)?;
+        }
+
+        Ok(removed)
+    }
+
+    /// Runs `enforce_retention` on a background thread right away and
+    /// then every `interval`, until the returned job is stopped or
+    /// dropped. Failed runs are retried on the next one.
+    #[inline(always)]
+    pub fn spawn_retention_job(
+        &self,
+        interval: Duration,
+    ) -> RetentionJob {
+        let db = self.clone();
+
+        RetentionJob::new(
+            interval,
+            move || {
+                let _ = db.enforce_retention();
+            },
+        )
+    }
+}
+
+/// Removes the files of a symbol in both tiers, if any.
+fn remove_symbol_files(
+    path_builder: &QuotickPathBuilder,
+) -> Result<(), io::Error> {
+    let backend = path_builder.backend();
+
+    // the cold tier mirrors the layout below the base path.
+    let cold_asset_path =
+        path_builder.cold_epoch_path()
+            .and_then(|path| path.parent().map(Path::to_path_buf));
+
+    let paths =
+        Some(path_builder.asset_path())
+            .into_iter()
+            .chain(cold_asset_path);
+
+    for path in paths.filter(|path| backend.is_dir(path)) {
+        backend.delete(&path)?;
+    }
+
+    Ok(())
+}
+
+fn dir_size(
+    backend: &dyn StorageBackend,
+    path: &Path,
+) -> Result<u64, io::Error> {
+    let mut size = 0;
+
+    for path in backend.list(path)? {
+        if backend.is_dir(&path) {
+            size += dir_size(backend, &path)?;
+        } else if backend.is_file(&path) {
+            size += backend.len(&path)?;
+        }
+    }
+
+    Ok(size)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test_util::TempPath;
+    use super::super::tick::{Quote, Trade};
+    use super::super::Frame;
+    use super::*;
+
+    #[test]
+    fn list_stats_and_drop() {
+        let base_path = TempPath::new("db");
+        let db = QuotickDb::new(&base_path).unwrap();
+
+        for symbol in &["MSFT", "AAPL", "BRK/A"] {
+            let mut quotick = db.create::<Trade>(symbol).unwrap();
+
+            quotick
+                .insert(&Frame::new(1, Trade::default()))
+                .unwrap();
+        }
+
+        let symbols =
+            db.symbols()
+                .unwrap()
+                .iter()
+                .map(|symbol| symbol.to_string())
+                .collect::<Vec<_>>();
+
+        assert_eq!(symbols, vec!["AAPL", "BRK/A", "MSFT"]);
+
+        let stats = db.stats("AAPL").unwrap();
+
+        assert_eq!(stats.epochs, 1);
+        assert_eq!(stats.first_epoch, Some(0));
+        assert!(stats.bytes > 0);
+
+        assert_eq!(db.meta("AAPL").unwrap().tick_type, "quotick::Trade");
+
+        match db.open::<Quote>("AAPL") {
+            Err(QuotickDbError::Quotick(QuotickError::Meta(_))) => {}
+            _ => panic!("opened a trade symbol as quotes"),
+        }
+
+        db.drop_symbol("AAPL").unwrap();
+        db.drop_symbol("BRK/A").unwrap();
+
+        assert_eq!(db.symbols().unwrap(), vec![Symbol::new("MSFT").unwrap()]);
+        assert!(db.open::<Trade>("../MSFT").is_err());
+        assert!(db.stats("AAPL").is_err());
+    }
+
+    #[test]
+    fn open_without_create() {
+        let options =
+            QuotickOptions {
+                create_if_missing: false,
+                ..QuotickOptions::default()
+            };
+
+        let base_path = TempPath::new("db");
+        let db = QuotickDb::with_options(&base_path, options).unwrap();
+
+        assert!(db.open::<Trade>("NOPE").is_err());
+        assert!(db.create::<Trade>("NOPE").is_ok());
+        assert!(db.open::<Trade>("NOPE").is_ok());
+    }
+
+    #[test]
+    fn retention_removes_oldest_epochs() {
+        const DAY: u64 = 86_400_000_000_000;
+
+        let base_path = TempPath::new("db");
+        let db = QuotickDb::new(&base_path).unwrap();
+
+        for symbol in &["L2", "KEEP"] {
+            let mut quotick = db.create::<Trade>(symbol).unwrap();
+
+            for day in 0..4 {
+                quotick
+                    .insert(&Frame::new(day * DAY, Trade::default()))
+                    .unwrap();
+            }
+
+            quotick.persist().unwrap();
+        }
+
+        assert!(db.set_retention("NOPE", None).is_err());
+
+        db.set_retention("L2", Some(RetentionPolicy::max_age(Duration::from_secs(90 * 86_400))))
+            .unwrap();
+
+        let report = db.enforce_retention().unwrap();
+
+        // age removes the newest epoch, too.
+        assert_eq!(report.epochs_removed, 4);
+        assert!(report.bytes_removed > 0);
+        assert_eq!(db.stats("L2").unwrap().epochs, 0);
+        assert_eq!(db.stats("KEEP").unwrap().epochs, 4);
+
+        let path_builder = db.path_builder(&Symbol::new("L2").unwrap());
+
+        assert!(!path_builder.index_backing_file(0).exists());
+        assert!(!path_builder.index_backing_file(3).exists());
+
+        let mut quotick = db.open::<Trade>("KEEP").unwrap();
+
+        assert_eq!(quotick.retention().unwrap(), None);
+
+        quotick
+            .insert(&Frame::new(4 * DAY, Trade::default()))
+            .unwrap();
+
+        db.set_retention("KEEP", Some(RetentionPolicy::max_bytes(0))).unwrap();
+
+        // the open handle enforces the policy on its next persist.
+        assert_eq!(db.enforce_retention().unwrap().epochs_removed, 0);
+        assert_eq!(quotick.epoch_index(), &[0, 1, 2, 3, 4]);
+
+        quotick.persist().unwrap();
+
+        assert_eq!(quotick.epoch_index(), &[4]);
+
+        drop(quotick);
+
+        db.spawn_retention_job(Duration::from_secs(3600)).stop();
+
+        assert_eq!(db.stats("KEEP").unwrap().epochs, 1);
+    }
+}
@@ -0,0 +1,665 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+
+use super::backing::backing_file::{decode_raw, BackingFile};
+use super::epoch_index::EpochIndex;
+use super::meta::SymbolMeta;
+use super::options::QuotickOptions;
+use super::pack::read_packed;
+use super::path_builder::QuotickPathBuilder;
+use super::quotick::{read_meta, QuotickError};
+use super::symbol::Symbol;
+use super::tick::{FieldKind, TickField};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynError {
+    /// The symbol does not record the fields of its tick type.
+    MissingSchema,
+    /// A field of a kind that cannot be decoded without its type.
+    UnsupportedKind(String),
+    /// An epoch written with a schema version other than the current one;
+    /// run `Quotick::migrate` with the typed tick first.
+    SchemaVersionMismatch {
+        expected: u32,
+        found: u32,
+    },
+    UnexpectedEnd,
+    BadString,
+    BadDate(String),
+    BadVariant(u32),
+}
+
+/// A single decoded field value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    UInt(u64),
+    Int(i64),
+    Float(f64),
+    String(String),
+    /// A `Price`, as its raw value and scale.
+    Price {
+        raw: i64,
+        scale: u32,
+    },
+    Date(NaiveDate),
+    /// The variant name of a fieldless enum.
+    Enum(String),
+    List(Vec<Value>),
+    Record(DynTick),
+}
+
+impl Value {
+    #[inline(always)]
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::UInt(value) => Some(*value),
+            Value::Int(value) if *value >= 0 => Some(*value as u64),
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int(value) => Some(*value),
+            Value::UInt(value) if *value <= i64::MAX as u64 => Some(*value as i64),
+            _ => None,
+        }
+    }
+
+    /// Numeric values as `f64`; prices are converted, possibly losing
+    /// precision.
+    #[inline(always)]
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::UInt(value) => Some(*value as f64),
+            Value::Int(value) => Some(*value as f64),
+            Value::Float(value) => Some(*value),
+            Value::Price { raw, scale } => Some(*raw as f64 / 10f64.powi(*scale as i32)),
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) | Value::Enum(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::UInt(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{:?}", value),
+            Value::Price { raw, scale } => {
+                let sign = if *raw < 0 { "-" } else { "" };
+                let scale = *scale as usize;
+
+                // padded to at least one integer digit, so scales beyond
+                // the digits of `raw` still place the point correctly.
+                let digits =
+                    format!(
+                        "{:0width$}",
+                        raw.unsigned_abs(),
+                        width = scale + 1,
+                    );
+
+                if scale == 0 {
+                    return write!(f, "{}{}", sign, digits);
+                }
+
+                let (int, frac) = digits.split_at(digits.len() - scale);
+
+                write!(f, "{}{}.{}", sign, int, frac)
+            }
+            Value::Date(value) => write!(f, "{}", value),
+            Value::Enum(value) => write!(f, "{}", value),
+            Value::List(values) => {
+                write!(f, "[")?;
+
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{}", value)?;
+                }
+
+                write!(f, "]")
+            }
+            Value::Record(record) => write!(f, "{}", record),
+        }
+    }
+}
+
+/// A tick decoded from the persisted schema of its symbol, as field
+/// names and values in declaration order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DynTick {
+    fields: Vec<(String, Value)>,
+}
+
+impl DynTick {
+    /// Decodes a single bincode serialized tick described by `fields`.
+    #[inline(always)]
+    pub fn from_bytes(
+        fields: &[TickField],
+        bytes: &[u8],
+    ) -> Result<DynTick, DynError> {
+        DynTick::decode(
+            fields,
+            &mut Reader::new(bytes),
+        )
+    }
+
+    fn decode(
+        fields: &[TickField],
+        reader: &mut Reader,
+    ) -> Result<DynTick, DynError> {
+        Ok(
+            DynTick {
+                fields: fields
+                    .iter()
+                    .map(|field|
+                        decode_value(&field.kind, reader)
+                            .map(|value| (field.name.clone(), value))
+                    )
+                    .collect::<Result<_, _>>()?,
+            },
+        )
+    }
+
+    #[inline(always)]
+    pub fn get(
+        &self,
+        name: &str,
+    ) -> Option<&Value> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value)
+    }
+
+    #[inline(always)]
+    pub fn fields(&self) -> &[(String, Value)] {
+        &self.fields
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl fmt::Display for DynTick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+
+        for (idx, (name, value)) in self.fields.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}: {}", name, value)?;
+        }
+
+        write!(f, "}}")
+    }
+}
+
+/// A frame of a `DynQuotick`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynFrame {
+    time: u64,
+    tick: DynTick,
+}
+
+impl DynFrame {
+    #[inline(always)]
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    #[inline(always)]
+    pub fn tick(&self) -> &DynTick {
+        &self.tick
+    }
+}
+
+/// Read only access to a symbol whose tick type is not known at compile
+/// time, i.e. for dump tools or bridges to other languages.
+///
+/// Frames are decoded with the fields recorded in the symbol metadata,
+/// so this only works for tick types describing their `fields`, which
+/// `#[derive(Tick)]` and the built-in ticks do.
+pub struct DynQuotick {
+    meta: SymbolMeta,
+    epoch_index: Vec<u64>,
+    path_builder: QuotickPathBuilder,
+}
+
+impl DynQuotick {
+    #[inline(always)]
+    pub fn open(
+        asset: &str,
+        base_path: impl AsRef<Path>,
+    ) -> Result<DynQuotick, QuotickError> {
+        DynQuotick::with_options(
+            asset,
+            base_path,
+            &QuotickOptions::default(),
+        )
+    }
+
+    /// Opens a symbol, resolving epochs across the tiers of `options`.
+    #[inline(always)]
+    pub fn with_options(
+        asset: &str,
+        base_path: impl AsRef<Path>,
+        options: &QuotickOptions,
+    ) -> Result<DynQuotick, QuotickError> {
+        let symbol = Symbol::new(asset)?;
+
+        let path_builder =
+            QuotickPathBuilder::new(
+                &symbol,
+                base_path,
+            )
+                .with_tiering(options.tiering.as_ref())
+                .with_backend(options.backend.clone())
+                .with_keys(options.encryption.clone());
+
+        let backend = path_builder.backend();
+
+        if !backend.is_file(&path_builder.epoch_index_backing_file())
+            || !backend.is_file(&path_builder.meta_backing_file()) {
+            return Err(QuotickError::SymbolNotFound);
+        }
+
+        let mut meta_backing =
+            BackingFile::<SymbolMeta>::with_backend(
+                path_builder.backend().clone(),
+                path_builder.keys().cloned(),
+                path_builder.meta_backing_file(),
+            )
+                .map_err(|_| QuotickError::MetaFileFailure)?;
+
+        let meta = read_meta(&mut meta_backing)?;
+
+        if meta.fields.is_empty() {
+            return Err(DynError::MissingSchema.into());
+        }
+
+        if let Some(kind) = meta.fields.iter().find_map(|field| unsupported_kind(&field.kind)) {
+            return Err(DynError::UnsupportedKind(kind).into());
+        }
+
+        let mut epoch_index_backing =
+            BackingFile::<EpochIndex>::with_backend(
+                path_builder.backend().clone(),
+                path_builder.keys().cloned(),
+                path_builder.epoch_index_backing_file(),
+            )
+                .map_err(|_| QuotickError::BackingFileFailure)?;
+
+        let epoch_index =
+            EpochIndex::read(&mut epoch_index_backing)
+                .unwrap_or_default()
+                .epochs;
+
+        Ok(
+            DynQuotick {
+                meta,
+                epoch_index,
+                path_builder,
+            },
+        )
+    }
+
+    /// Decodes all frames of `epoch`, sorted by time. Never creates or
+    /// writes files.
+    pub fn read_epoch(
+        &self,
+        epoch: u64,
+    ) -> Result<Vec<DynFrame>, QuotickError> {
+        let backend = self.path_builder.backend();
+        let path = self.path_builder.index_backing_file(epoch);
+
+        let bytes =
+            if backend.is_file(&path) {
+                backend.read(&path)
+                    .map_err(|_| QuotickError::BackingFileFailure)?
+            } else {
+                read_packed(&self.path_builder, &self.meta.epoch_rule, epoch)?
+                    .ok_or(QuotickError::EpochNotFound(epoch))?
+            };
+
+        if bytes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (schema_version, buf) =
+            decode_raw(&bytes, self.path_builder.keys().map(Arc::as_ref))
+                .map_err(|_| QuotickError::BackingFileFailure)?;
+
+        if schema_version != self.meta.schema_version {
+            return Err(
+                DynError::SchemaVersionMismatch {
+                    expected: self.meta.schema_version,
+                    found: schema_version,
+                }
+                    .into(),
+            );
+        }
+
+        // the frame index is serialized as a map from time to tick.
+        let mut reader = Reader::new(&buf);
+
+        let len = reader.len()?;
+
+        (0..len)
+            .map(|_| {
+                let time = reader.u64()?;
+                let tick = DynTick::decode(&self.meta.fields, &mut reader)?;
+
+                Ok(DynFrame { time, tick })
+            })
+            .collect::<Result<_, DynError>>()
+            .map_err(QuotickError::from)
+    }
+
+    /// Decodes the frames of all epochs, oldest first.
+    #[inline(always)]
+    pub fn frames(&self) -> impl Iterator<Item=Result<DynFrame, QuotickError>> + '_ {
+        self.epoch_index
+            .iter()
+            .flat_map(move |&epoch|
+                match self.read_epoch(epoch) {
+                    Ok(frames) => frames.into_iter().map(Ok).collect::<Vec<_>>(),
+                    Err(err) => vec![Err(err)],
+                }
+            )
+    }
+
+    #[inline(always)]
+    pub fn fields(&self) -> &[TickField] {
+        &self.meta.fields
+    }
+
+    #[inline(always)]
+    pub fn epoch_index(&self) -> &[u64] {
+        &self.epoch_index
+    }
+
+    #[inline(always)]
+    pub fn path_builder(&self) -> &QuotickPathBuilder {
+        &self.path_builder
+    }
+
+    #[inline(always)]
+    pub fn meta(&self) -> &SymbolMeta {
+        &self.meta
+    }
+}
+
+fn unsupported_kind(
+    kind: &FieldKind,
+) -> Option<String> {
+    match kind {
+        FieldKind::Other(ty) => Some(ty.clone()),
+        FieldKind::Array(kind, _) | FieldKind::Seq(kind) => unsupported_kind(kind),
+        FieldKind::Struct(fields) => fields.iter().find_map(|field| unsupported_kind(&field.kind)),
+        _ => None,
+    }
+}
+
+fn decode_value(
+    kind: &FieldKind,
+    reader: &mut Reader,
+) -> Result<Value, DynError> {
+    Ok(
+        match kind {
+            FieldKind::Bool => Value::Bool(reader.take::<1>()?[0] != 0),
+            FieldKind::U8 => Value::UInt(u8::from_le_bytes(reader.take()?) as u64),
+            FieldKind::U16 => Value::UInt(u16::from_le_bytes(reader.take()?) as u64),
+            FieldKind::U32 => Value::UInt(u32::from_le_bytes(reader.take()?) as u64),
+            FieldKind::U64 => Value::UInt(reader.u64()?),
+            FieldKind::I8 => Value::Int(i8::from_le_bytes(reader.take()?) as i64),
+            FieldKind::I16 => Value::Int(i16::from_le_bytes(reader.take()?) as i64),
+            FieldKind::I32 => Value::Int(i32::from_le_bytes(reader.take()?) as i64),
+            FieldKind::I64 => Value::Int(i64::from_le_bytes(reader.take()?)),
+            FieldKind::F32 => Value::Float(f32::from_le_bytes(reader.take()?) as f64),
+            FieldKind::F64 => Value::Float(f64::from_le_bytes(reader.take()?)),
+            FieldKind::String => Value::String(reader.string()?),
+            FieldKind::Price(scale) => {
+                Value::Price {
+                    raw: i64::from_le_bytes(reader.take()?),
+                    scale: *scale,
+                }
+            }
+            FieldKind::Date => {
+                let date = reader.string()?;
+
+                Value::Date(
+                    NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                        .map_err(|_| DynError::BadDate(date))?,
+                )
+            }
+            FieldKind::Enum(variants) => {
+                let idx = u32::from_le_bytes(reader.take()?);
+
+                Value::Enum(
+                    variants
+                        .get(idx as usize)
+                        .cloned()
+                        .ok_or(DynError::BadVariant(idx))?,
+                )
+            }
+            FieldKind::Array(kind, len) => {
+                Value::List(
+                    (0..*len)
+                        .map(|_| decode_value(kind, reader))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            FieldKind::Seq(kind) => {
+                let len = reader.len()?;
+
+                Value::List(
+                    (0..len)
+                        .map(|_| decode_value(kind, reader))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            FieldKind::Struct(fields) => Value::Record(DynTick::decode(fields, reader)?),
+            FieldKind::Other(ty) => return Err(DynError::UnsupportedKind(ty.clone())),
+        },
+    )
+}
+
+/// Reads the default bincode encoding: little endian fixed size
+/// integers, `u64` length prefixes and `u32` enum variant indices.
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    #[inline(always)]
+    fn new(
+        buf: &'a [u8],
+    ) -> Reader<'a> {
+        Reader {
+            buf,
+        }
+    }
+
+    #[inline(always)]
+    fn bytes(
+        &mut self,
+        len: usize,
+    ) -> Result<&'a [u8], DynError> {
+        if self.buf.len() < len {
+            return Err(DynError::UnexpectedEnd);
+        }
+
+        let (bytes, rest) = self.buf.split_at(len);
+
+        self.buf = rest;
+
+        Ok(bytes)
+    }
+
+    #[inline(always)]
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DynError> {
+        let mut bytes = [0u8; N];
+
+        bytes.copy_from_slice(self.bytes(N)?);
+
+        Ok(bytes)
+    }
+
+    #[inline(always)]
+    fn u64(&mut self) -> Result<u64, DynError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    #[inline(always)]
+    fn len(&mut self) -> Result<usize, DynError> {
+        let len = self.u64()? as usize;
+
+        // every element takes at least a byte, except for unit types,
+        // which ticks cannot describe.
+        if len > self.buf.len() {
+            return Err(DynError::UnexpectedEnd);
+        }
+
+        Ok(len)
+    }
+
+    #[inline(always)]
+    fn string(&mut self) -> Result<String, DynError> {
+        let len = self.len()?;
+
+        String::from_utf8(self.bytes(len)?.to_vec())
+            .map_err(|_| DynError::BadString)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::db::QuotickDb;
+    use super::super::test_util::TempPath;
+    use super::super::tick::{BookDelta, Conditions, ConsolidatedTrade, LevelChange, Side, Tape, Venue};
+    use super::super::Frame;
+    use super::*;
+
+    #[test]
+    fn decodes_nested_ticks() {
+        let delta =
+            BookDelta {
+                sequence: 7,
+                venue: Venue(b'N'),
+                snapshot: false,
+                changes: vec![
+                    LevelChange {
+                        side: Side::Ask,
+                        price: "100.5".parse().unwrap(),
+                        size: 10,
+                        order_count: 2,
+                    },
+                ],
+            };
+
+        let tick =
+            DynTick::from_bytes(
+                &<BookDelta as super::super::Tick>::fields(),
+                &bincode::serialize(&delta).unwrap(),
+            )
+                .unwrap();
+
+        assert_eq!(tick.get("sequence"), Some(&Value::UInt(7)));
+        assert_eq!(
+            tick.to_string(),
+            "{sequence: 7, venue: 78, snapshot: false, changes: [{side: Ask, price: 100.50000000, size: 10, order_count: 2}]}",
+        );
+
+        assert_eq!(
+            DynTick::from_bytes(&[TickField::new("name", FieldKind::String)], &[3, 0, 0]),
+            Err(DynError::UnexpectedEnd),
+        );
+    }
+
+    #[test]
+    fn reads_symbols_without_their_type() {
+        let base_path = TempPath::new("dyn");
+        let db = QuotickDb::new(&base_path).unwrap();
+
+        {
+            let mut quotick = db.create::<ConsolidatedTrade>("AAPL").unwrap();
+
+            for idx in 0..3u64 {
+                quotick
+                    .insert(
+                        &Frame::new(
+                            idx * 86_400_000_000_000 + 1,
+                            ConsolidatedTrade {
+                                id: idx,
+                                size: 100,
+                                price: "123.45".parse().unwrap(),
+                                conditions: Conditions::new(b"@"),
+                                tape: Tape::C,
+                                ..Default::default()
+                            },
+                        ),
+                    )
+                    .unwrap();
+            }
+        }
+
+        let quotick = db.open_dyn("AAPL").unwrap();
+
+        assert_eq!(quotick.fields().len(), 8);
+
+        let frames =
+            quotick.frames()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[2].time(), 2 * 86_400_000_000_000 + 1);
+        assert_eq!(frames[2].tick().get("id").and_then(Value::as_u64), Some(2));
+        assert_eq!(frames[0].tick().get("tape").and_then(Value::as_str), Some("C"));
+        assert_eq!(frames[0].tick().get("price").and_then(Value::as_f64), Some(123.45));
+
+        assert!(db.open_dyn("MSFT").is_err());
+
+        // reading does not create missing epoch files.
+        let missing = quotick.path_builder().index_backing_file(7);
+
+        assert!(matches!(quotick.read_epoch(7), Err(QuotickError::EpochNotFound(7))));
+        assert!(!missing.exists());
+
+        assert_eq!(Value::Price { raw: -5, scale: 20 }.to_string(), "-0.00000000000000000005");
+        assert_eq!(Value::Price { raw: 12_345, scale: 2 }.to_string(), "123.45");
+        assert_eq!(Value::Price { raw: -7, scale: 0 }.to_string(), "-7");
+
+        let typed = db.open::<ConsolidatedTrade>("AAPL").unwrap();
+
+        assert_eq!(typed.open_dyn("AAPL").unwrap().frames().count(), 3);
+    }
+}
@@ -1,10 +1,18 @@
+use std::path::Path;
+use std::sync::Arc;
+
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use super::backing::backing_file::{decode_raw, encode_versioned};
 use super::BackingFile;
-use super::frame::Frame;
+use super::frame::{Frame, FrameRef};
+use super::options::QuotickOptions;
+use super::pack::read_packed;
 use super::path_builder::QuotickPathBuilder;
 use super::radix_trie::{Trie, TrieCommon};
+use super::schema::SchemaUpgrades;
+use super::stats::EpochStats;
 use super::Tick;
 
 #[derive(Debug)]
@@ -15,17 +23,25 @@ pub enum EpochError {
     FrameConflict,
     FrameTooBig,
     FrameEmpty,
+    UnsupportedSchemaVersion(u32),
+    /// A tick stored with this schema version could not be converted.
+    UpgradeFailed(u32),
 }
 
-type FrameIndex<T> = Trie<u64, T>;
+pub(crate) type FrameIndex<T> = Trie<u64, T>;
 
 pub struct Epoch<T: Tick + Serialize + DeserializeOwned> {
-    frame_index_backing: BackingFile<FrameIndex<T>>,
+    /// `None` for packed epochs until they are written to a file of
+    /// their own.
+    frame_index_backing: Option<BackingFile<FrameIndex<T>>>,
+    compression_level: u8,
 
     pub frame_index: FrameIndex<T>,
 
     epoch: u64,
 
+    schema_version: u32,
+
     tainted: bool,
 
     path_builder: QuotickPathBuilder,
@@ -36,29 +52,79 @@ impl<T: Tick + Serialize + DeserializeOwned> Epoch<T> {
     pub fn new(
         epoch: u64,
         path_builder: QuotickPathBuilder,
+        options: &QuotickOptions,
+        upgrades: &SchemaUpgrades<T>,
     ) -> Result<Epoch<T>, EpochError> {
-        let mut frame_index_backing =
-            BackingFile::<FrameIndex<T>>::new(
-                path_builder.index_backing_file(epoch),
-            )
-                .or_else(|_|
-                    Err(
-                        EpochError::BackingFileFailure(
-                            "Failed to open frame index backing file.",
-                        ),
-                    )
-                )?;
+        let path = path_builder.index_backing_file(epoch);
+
+        let compression_level = options.compression_level_for(path_builder.tier(epoch));
+
+        // an epoch file of its own takes precedence over the pack.
+        let packed =
+            if path_builder.backend().is_file(&path) {
+                None
+            } else {
+                read_packed(&path_builder, &T::epoch_rule(), epoch)
+                    .map_err(|_| EpochError::IndexFileFailure)?
+            };
+
+        let (frame_index_backing, raw) =
+            match packed {
+                Some(bytes) => {
+                    let raw =
+                        decode_raw(&bytes, path_builder.keys().map(Arc::as_ref))
+                            .map_err(|_| EpochError::IndexFileFailure)?;
+
+                    (None, Some(raw))
+                }
+                None => {
+                    let mut frame_index_backing = open_backing::<T>(&path_builder, &path, compression_level)?;
+
+                    let is_empty =
+                        frame_index_backing.is_empty()
+                            .map_err(|_| EpochError::IndexFileFailure)?;
 
-        let frame_index =
-            frame_index_backing.try_read()
-                .unwrap_or_else(|_| Trie::new());
+                    let raw =
+                        if is_empty {
+                            None
+                        } else {
+                            Some(
+                                frame_index_backing.try_read_raw()
+                                    .map_err(|_| EpochError::IndexFileFailure)?,
+                            )
+                        };
+
+                    (Some(frame_index_backing), raw)
+                }
+            };
+
+        let (schema_version, frame_index) =
+            match raw {
+                None => (T::schema_version(), Trie::new()),
+                Some((schema_version, buf)) => {
+                    let frame_index =
+                        if schema_version == T::schema_version() {
+                            bincode::deserialize::<FrameIndex<T>>(&buf)
+                                .map_err(|_| EpochError::IndexFileFailure)?
+                        } else {
+                            upgrades.upgrade(
+                                schema_version,
+                                &buf,
+                            )?
+                        };
+
+                    (schema_version, frame_index)
+                }
+            };
 
         Ok(
             Epoch {
                 frame_index_backing,
+                compression_level,
                 frame_index,
 
                 epoch,
+                schema_version,
                 tainted: false,
 
                 path_builder,
@@ -66,8 +132,54 @@ impl<T: Tick + Serialize + DeserializeOwned> Epoch<T> {
         )
     }
 
+    /// Frames of the epoch sorted by time, borrowing their ticks.
     #[inline(always)]
-    pub fn frames(&mut self) -> impl Iterator<Item=Frame<T>> + '_ {
+    pub fn iter(&self) -> impl Iterator<Item=FrameRef<'_, T>> + '_ {
+        self.frame_index
+            .iter()
+            .map(|(time, item)|
+                     FrameRef::new(
+                         *time,
+                         item,
+                     ),
+            )
+    }
+
+    /// Calls `f` for every frame of the epoch, sorted by time.
+    #[inline(always)]
+    pub fn for_each_frame<F: FnMut(FrameRef<'_, T>)>(
+        &self,
+        f: F,
+    ) {
+        self.iter()
+            .for_each(f);
+    }
+
+    /// Number of frames in the epoch.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.frame_index.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.frame_index.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn get(
+        &self,
+        time: u64,
+    ) -> Option<FrameRef<'_, T>> {
+        self.frame_index
+            .get(&time)
+            .map(|item| FrameRef::new(time, item))
+    }
+
+    /// Frames of the epoch sorted by time; clones every tick, prefer
+    /// `iter` for scans.
+    #[inline(always)]
+    pub fn frames(&self) -> impl Iterator<Item=Frame<T>> + '_ {
         self.frame_index
             .iter()
             .map(|(time, item)|
@@ -83,6 +195,20 @@ impl<T: Tick + Serialize + DeserializeOwned> Epoch<T> {
         self.epoch
     }
 
+    /// Schema version the epoch was stored with; differs from
+    /// `T::schema_version()` until the epoch has been rewritten.
+    #[inline(always)]
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    /// Marks the epoch for rewriting with the current schema version
+    /// on the next `persist`.
+    #[inline(always)]
+    pub fn rewrite(&mut self) {
+        self.tainted = true;
+    }
+
     #[inline(always)]
     pub fn insert(
         &mut self,
@@ -106,24 +232,131 @@ impl<T: Tick + Serialize + DeserializeOwned> Epoch<T> {
         Ok(())
     }
 
+    /// Size of the frames serialized with the current schema version,
+    /// before compression.
+    #[inline(always)]
+    pub fn uncompressed_size(&self) -> u64 {
+        bincode::serialized_size(&self.frame_index)
+            .unwrap_or(0)
+    }
+
+    /// Statistics of the epoch as loaded, without its stored size and
+    /// counters, which `Quotick::epoch_stats` fills in.
     #[inline(always)]
-    pub fn persist(&mut self) {
+    pub fn stats(&self) -> EpochStats {
+        EpochStats {
+            epoch: self.epoch,
+            frames: self.len(),
+            uncompressed_bytes: self.uncompressed_size(),
+            first_time: self.iter().next().map(|frame| frame.time()),
+            last_time: self.iter().last().map(|frame| frame.time()),
+            ..EpochStats::default()
+        }
+    }
+
+    /// Atomically replaces the file at `path` with the frames, written
+    /// with the current schema version, i.e. to swap a compacted copy in
+    /// place of the epoch file.
+    #[inline(always)]
+    pub fn write_to<P: AsRef<Path>>(
+        &self,
+        path: P,
+        compression_level: u8,
+    ) -> Result<(), EpochError> {
+        let buf =
+            encode_versioned(
+                &self.frame_index,
+                T::schema_version(),
+                compression_level,
+                self.path_builder.keys().map(Arc::as_ref),
+            )
+                .map_err(|_| EpochError::WriteFailure)?;
+
+        self.path_builder
+            .backend()
+            .replace(
+                path.as_ref(),
+                &buf,
+            )
+            .map_err(|_| EpochError::WriteFailure)
+    }
+
+    /// Writes the epoch if it changed since it was read or last written.
+    #[inline(always)]
+    pub fn persist(&mut self) -> Result<(), EpochError> {
         if !self.tainted {
-            return;
+            return Ok(());
         }
 
-        self.frame_index_backing
-            .write_all(
+        let frame_index_backing =
+            match self.frame_index_backing.as_mut() {
+                Some(frame_index_backing) => frame_index_backing,
+                // a packed epoch moves to a file of its own, which shadows
+                // the pack.
+                None => {
+                    self.frame_index_backing.insert(
+                        open_backing::<T>(
+                            &self.path_builder,
+                            self.path_builder.index_backing_file(self.epoch),
+                            self.compression_level,
+                        )?,
+                    )
+                }
+            };
+
+        frame_index_backing
+            .write_versioned(
                 &self.frame_index,
-            );
+                T::schema_version(),
+            )
+            .map_err(|_| EpochError::WriteFailure)?;
 
+        self.schema_version = T::schema_version();
         self.tainted = false;
+
+        // a zero-copy archive of the previous contents is stale now.
+        if self.path_builder.backend().is_local() {
+            let archive = self.path_builder.archive_file(self.epoch);
+
+            if archive.exists() {
+                let _ = std::fs::remove_file(archive);
+            }
+        }
+
+        Ok(())
     }
 }
 
+#[inline(always)]
+fn open_backing<T: Tick + Serialize + DeserializeOwned>(
+    path_builder: &QuotickPathBuilder,
+    path: impl AsRef<Path>,
+    compression_level: u8,
+) -> Result<BackingFile<FrameIndex<T>>, EpochError> {
+    let mut frame_index_backing =
+        BackingFile::<FrameIndex<T>>::with_backend(
+            path_builder.backend().clone(),
+            path_builder.keys().cloned(),
+            path,
+        )
+            .map_err(|_|
+                EpochError::BackingFileFailure(
+                    "Failed to open frame index backing file.",
+                )
+            )?;
+
+    frame_index_backing
+        .set_compression_level(
+            compression_level,
+        );
+
+    Ok(frame_index_backing)
+}
+
 impl<T: Tick + Serialize + DeserializeOwned> Drop for Epoch<T> {
     #[inline(always)]
     fn drop(&mut self) {
-        self.persist();
+        // errors can't be reported here, persist explicitly to see them.
+        let _ = self.persist();
     }
 }
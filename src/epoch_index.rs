@@ -0,0 +1,105 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::backing::backing_file::{BackingFile, BackingFileError};
+
+/// Version of the `EpochIndex` layout in `epochs.qtf`.
+pub const EPOCH_INDEX_FORMAT_VERSION: u32 = 1;
+
+/// The epochs of a symbol, as stored in `epochs.qtf`.
+///
+/// Both lists are sorted. Sealed epochs are immutable: inserts into them
+/// are rejected and they are never rewritten, until they are unsealed,
+/// see `Quotick::seal`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct EpochIndex {
+    pub epochs: Vec<u64>,
+    pub sealed: Vec<u64>,
+}
+
+impl EpochIndex {
+    /// Reads the index, converting the plain list of epochs written
+    /// before sealing existed. An empty file is an empty index.
+    pub fn read(
+        backing: &mut BackingFile<EpochIndex>,
+    ) -> Result<EpochIndex, BackingFileError> {
+        if backing.is_empty()? {
+            return Ok(EpochIndex::default());
+        }
+
+        let (version, buf) = backing.try_read_raw()?;
+
+        EpochIndex::decode(version, &buf)
+    }
+
+    /// Decodes an index read by other means, see `decode_raw`.
+    pub fn decode(
+        version: u32,
+        buf: &[u8],
+    ) -> Result<EpochIndex, BackingFileError> {
+        let index =
+            match version {
+                0 => {
+                    bincode::deserialize::<Vec<u64>>(buf)
+                        .map(|epochs| EpochIndex { epochs, sealed: Vec::new() })
+                }
+                EPOCH_INDEX_FORMAT_VERSION => bincode::deserialize::<EpochIndex>(buf),
+                _ => return Err(BackingFileError::BadData),
+            };
+
+        index.map_err(|err| BackingFileError::External(err))
+    }
+
+    #[inline(always)]
+    pub fn write(
+        &self,
+        backing: &mut BackingFile<EpochIndex>,
+    ) -> Result<(), BackingFileError> {
+        backing.write_versioned(
+            self,
+            EPOCH_INDEX_FORMAT_VERSION,
+        )
+    }
+
+    #[inline(always)]
+    pub fn is_sealed(
+        &self,
+        epoch: u64,
+    ) -> bool {
+        self.sealed.binary_search(&epoch).is_ok()
+    }
+
+    /// Drops sealed marks of epochs no longer in the index.
+    #[inline(always)]
+    pub(crate) fn retain_sealed(&mut self) {
+        let epochs = &self.epochs;
+
+        self.sealed.retain(|epoch| epochs.binary_search(epoch).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test_util::TempPath;
+    use super::*;
+
+    #[test]
+    fn reads_legacy_index() {
+        let path = TempPath::new("epoch-index");
+
+        let mut legacy = BackingFile::<Vec<u64>>::new(&path).ok().unwrap();
+
+        legacy.write_all(&vec![1, 2, 3]).ok().unwrap();
+
+        let mut backing = BackingFile::<EpochIndex>::new(&path).ok().unwrap();
+
+        let mut index = EpochIndex::read(&mut backing).ok().unwrap();
+
+        assert_eq!(index.epochs, vec![1, 2, 3]);
+        assert!(index.sealed.is_empty());
+
+        index.sealed = vec![2];
+        index.write(&mut backing).ok().unwrap();
+
+        assert!(EpochIndex::read(&mut backing).ok().unwrap().is_sealed(2));
+    }
+}
@@ -0,0 +1,338 @@
+use std::convert::{TryFrom, TryInto};
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use serde_derive::{Deserialize, Serialize};
+
+const NANOS_PER_HOUR: u64 = 3_600_000_000_000;
+const NANOS_PER_DAY: u64 = 86_400_000_000_000;
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EpochRuleError {
+    UnknownTimeZone(String),
+    BadSessionTime(String),
+}
+
+/// Partitions nanosecond timestamps into epochs.
+///
+/// `Hour`, `Day`, `Week` and `Month` are aligned to UTC, weeks start on
+/// Monday. `Session` follows the trading session of an exchange, so that
+/// a session is always stored in a single epoch regardless of where it
+/// falls relative to UTC midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EpochRule {
+    Hour,
+    Day,
+    Week,
+    Month,
+    Session(SessionRule),
+}
+
+/// A daily trading session in the local time of an exchange.
+///
+/// If `open` is later than `close` the session runs overnight, i.e. a
+/// futures session opening at 17:00 and closing at 16:00 the next day.
+/// Such a session is filed under the date it closes on. Sessions within
+/// a single local day are filed under that day.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SessionRule {
+    pub tz: Tz,
+    pub open: NaiveTime,
+    pub close: NaiveTime,
+}
+
+impl SessionRule {
+    #[inline(always)]
+    pub fn new(
+        tz: Tz,
+        open: NaiveTime,
+        close: NaiveTime,
+    ) -> SessionRule {
+        SessionRule {
+            tz,
+            open,
+            close,
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_overnight(&self) -> bool {
+        self.open > self.close
+    }
+
+    /// The local date a session containing `time` is filed under.
+    #[inline(always)]
+    pub fn session_date(
+        &self,
+        time: u64,
+    ) -> NaiveDate {
+        let local =
+            self.tz
+                .from_utc_datetime(&nanos_to_datetime(time))
+                .naive_local();
+
+        if self.is_overnight() && local.time() >= self.open {
+            local.date() + Duration::days(1)
+        } else {
+            local.date()
+        }
+    }
+
+    /// First nanosecond belonging to the session filed under `date`.
+    #[inline(always)]
+    pub fn session_start(
+        &self,
+        date: NaiveDate,
+    ) -> u64 {
+        let start =
+            if self.is_overnight() {
+                (date - Duration::days(1)).and_time(self.open)
+            } else {
+                date.and_time(NaiveTime::MIN)
+            };
+
+        local_to_nanos(self.tz, start)
+    }
+}
+
+impl EpochRule {
+    /// Builds a session rule from an IANA time zone name, i.e.
+    /// `America/Chicago`, and `HH:MM` session times.
+    #[inline(always)]
+    pub fn session(
+        tz: &str,
+        open: &str,
+        close: &str,
+    ) -> Result<EpochRule, EpochRuleError> {
+        let tz =
+            Tz::from_str(tz)
+                .map_err(|_| EpochRuleError::UnknownTimeZone(tz.to_string()))?;
+
+        let parse_time =
+            |time: &str|
+                NaiveTime::parse_from_str(time, "%H:%M")
+                    .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M:%S"))
+                    .map_err(|_| EpochRuleError::BadSessionTime(time.to_string()));
+
+        Ok(
+            EpochRule::Session(
+                SessionRule::new(
+                    tz,
+                    parse_time(open)?,
+                    parse_time(close)?,
+                ),
+            ),
+        )
+    }
+
+    #[inline(always)]
+    pub fn epoch(
+        &self,
+        time: u64,
+    ) -> u64 {
+        match self {
+            EpochRule::Hour => time / NANOS_PER_HOUR,
+            EpochRule::Day => time / NANOS_PER_DAY,
+            EpochRule::Week => (time / NANOS_PER_DAY + 3) / 7,
+            // times are clamped to 2262, see `nanos_to_datetime`, so the
+            // year can't fall before 1970.
+            EpochRule::Month => {
+                let date = nanos_to_datetime(time).date();
+                let years: u64 = (date.year() - 1970).try_into().unwrap_or(0);
+
+                years * 12 + date.month0() as u64
+            }
+            // sessions starting before 1970-01-01 local time fall into
+            // the first epoch.
+            EpochRule::Session(session) => {
+                (session.session_date(time) - unix_date())
+                    .num_days()
+                    .try_into()
+                    .unwrap_or(0)
+            }
+        }
+    }
+
+    /// First nanosecond of `epoch`; the epoch ends where `epoch + 1` starts.
+    #[inline(always)]
+    pub fn start(
+        &self,
+        epoch: u64,
+    ) -> u64 {
+        match self {
+            EpochRule::Hour => epoch * NANOS_PER_HOUR,
+            EpochRule::Day => epoch * NANOS_PER_DAY,
+            // the week containing the unix epoch starts on Monday, 1969-12-29.
+            EpochRule::Week => (epoch * 7).saturating_sub(3) * NANOS_PER_DAY,
+            EpochRule::Month => {
+                let date =
+                    NaiveDate::from_ymd_opt(
+                        1970 + (epoch / 12) as i32,
+                        (epoch % 12) as u32 + 1,
+                        1,
+                    )
+                        .unwrap_or(NaiveDate::MAX);
+
+                datetime_to_nanos(date.and_time(NaiveTime::MIN))
+            }
+            EpochRule::Session(session) => {
+                session.session_start(
+                    unix_date() + Duration::days(epoch as i64),
+                )
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn end(
+        &self,
+        epoch: u64,
+    ) -> u64 {
+        self.start(epoch + 1)
+    }
+
+    #[inline(always)]
+    pub fn name(&self) -> &'static str {
+        match self {
+            EpochRule::Hour => "hour",
+            EpochRule::Day => "day",
+            EpochRule::Week => "week",
+            EpochRule::Month => "month",
+            EpochRule::Session(_) => "session",
+        }
+    }
+}
+
+#[inline(always)]
+fn unix_date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
+}
+
+/// Times past `i64::MAX` nanoseconds, in April 2262, clamp to it.
+#[inline(always)]
+pub(crate) fn nanos_to_datetime(
+    time: u64,
+) -> NaiveDateTime {
+    Utc.timestamp_nanos(i64::try_from(time).unwrap_or(i64::MAX))
+        .naive_utc()
+}
+
+/// Times before 1970 clamp to 0, times past `i64::MAX` nanoseconds to it.
+#[inline(always)]
+pub(crate) fn datetime_to_nanos(
+    datetime: NaiveDateTime,
+) -> u64 {
+    let utc = datetime.and_utc();
+
+    utc.timestamp()
+        .checked_mul(NANOS_PER_SECOND)
+        .and_then(|nanos| nanos.checked_add(utc.timestamp_subsec_nanos() as i64))
+        .unwrap_or(if utc.timestamp() < 0 { 0 } else { i64::MAX })
+        .max(0) as u64
+}
+
+/// Converts a local wall clock time to UTC nanoseconds. Ambiguous times
+/// resolve to their first occurrence, times skipped by a DST transition
+/// to the instant of the transition.
+pub(crate) fn local_to_nanos(
+    tz: Tz,
+    local: NaiveDateTime,
+) -> u64 {
+    let mut probe = local;
+
+    loop {
+        if let Some(datetime) = tz.from_local_datetime(&probe).earliest() {
+            return datetime_to_nanos(datetime.naive_utc());
+        }
+
+        // the first valid minute after a gap is where the clocks jumped to.
+        probe =
+            probe.with_second(0)
+                .and_then(|probe| probe.with_nanosecond(0))
+                .unwrap_or(probe)
+                + Duration::minutes(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn utc(datetime: &str) -> u64 {
+        datetime_to_nanos(
+            NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M").unwrap(),
+        )
+    }
+
+    #[test]
+    fn utc_rules_match_legacy_day_epochs() {
+        let time = utc("2021-03-04 13:37");
+
+        assert_eq!(EpochRule::Day.epoch(time), time / 86_400_000_000_000);
+        assert_eq!(EpochRule::Hour.epoch(time), time / 3_600_000_000_000);
+    }
+
+    #[test]
+    fn weeks_and_months() {
+        // Monday to Sunday
+        let monday = EpochRule::Week.epoch(utc("2021-03-01 00:00"));
+
+        assert_eq!(EpochRule::Week.epoch(utc("2021-03-07 23:59")), monday);
+        assert_eq!(EpochRule::Week.epoch(utc("2021-03-08 00:00")), monday + 1);
+        assert_eq!(EpochRule::Week.start(monday), utc("2021-03-01 00:00"));
+
+        let march = EpochRule::Month.epoch(utc("2021-03-31 23:59"));
+
+        assert_eq!(march, 51 * 12 + 2);
+        assert_eq!(EpochRule::Month.start(march), utc("2021-03-01 00:00"));
+        assert_eq!(EpochRule::Month.end(march), utc("2021-04-01 00:00"));
+
+        // out of range times clamp instead of wrapping.
+        let last = EpochRule::Month.epoch(i64::MAX as u64);
+
+        assert_eq!(EpochRule::Month.epoch(u64::MAX), last);
+        assert_eq!(EpochRule::Month.start(u64::MAX / 2), i64::MAX as u64);
+        assert_eq!(nanos_to_datetime(u64::MAX), nanos_to_datetime(i64::MAX as u64));
+    }
+
+    #[test]
+    fn overnight_session_is_not_split() {
+        let rule = EpochRule::session("America/Chicago", "17:00", "16:00").unwrap();
+
+        // Sunday 17:00 CST to Monday 16:00 CST is the Monday session.
+        let open = rule.epoch(utc("2021-03-07 23:00"));
+        let midnight = rule.epoch(utc("2021-03-08 06:00"));
+        let close = rule.epoch(utc("2021-03-08 21:59"));
+
+        assert_eq!(open, midnight);
+        assert_eq!(open, close);
+        assert_eq!(rule.start(open), utc("2021-03-07 23:00"));
+
+        // after the DST switch the session opens at 22:00 UTC.
+        let next = rule.epoch(utc("2021-03-15 22:00"));
+
+        assert_eq!(rule.start(next), utc("2021-03-15 22:00"));
+        assert_eq!(rule.epoch(utc("2021-03-15 21:59")), next - 1);
+    }
+
+    #[test]
+    fn asia_pacific_day_is_not_split() {
+        let rule = EpochRule::session("Asia/Tokyo", "09:00", "15:00").unwrap();
+
+        // 09:00 JST is 00:00 UTC, 23:30 UTC the previous day is 08:30 JST.
+        assert_eq!(
+            rule.epoch(utc("2021-03-07 23:30")),
+            rule.epoch(utc("2021-03-08 06:00")),
+        );
+
+        // 1969-12-31 19:00 in New York belongs to a session before 1970.
+        let rule = EpochRule::session("America/New_York", "09:30", "16:00").unwrap();
+
+        assert_eq!(rule.epoch(0), 0);
+
+        assert!(EpochRule::session("Mars/Olympus", "09:00", "15:00").is_err());
+        assert!(EpochRule::session("Asia/Tokyo", "9am", "15:00").is_err());
+    }
+}
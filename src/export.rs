@@ -0,0 +1,413 @@
+use std::convert::{TryFrom, TryInto};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::backing::storage::StorageBackend;
+use super::meta::SymbolMeta;
+
+const EXPORT_MAGIC: &[u8] = b"QTEX";
+
+/// Version of the export file layout.
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// magic, version.
+const PREAMBLE_LEN: u64 = 8;
+
+/// header length, header checksum.
+const TRAILER_LEN: u64 = 12;
+
+#[derive(Debug)]
+pub enum ExportError {
+    IoError(io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    BadHeader,
+    Truncated,
+    /// The epoch, or the header if `None`, does not match its checksum.
+    ChecksumMismatch(Option<u64>),
+}
+
+impl From<io::Error> for ExportError {
+    #[inline(always)]
+    fn from(err: io::Error) -> Self {
+        ExportError::IoError(err)
+    }
+}
+
+/// Describes the contents of an export file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportHeader {
+    /// Name of the exported symbol.
+    pub symbol: String,
+    pub meta: SymbolMeta,
+    /// Inclusive start and end of the exported frame times, `None` for
+    /// an empty range.
+    pub bounds: Option<(u64, Option<u64>)>,
+    /// Nanoseconds since the unix epoch.
+    pub created: u64,
+    /// Whether the epoch files are encrypted with the keys of the
+    /// exporting database, see `Quotick::export_unencrypted`.
+    pub encrypted: bool,
+    /// Epochs in the order their files follow the preamble.
+    pub epochs: Vec<ExportedEpoch>,
+}
+
+impl ExportHeader {
+    #[inline(always)]
+    pub fn frames(&self) -> u64 {
+        self.epochs.iter().map(|epoch| epoch.frames).sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedEpoch {
+    pub epoch: u64,
+    pub frames: u64,
+    /// Position of the epoch file within the export file.
+    pub offset: u64,
+    pub len: u64,
+    /// CRC-32 of the epoch file.
+    pub checksum: u32,
+}
+
+/// A symbol, or a time range of it, in a single self-describing file,
+/// see `Quotick::export` and `QuotickDb::import`.
+///
+/// The file starts with the magic and the format version, followed by
+/// the epoch files, the header listing them and, last, the length and
+/// the CRC-32 of the header. Epochs are written and read one at a time,
+/// so exports of any size never need to fit in memory.
+///
+/// Epoch files are written in the current schema version of the symbol,
+/// so importing them needs no schema upgrades, and encrypted with the
+/// keys of the exporting database, if any, unless exported with
+/// `Quotick::export_unencrypted`.
+#[derive(Debug)]
+pub struct ExportFile<'a> {
+    backend: &'a dyn StorageBackend,
+    path: PathBuf,
+    header: ExportHeader,
+}
+
+impl<'a> ExportFile<'a> {
+    /// Opens the export file at `path`, verifying its header against its
+    /// checksum. Epochs are verified as they are read, see `read_epoch`.
+    pub fn open(
+        backend: &'a dyn StorageBackend,
+        path: impl AsRef<Path>,
+    ) -> Result<ExportFile<'a>, ExportError> {
+        let path = path.as_ref();
+        let len = backend.len(path)?;
+
+        if len < PREAMBLE_LEN + TRAILER_LEN {
+            return Err(ExportError::Truncated);
+        }
+
+        let preamble = backend.read_at(path, 0, PREAMBLE_LEN as usize)?;
+
+        if &preamble[..4] != EXPORT_MAGIC {
+            return Err(ExportError::BadMagic);
+        }
+
+        let version = u32::from_le_bytes(preamble[4..8].try_into().unwrap());
+
+        if version != EXPORT_FORMAT_VERSION {
+            return Err(ExportError::UnsupportedVersion(version));
+        }
+
+        let trailer = backend.read_at(path, len - TRAILER_LEN, TRAILER_LEN as usize)?;
+
+        let header_len = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+        let checksum = u32::from_le_bytes(trailer[8..12].try_into().unwrap());
+
+        let header_offset =
+            (len - TRAILER_LEN)
+                .checked_sub(header_len)
+                .filter(|&offset| offset >= PREAMBLE_LEN)
+                .ok_or(ExportError::Truncated)?;
+
+        let header = backend.read_at(path, header_offset, header_len as usize)?;
+
+        if crc32fast::hash(&header) != checksum {
+            return Err(ExportError::ChecksumMismatch(None));
+        }
+
+        let header =
+            bincode::deserialize::<ExportHeader>(&header)
+                .map_err(|_| ExportError::BadHeader)?;
+
+        // epoch files lie between the preamble and the header.
+        let within =
+            |epoch: &ExportedEpoch| {
+                epoch.offset >= PREAMBLE_LEN
+                    && epoch.offset
+                        .checked_add(epoch.len)
+                        .is_some_and(|end| end <= header_offset)
+            };
+
+        if !header.epochs.iter().all(within) {
+            return Err(ExportError::Truncated);
+        }
+
+        Ok(
+            ExportFile {
+                backend,
+                path: path.to_path_buf(),
+                header,
+            },
+        )
+    }
+
+    #[inline(always)]
+    pub fn header(&self) -> &ExportHeader {
+        &self.header
+    }
+
+    /// Reads the file of `epoch`, one of those in the header, verifying
+    /// it against its checksum.
+    pub fn read_epoch(
+        &self,
+        epoch: &ExportedEpoch,
+    ) -> Result<Vec<u8>, ExportError> {
+        let len =
+            usize::try_from(epoch.len)
+                .map_err(|_| ExportError::Truncated)?;
+
+        let bytes = self.backend.read_at(&self.path, epoch.offset, len)?;
+
+        if crc32fast::hash(&bytes) != epoch.checksum {
+            return Err(ExportError::ChecksumMismatch(Some(epoch.epoch)));
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Writes an export file one epoch at a time, see `ExportFile`.
+///
+/// The file is written next to `path` and moved there by `finish`, so
+/// an export that did not finish does not replace an earlier one.
+pub(crate) struct ExportWriter<'a> {
+    backend: &'a dyn StorageBackend,
+    path: PathBuf,
+    tmp_path: PathBuf,
+    offset: u64,
+    epochs: Vec<ExportedEpoch>,
+}
+
+impl<'a> ExportWriter<'a> {
+    pub(crate) fn create(
+        backend: &'a dyn StorageBackend,
+        path: impl AsRef<Path>,
+    ) -> Result<ExportWriter<'a>, ExportError> {
+        let path = path.as_ref().to_path_buf();
+
+        let mut tmp_path = path.as_os_str().to_owned();
+
+        tmp_path.push(".partial");
+
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let mut preamble = Vec::with_capacity(PREAMBLE_LEN as usize);
+
+        preamble.extend_from_slice(EXPORT_MAGIC);
+        preamble.extend_from_slice(&EXPORT_FORMAT_VERSION.to_le_bytes());
+
+        backend.write(&tmp_path, &preamble)?;
+
+        Ok(
+            ExportWriter {
+                backend,
+                path,
+                tmp_path,
+                offset: PREAMBLE_LEN,
+                epochs: Vec::new(),
+            },
+        )
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        epoch: u64,
+        frames: u64,
+        bytes: &[u8],
+    ) -> Result<(), ExportError> {
+        self.backend.append(&self.tmp_path, bytes)?;
+
+        self.epochs.push(
+            ExportedEpoch {
+                epoch,
+                frames,
+                offset: self.offset,
+                len: bytes.len() as u64,
+                checksum: crc32fast::hash(bytes),
+            },
+        );
+
+        self.offset += bytes.len() as u64;
+
+        Ok(())
+    }
+
+    /// Appends `header`, listing the epochs pushed, and moves the file to
+    /// its path.
+    pub(crate) fn finish(
+        self,
+        mut header: ExportHeader,
+    ) -> Result<ExportHeader, ExportError> {
+        header.epochs = self.epochs;
+
+        let mut buf =
+            bincode::serialize(&header)
+                .map_err(|_| ExportError::BadHeader)?;
+
+        let checksum = crc32fast::hash(&buf);
+        let header_len = buf.len() as u64;
+
+        buf.extend_from_slice(&header_len.to_le_bytes());
+        buf.extend_from_slice(&checksum.to_le_bytes());
+
+        self.backend.append(&self.tmp_path, &buf)?;
+        self.backend.rename(&self.tmp_path, &self.path)?;
+
+        Ok(header)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::sync::Arc;
+
+    use super::super::backing::encryption::{KeyProvider, StaticKey, ENCRYPTED_MAGIC};
+    use super::super::backing::storage::MemoryBackend;
+    use super::super::db::{QuotickDb, QuotickDbError};
+    use super::super::options::QuotickOptions;
+    use super::super::symbol::Symbol;
+    use super::super::test_util::{sizes, TempPath};
+    use super::super::tick::Trade;
+    use super::super::Frame;
+    use super::*;
+
+    const DAY: u64 = 86_400_000_000_000;
+
+    #[test]
+    fn exports_and_imports_symbols_and_ranges() {
+        let src_path = TempPath::new("export-src");
+        let dest_path = TempPath::new("export-dest");
+        let path = TempPath::new("export");
+
+        let src = QuotickDb::new(&src_path).unwrap();
+        let dest = QuotickDb::new(&dest_path).unwrap();
+
+        let mut quotick = src.create::<Trade>("SYMBL").unwrap();
+
+        for day in 0..4 {
+            for hour in 0..2 {
+                quotick
+                    .insert(&Frame::new(day * DAY + hour, Trade { size: day * 10 + hour, ..Trade::default() }))
+                    .unwrap();
+            }
+        }
+
+        quotick.seal(0).unwrap();
+        quotick.pack_sealed().unwrap();
+
+        let header = quotick.export(.., &path).unwrap();
+
+        assert_eq!(header.symbol, "SYMBL");
+        assert_eq!(header.epochs.len(), 4);
+        assert_eq!(header.frames(), 8);
+
+        assert_eq!(dest.import(&path).unwrap().as_str(), "SYMBL");
+        assert_eq!(sizes(&dest.open::<Trade>("SYMBL").unwrap()), sizes(&quotick));
+        assert_eq!(dest.open_dyn("SYMBL").unwrap().frames().count(), 8);
+
+        assert!(matches!(dest.import(&path), Err(QuotickDbError::SymbolExists)));
+
+        // only frames within the range, epochs without any are left out.
+        let header = src.export::<Trade>("SYMBL", DAY + 1..=2 * DAY, &path).unwrap();
+
+        assert_eq!(header.epochs.iter().map(|epoch| epoch.epoch).collect::<Vec<_>>(), vec![1, 2]);
+
+        dest.import_as("PART", &path).unwrap();
+
+        assert_eq!(sizes(&dest.open::<Trade>("PART").unwrap()), vec![11, 20]);
+
+        // a flipped bit in an epoch file is caught on import.
+        let mut bytes = fs::read(&path).unwrap();
+        let epoch = header.epochs[1];
+
+        bytes[(epoch.offset + epoch.len - 1) as usize] ^= 1;
+
+        fs::write(&path, &bytes).unwrap();
+
+        assert!(matches!(
+            dest.import_as("OTHER", &path),
+            Err(QuotickDbError::Export(ExportError::ChecksumMismatch(Some(2)))),
+        ));
+
+        assert!(!dest.contains("OTHER").unwrap());
+        assert!(!dest_path.join(Symbol::new("OTHER").unwrap().dir_name()).exists());
+
+        // as is one in the header.
+        bytes[(epoch.offset + epoch.len - 1) as usize] ^= 1;
+
+        let last = bytes.len() - TRAILER_LEN as usize - 1;
+
+        bytes[last] ^= 1;
+
+        fs::write(&path, &bytes).unwrap();
+
+        assert!(matches!(dest.import_as("OTHER", &path), Err(QuotickDbError::Export(ExportError::ChecksumMismatch(None)))));
+    }
+
+    #[test]
+    fn imports_across_keys() {
+        let backend = MemoryBackend::new();
+
+        let db =
+            |path: &str, key: u8| {
+                QuotickDb::with_options(
+                    path,
+                    QuotickOptions {
+                        backend: Arc::new(backend.clone()),
+                        encryption: Some(Arc::new(StaticKey::with_id(key as u32, [key; 32])) as Arc<dyn KeyProvider>),
+                        ..QuotickOptions::default()
+                    },
+                ).unwrap()
+            };
+
+        let src = db("/src", 1);
+        let dest = db("/dest", 2);
+
+        let mut quotick = src.create::<Trade>("SYMBL").unwrap();
+
+        quotick.insert(&Frame::new(1, Trade { size: 42, ..Trade::default() })).unwrap();
+
+        // encrypted with the keys of the source by default.
+        let header = quotick.export(.., "/export.qtx").unwrap();
+
+        assert!(header.encrypted);
+
+        let export = ExportFile::open(&backend, "/export.qtx").unwrap();
+
+        assert!(export.header().epochs.iter().all(|epoch| export.read_epoch(epoch).unwrap().starts_with(ENCRYPTED_MAGIC)));
+        assert!(matches!(dest.import("/export.qtx"), Err(QuotickDbError::BackingFileFailure)));
+        assert!(!dest.contains("SYMBL").unwrap());
+
+        // a database with the same keys imports it.
+        db("/same", 1).import("/export.qtx").unwrap();
+
+        assert!(!quotick.export_unencrypted(.., "/export.qtx").unwrap().encrypted);
+
+        dest.import("/export.qtx").unwrap();
+
+        let imported = dest.open::<Trade>("SYMBL").unwrap();
+
+        assert_eq!(imported.newest_frame().unwrap().tick().size, 42);
+        assert_eq!(&backend.read(&imported.path_builder().index_backing_file(0)).unwrap()[..4], ENCRYPTED_MAGIC);
+    }
+}
@@ -1,5 +1,6 @@
 use serde_derive::{Deserialize, Serialize};
 
+use super::timestamp::Timestamp;
 use super::Tick;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,6 +21,17 @@ impl<T: Tick> Frame<T> {
         }
     }
 
+    #[inline(always)]
+    pub fn at(
+        time: Timestamp,
+        tick: T,
+    ) -> Frame<T> {
+        Frame::new(
+            time.as_nanos(),
+            tick,
+        )
+    }
+
     #[inline(always)]
     pub fn tick(&self) -> &T {
         &self.tick
@@ -30,8 +42,119 @@ impl<T: Tick> Frame<T> {
         self.time
     }
 
+    #[inline(always)]
+    pub fn timestamp(&self) -> Timestamp {
+        Timestamp::from_nanos(self.time)
+    }
+
     #[inline(always)]
     pub fn epoch(&self) -> u64 {
-        self.tick.epoch(self.time)
+        T::epoch_rule().epoch(self.time)
+    }
+}
+
+/// A frame borrowing its tick from a loaded `Epoch`.
+#[derive(Debug)]
+pub struct FrameRef<'a, T: Tick> {
+    time: u64,
+    tick: &'a T,
+}
+
+impl<'a, T: Tick> FrameRef<'a, T> {
+    #[inline(always)]
+    pub fn new(
+        time: u64,
+        tick: &'a T,
+    ) -> FrameRef<'a, T> {
+        FrameRef {
+            time,
+            tick,
+        }
+    }
+
+    #[inline(always)]
+    pub fn tick(&self) -> &'a T {
+        self.tick
+    }
+
+    #[inline(always)]
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    #[inline(always)]
+    pub fn timestamp(&self) -> Timestamp {
+        Timestamp::from_nanos(self.time)
+    }
+
+    #[inline(always)]
+    pub fn epoch(&self) -> u64 {
+        T::epoch_rule().epoch(self.time)
+    }
+
+    /// Clones the tick into an owned `Frame`.
+    #[inline(always)]
+    pub fn to_frame(&self) -> Frame<T> {
+        Frame::new(
+            self.time,
+            self.tick.clone(),
+        )
+    }
+}
+
+impl<'a, T: Tick> Clone for FrameRef<'a, T> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: Tick> Copy for FrameRef<'a, T> {}
+
+#[cfg(test)]
+mod test {
+    use super::super::quotick::Quotick;
+    use super::super::test_util::TempPath;
+    use super::super::tick::Trade;
+    use super::*;
+
+    #[test]
+    fn visits_frames_without_cloning() {
+        let base_path = TempPath::new("frame");
+        let mut quotick = Quotick::<Trade>::new("SYMBL", &base_path).unwrap();
+
+        let day = 86_400_000_000_000;
+
+        for time in [2 * day + 5, 1, day + 3, 2] {
+            quotick
+                .insert(&Frame::new(time, Trade { size: time, ..Default::default() }))
+                .unwrap();
+        }
+
+        quotick.persist().unwrap();
+
+        // the current epoch is still loaded and gets another frame.
+        quotick
+            .insert(&Frame::new(2 * day + 1, Trade::default()))
+            .unwrap();
+
+        let mut times = Vec::new();
+
+        quotick
+            .for_each_frame(|frame| times.push(frame.time()))
+            .unwrap();
+
+        assert_eq!(times, vec![1, 2, day + 3, 2 * day + 1, 2 * day + 5]);
+
+        let epoch = quotick.epochs().next().unwrap();
+
+        let sizes =
+            epoch.iter()
+                .map(|frame| frame.tick().size)
+                .sum::<u64>();
+
+        assert_eq!(sizes, 3);
+        assert_eq!(epoch.get(2).unwrap().tick().size, 2);
+        assert_eq!(quotick.oldest_frame().unwrap().time(), 1);
     }
 }
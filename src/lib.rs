@@ -1,16 +1,59 @@
-#![feature(nll)]
+pub use chrono;
+pub use chrono_tz;
+
+#[cfg(feature = "async")]
+pub use async_tick::AsyncQuotick;
 
 pub use backing::backing_file::BackingFile;
+pub use backing::storage::{FsBackend, MemoryBackend, StorageBackend};
+pub use db::QuotickDb;
+pub use dyn_tick::{DynQuotick, DynTick, Value};
 pub use epoch::Epoch;
+pub use epoch_rule::EpochRule;
 pub use frame::Frame;
+pub use options::QuotickOptions;
+pub use price::Price;
 pub use quotick::Quotick;
+pub use retention::RetentionPolicy;
+pub use symbol::Symbol;
 pub use tick::Tick;
+pub use tier::TierPolicy;
+pub use timestamp::Timestamp;
+#[cfg(feature = "derive")]
+pub use quotick_derive::Tick;
 
+pub mod archive;
+#[cfg(feature = "async")]
+pub mod async_tick;
 pub mod backing;
+pub mod backup;
+pub mod calendar;
+pub mod columns;
+pub mod db;
+pub mod dyn_tick;
 pub mod epoch;
+pub mod epoch_index;
+pub mod epoch_rule;
+pub mod export;
 pub mod frame;
+pub mod meta;
+pub mod options;
+pub mod pack;
 pub mod path_builder;
+pub mod price;
 pub mod quotick;
+pub mod retention;
+pub mod schema;
+pub mod sealed;
+pub mod stats;
+pub mod symbol;
 pub mod tick;
+pub mod tier;
+pub mod timestamp;
 
+// vendored from the `radix_trie` crate and kept as is.
+#[allow(unused, mismatched_lifetime_syntaxes, clippy::all)]
 mod radix_trie;
+
+#[cfg(test)]
+mod test_util;
@@ -13,16 +13,16 @@ struct Trade {
 
 impl quotick::tick::Tick for Trade {
     #[inline(always)]
-    fn epoch(&self, time: u64) -> u64 {
-        // one day
-        time / 86_400_000_000_000
+    fn epoch_rule() -> quotick::epoch_rule::EpochRule {
+        // one epoch per day
+        quotick::epoch_rule::EpochRule::Day
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TestDataTrade {
     #[serde(rename = "T")]
-    T: Option<String>,
+    ticker: Option<String>,
     // AAPL
     #[serde(rename = "t")]
     t: i64,
@@ -64,13 +64,8 @@ fn run(ticks: &[TestDataTrade]) {
             .expect("Could not open test database.");
 
     for tick in ticks {
-        if let Some(_) = tick.p {
-            //let p = tick.p.unwrap();
-
-            //println!("{} {} {} = ${}", tick.t, tick.s, p, tick.s as f64 * p as f64);
-        }
-
-        quotick.insert(
+        // the test data repeats some frames, those are skipped.
+        let _ = quotick.insert(
             &quotick::Frame::new(
                 tick.t as u64,
                 Trade {
@@ -84,14 +79,14 @@ fn run(ticks: &[TestDataTrade]) {
         );
     }
 
-    quotick.persist();
+    quotick.persist().unwrap();
 
     quotick
         .epochs()
         .for_each(
-            |mut epoch| {
+            |epoch| {
                 epoch
-                    .frames()
+                    .iter()
                     .for_each(
                         |frame| {
                             frame.time();
@@ -114,7 +109,8 @@ fn main() {
 
     let mut test_data = Vec::<u8>::new();
 
-    file.read_to_end(&mut test_data);
+    file.read_to_end(&mut test_data)
+        .expect("Could not read ./test_data/test_data.");
 
     let ticks =
         bincode::deserialize::<Vec<TestDataTrade>>(
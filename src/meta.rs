@@ -0,0 +1,162 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::epoch_rule::EpochRule;
+use super::tick::TickField;
+use super::Tick;
+
+/// Version of the `SymbolMeta` layout itself.
+pub const META_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetaError {
+    UnsupportedFormat(u32),
+    TickTypeMismatch {
+        expected: String,
+        found: String,
+    },
+    FingerprintMismatch {
+        expected: u64,
+        found: u64,
+    },
+    NewerSchemaVersion {
+        supported: u32,
+        found: u32,
+    },
+    EpochRuleMismatch {
+        expected: EpochRule,
+        found: EpochRule,
+    },
+}
+
+/// Describes how a symbol was written.
+///
+/// Stored once per symbol in `meta.qtf` and checked whenever the symbol is
+/// opened, so that a `Quotick::<Quote>` is never pointed at `Trade` data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolMeta {
+    pub format_version: u32,
+
+    pub tick_type: String,
+    pub schema_version: u32,
+    pub schema_fingerprint: u64,
+    pub epoch_rule: EpochRule,
+
+    /// Creation time in nanoseconds since the unix epoch.
+    pub created_at: u64,
+    pub created_by: String,
+
+    /// Fields of the current schema, used to read the symbol without
+    /// knowing its tick type. Empty if the tick type does not describe
+    /// its fields.
+    pub fields: Vec<TickField>,
+}
+
+impl SymbolMeta {
+    #[inline(always)]
+    pub fn for_tick<T: Tick>() -> SymbolMeta {
+        let created_at =
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos() as u64)
+                .unwrap_or(0);
+
+        SymbolMeta {
+            format_version: META_FORMAT_VERSION,
+
+            tick_type: T::type_name().to_string(),
+            schema_version: T::schema_version(),
+            schema_fingerprint: T::schema_fingerprint(),
+            epoch_rule: T::epoch_rule(),
+
+            created_at,
+            created_by: format!("quotick {}", env!("CARGO_PKG_VERSION")),
+
+            fields: T::fields(),
+        }
+    }
+
+    /// Verifies that ticks of type `T` can be read from and written to a
+    /// symbol described by this metadata.
+    ///
+    /// A symbol written with an older schema version of `T` passes, its
+    /// epochs are converted by the registered `SchemaUpgrades` on read.
+    /// The metadata keeps describing the older schema until
+    /// `Quotick::migrate` has rewritten every epoch.
+    #[inline(always)]
+    pub fn check<T: Tick>(&self) -> Result<(), MetaError> {
+        if self.format_version != META_FORMAT_VERSION {
+            return Err(MetaError::UnsupportedFormat(self.format_version));
+        }
+
+        if self.tick_type != T::type_name() {
+            return Err(
+                MetaError::TickTypeMismatch {
+                    expected: T::type_name().to_string(),
+                    found: self.tick_type.clone(),
+                },
+            );
+        }
+
+        if self.schema_version > T::schema_version() {
+            return Err(
+                MetaError::NewerSchemaVersion {
+                    supported: T::schema_version(),
+                    found: self.schema_version,
+                },
+            );
+        }
+
+        if self.schema_version == T::schema_version()
+            && self.schema_fingerprint != T::schema_fingerprint() {
+            return Err(
+                MetaError::FingerprintMismatch {
+                    expected: T::schema_fingerprint(),
+                    found: self.schema_fingerprint,
+                },
+            );
+        }
+
+        if self.epoch_rule != T::epoch_rule() {
+            return Err(
+                MetaError::EpochRuleMismatch {
+                    expected: T::epoch_rule(),
+                    found: self.epoch_rule,
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Whether the symbol was written with an older schema version of `T`.
+    #[inline(always)]
+    pub fn is_outdated<T: Tick>(&self) -> bool {
+        self.schema_version < T::schema_version()
+    }
+
+    /// Records the current schema of `T`, keeping the creation info.
+    #[inline(always)]
+    pub fn upgrade<T: Tick>(&mut self) {
+        self.schema_version = T::schema_version();
+        self.schema_fingerprint = T::schema_fingerprint();
+        self.fields = T::fields();
+    }
+}
+
+/// 64 bit FNV-1a; used for schema fingerprints because, unlike the std
+/// hashers, its output is stable across compiler versions and platforms.
+#[inline(always)]
+pub fn fingerprint(
+    bytes: &[u8],
+) -> u64 {
+    bytes
+        .iter()
+        .fold(
+            0xcbf2_9ce4_8422_2325,
+            |hash, &byte|
+                (hash ^ byte as u64)
+                    .wrapping_mul(0x0100_0000_01b3),
+        )
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use super::backing::encryption::KeyProvider;
+use super::backing::storage::{FsBackend, StorageBackend};
+use super::tier::{Tier, TierPolicy};
+use super::timestamp::TimeWindow;
+
+#[derive(Debug, Clone)]
+pub struct QuotickOptions {
+    /// Create the symbol directory if it does not exist yet.
+    pub create_if_missing: bool,
+
+    /// Deflate level (0 - 10) used when writing epoch and index files.
+    pub compression_level: u8,
+
+    /// Reject frames with times outside this window, see `TimeWindow`.
+    pub time_window: Option<TimeWindow>,
+
+    /// Move old epochs to a cold tier, see `TierPolicy`.
+    pub tiering: Option<TierPolicy>,
+
+    /// Where files are stored, see `StorageBackend`.
+    pub backend: Arc<dyn StorageBackend>,
+
+    /// Encrypt epoch and index files with the keys of this provider, see
+    /// `KeyProvider`.
+    pub encryption: Option<Arc<dyn KeyProvider>>,
+}
+
+impl Default for QuotickOptions {
+    #[inline(always)]
+    fn default() -> Self {
+        QuotickOptions {
+            create_if_missing: true,
+            compression_level: 3,
+            time_window: None,
+            tiering: None,
+            backend: Arc::new(FsBackend),
+            encryption: None,
+        }
+    }
+}
+
+impl QuotickOptions {
+    /// Deflate level used when writing epochs in `tier`.
+    #[inline(always)]
+    pub fn compression_level_for(
+        &self,
+        tier: Tier,
+    ) -> u8 {
+        match (tier, &self.tiering) {
+            (Tier::Cold, Some(tiering)) => tiering.compression_level,
+            _ => self.compression_level,
+        }
+    }
+}
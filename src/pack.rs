@@ -0,0 +1,440 @@
+use std::convert::TryInto;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::backing::storage::StorageBackend;
+use super::epoch_rule::EpochRule;
+use super::path_builder::QuotickPathBuilder;
+
+const PACK_MAGIC: &[u8] = b"QTPK";
+const PACK_VERSION: u32 = 1;
+
+/// entry count, version, magic.
+const TRAILER_LEN: u64 = 16;
+
+/// epoch, offset, length.
+const ENTRY_LEN: u64 = 24;
+
+#[derive(Debug)]
+pub enum PackError {
+    IoError(io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    Truncated,
+    /// The trailer lists more entries than fit the file.
+    Corrupt,
+}
+
+impl From<io::Error> for PackError {
+    #[inline(always)]
+    fn from(err: io::Error) -> Self {
+        PackError::IoError(err)
+    }
+}
+
+/// Month an epoch of `rule` is packed under, as an epoch of
+/// `EpochRule::Month`.
+#[inline(always)]
+pub fn pack_month(
+    rule: &EpochRule,
+    epoch: u64,
+) -> u64 {
+    EpochRule::Month.epoch(rule.start(epoch))
+}
+
+/// Reads the epoch file of `epoch` from the pack of its month, if the
+/// symbol has one containing it.
+pub(crate) fn read_packed(
+    path_builder: &QuotickPathBuilder,
+    rule: &EpochRule,
+    epoch: u64,
+) -> Result<Option<Vec<u8>>, PackError> {
+    let path = path_builder.pack_file(pack_month(rule, epoch));
+
+    if !path_builder.backend().is_file(&path) {
+        return Ok(None);
+    }
+
+    PackFile::open(path_builder.backend().clone(), path)?
+        .read(epoch)
+}
+
+/// Size of the entry of `epoch` in the pack of its month, if any.
+pub(crate) fn packed_len(
+    path_builder: &QuotickPathBuilder,
+    rule: &EpochRule,
+    epoch: u64,
+) -> Option<u64> {
+    let path = path_builder.pack_file(pack_month(rule, epoch));
+
+    if !path_builder.backend().is_file(&path) {
+        return None;
+    }
+
+    PackFile::open(path_builder.backend().clone(), path)
+        .ok()?
+        .entry(epoch)
+        .map(|entry| entry.len)
+}
+
+/// Epoch files of a symbol concatenated into a single file.
+///
+/// The epoch files are stored verbatim, followed by a footer of
+/// `(epoch, offset, length)` entries sorted by epoch and a trailer with
+/// the entry count, the format version and the magic. Opening a pack
+/// only reads the footer. Symbols keep one pack per month, see
+/// `Quotick::pack_sealed`; `Epoch` falls back to the pack when an epoch
+/// has no file of its own.
+pub struct PackFile {
+    backend: Arc<dyn StorageBackend>,
+    path: PathBuf,
+    entries: Vec<PackEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackEntry {
+    pub epoch: u64,
+    pub offset: u64,
+    pub len: u64,
+}
+
+impl PackFile {
+    /// Writes the epoch files in `epochs` to a pack at `path`, atomically
+    /// replacing any existing one.
+    pub fn write<P: AsRef<Path>>(
+        backend: &dyn StorageBackend,
+        path: P,
+        epochs: &[(u64, Vec<u8>)],
+    ) -> Result<(), PackError> {
+        let mut epochs = epochs.iter().collect::<Vec<_>>();
+
+        epochs.sort_by_key(|(epoch, _)| *epoch);
+
+        let mut buf = Vec::new();
+        let mut footer = Vec::with_capacity(epochs.len() * ENTRY_LEN as usize);
+
+        for (epoch, bytes) in epochs.iter() {
+            footer.extend_from_slice(&epoch.to_le_bytes());
+            footer.extend_from_slice(&(buf.len() as u64).to_le_bytes());
+            footer.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+
+            buf.extend_from_slice(bytes);
+        }
+
+        buf.extend_from_slice(&footer);
+        buf.extend_from_slice(&(epochs.len() as u64).to_le_bytes());
+        buf.extend_from_slice(&PACK_VERSION.to_le_bytes());
+        buf.extend_from_slice(PACK_MAGIC);
+
+        backend.replace(path.as_ref(), &buf)?;
+
+        Ok(())
+    }
+
+    /// Opens a pack and reads its footer.
+    pub fn open<P: AsRef<Path>>(
+        backend: Arc<dyn StorageBackend>,
+        path: P,
+    ) -> Result<PackFile, PackError> {
+        let path = path.as_ref().to_path_buf();
+
+        let size = backend.len(&path)?;
+
+        if size < TRAILER_LEN {
+            return Err(PackError::Truncated);
+        }
+
+        let trailer = backend.read_at(&path, size - TRAILER_LEN, TRAILER_LEN as usize)?;
+
+        if &trailer[12..] != PACK_MAGIC {
+            return Err(PackError::BadMagic);
+        }
+
+        let version = u32::from_le_bytes(trailer[8..12].try_into().unwrap());
+
+        if version != PACK_VERSION {
+            return Err(PackError::UnsupportedVersion(version));
+        }
+
+        let count = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+
+        let footer_len =
+            count.checked_mul(ENTRY_LEN)
+                .filter(|footer_len| footer_len.checked_add(TRAILER_LEN).is_some_and(|len| len <= size))
+                .ok_or(PackError::Corrupt)?;
+
+        let footer_start = size - TRAILER_LEN - footer_len;
+
+        let footer = backend.read_at(&path, footer_start, footer_len as usize)?;
+
+        let u64_at = |offset: usize| u64::from_le_bytes(footer[offset..offset + 8].try_into().unwrap());
+
+        let entries =
+            (0..count as usize)
+                .map(|idx| {
+                    PackEntry {
+                        epoch: u64_at(idx * ENTRY_LEN as usize),
+                        offset: u64_at(idx * ENTRY_LEN as usize + 8),
+                        len: u64_at(idx * ENTRY_LEN as usize + 16),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+        let in_bounds =
+            entries
+                .iter()
+                .all(|entry| entry.offset.checked_add(entry.len).is_some_and(|end| end <= footer_start));
+
+        if !in_bounds {
+            return Err(PackError::Truncated);
+        }
+
+        Ok(
+            PackFile {
+                backend,
+                path,
+                entries,
+            },
+        )
+    }
+
+    /// Entries of the pack, sorted by epoch.
+    #[inline(always)]
+    pub fn entries(&self) -> &[PackEntry] {
+        &self.entries
+    }
+
+    #[inline(always)]
+    pub fn contains(
+        &self,
+        epoch: u64,
+    ) -> bool {
+        self.entry(epoch).is_some()
+    }
+
+    #[inline(always)]
+    fn entry(
+        &self,
+        epoch: u64,
+    ) -> Option<&PackEntry> {
+        self.entries
+            .binary_search_by_key(&epoch, |entry| entry.epoch)
+            .ok()
+            .map(|idx| &self.entries[idx])
+    }
+
+    /// Reads the epoch file of `epoch`, as it was before packing.
+    pub fn read(
+        &self,
+        epoch: u64,
+    ) -> Result<Option<Vec<u8>>, PackError> {
+        let entry =
+            match self.entry(epoch) {
+                Some(entry) => *entry,
+                None => return Ok(None),
+            };
+
+        Ok(Some(self.backend.read_at(&self.path, entry.offset, entry.len as usize)?))
+    }
+
+    /// Reads all epoch files of the pack.
+    pub fn read_all(&self) -> Result<Vec<(u64, Vec<u8>)>, PackError> {
+        let epochs =
+            self.entries
+                .iter()
+                .map(|entry| entry.epoch)
+                .collect::<Vec<_>>();
+
+        let mut files = Vec::with_capacity(epochs.len());
+
+        for epoch in epochs {
+            if let Some(bytes) = self.read(epoch)? {
+                files.push((epoch, bytes));
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::super::backing::storage::FsBackend;
+    use super::super::db::QuotickDb;
+    use super::super::retention::RetentionPolicy;
+    use super::super::test_util::{sizes, TempPath};
+    use super::super::tick::Trade;
+    use super::super::Frame;
+    use super::*;
+
+    #[test]
+    fn round_trips_and_validates() {
+        let path = TempPath::new("pack");
+        let backend: Arc<dyn StorageBackend> = Arc::new(FsBackend);
+
+        PackFile::write(&*backend, &path, &[(7, b"seven".to_vec()), (3, b"three".to_vec()), (5, Vec::new())])
+            .unwrap();
+
+        let pack = PackFile::open(backend.clone(), &path).unwrap();
+
+        assert_eq!(pack.entries().iter().map(|entry| entry.epoch).collect::<Vec<_>>(), vec![3, 5, 7]);
+        assert_eq!(pack.read(7).unwrap(), Some(b"seven".to_vec()));
+        assert_eq!(pack.read(5).unwrap(), Some(Vec::new()));
+        assert_eq!(pack.read(4).unwrap(), None);
+        assert_eq!(pack.read_all().unwrap().len(), 3);
+
+        let bytes = fs::read(&path).unwrap();
+
+        fs::write(&path, &bytes[1..]).unwrap();
+
+        assert!(matches!(PackFile::open(backend.clone(), &path), Err(PackError::Truncated)));
+
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+
+        assert!(matches!(PackFile::open(backend.clone(), &path), Err(PackError::BadMagic)));
+
+        // an entry count whose footer length overflows.
+        let mut corrupt = bytes.clone();
+        let trailer = corrupt.len() - TRAILER_LEN as usize;
+
+        corrupt[trailer..trailer + 8].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+
+        fs::write(&path, &corrupt).unwrap();
+
+        assert!(matches!(PackFile::open(backend.clone(), &path), Err(PackError::Corrupt)));
+    }
+
+    #[test]
+    fn packed_epochs_read_like_epoch_files() {
+        const DAY: u64 = 86_400_000_000_000;
+
+        let base_path = TempPath::new("packed");
+        let db = QuotickDb::new(&base_path).unwrap();
+
+        let mut quotick = db.create::<Trade>("SYMBL").unwrap();
+
+        for day in 0..4 {
+            quotick
+                .insert(&Frame::new(day * DAY, Trade { size: day, ..Trade::default() }))
+                .unwrap();
+        }
+
+        for day in 0..3 {
+            quotick.seal(day).unwrap();
+        }
+
+        assert_eq!(quotick.pack_sealed().unwrap(), 3);
+        assert_eq!(quotick.pack_sealed().unwrap(), 0);
+
+        let path_builder = quotick.path_builder().clone();
+
+        assert!(!path_builder.index_backing_file(0).exists());
+        assert!(path_builder.index_backing_file(3).exists());
+        assert_eq!(PackFile::open(path_builder.backend().clone(), path_builder.pack_file(0)).unwrap().entries().len(), 3);
+
+        assert_eq!(sizes(&quotick), vec![0, 1, 2, 3]);
+        assert_eq!(db.open_dyn("SYMBL").unwrap().frames().count(), 4);
+
+        // writing to an unsealed packed epoch gives it a file of its own again.
+        quotick.unseal(1).unwrap();
+
+        quotick
+            .insert(&Frame::new(DAY + 1, Trade { size: 5, ..Trade::default() }))
+            .unwrap();
+
+        quotick.persist().unwrap();
+
+        assert!(path_builder.index_backing_file(1).exists());
+        assert_eq!(sizes(&quotick), vec![0, 1, 5, 2, 3]);
+
+        quotick.seal(1).unwrap();
+
+        assert_eq!(quotick.pack_sealed().unwrap(), 1);
+        assert_eq!(sizes(&quotick), vec![0, 1, 5, 2, 3]);
+    }
+
+    #[test]
+    fn retention_counts_packed_epochs() {
+        const DAY: u64 = 86_400_000_000_000;
+
+        let base_path = TempPath::new("packed-retention");
+        let db = QuotickDb::new(&base_path).unwrap();
+
+        let mut quotick = db.create::<Trade>("SYMBL").unwrap();
+
+        for day in 0..4 {
+            quotick
+                .insert(&Frame::new(day * DAY, Trade { size: day, ..Trade::default() }))
+                .unwrap();
+
+            quotick.seal(day).unwrap();
+        }
+
+        quotick.unseal(3).unwrap();
+
+        assert_eq!(quotick.pack_sealed().unwrap(), 3);
+
+        let path_builder = quotick.path_builder().clone();
+        let backend = path_builder.backend().clone();
+
+        let loose = backend.len(&path_builder.index_backing_file(3)).unwrap();
+
+        quotick.set_retention(Some(RetentionPolicy::max_bytes(loose))).unwrap();
+
+        let report = quotick.enforce_retention().unwrap();
+
+        assert_eq!(report.epochs_removed, 3);
+        assert!(report.bytes_removed > 0);
+        assert_eq!(quotick.epoch_index(), &[3]);
+        assert!(!backend.is_file(&path_builder.pack_file(0)));
+    }
+
+    #[test]
+    fn expired_epochs_leave_their_pack() {
+        const DAY: u64 = 86_400_000_000_000;
+
+        let base_path = TempPath::new("packed-expiry");
+        let db = QuotickDb::new(&base_path).unwrap();
+
+        let mut quotick = db.create::<Trade>("SYMBL").unwrap();
+
+        for day in 0..3 {
+            quotick
+                .insert(&Frame::new(day * DAY, Trade { size: day + 1, ..Trade::default() }))
+                .unwrap();
+
+            quotick.seal(day).unwrap();
+        }
+
+        assert_eq!(quotick.pack_sealed().unwrap(), 3);
+
+        let path_builder = quotick.path_builder().clone();
+        let pack = PackFile::open(path_builder.backend().clone(), path_builder.pack_file(0)).unwrap();
+
+        let total = pack.entries().iter().map(|entry| entry.len).sum::<u64>();
+
+        // only the oldest epoch expires, the pack stays for the others.
+        quotick.set_retention(Some(RetentionPolicy::max_bytes(total - pack.entries()[0].len))).unwrap();
+
+        assert_eq!(quotick.enforce_retention().unwrap().epochs_removed, 1);
+
+        let pack = PackFile::open(path_builder.backend().clone(), path_builder.pack_file(0)).unwrap();
+
+        assert!(!pack.contains(0));
+        assert_eq!(sizes(&quotick), vec![2, 3]);
+
+        // a late insert into the expired epoch starts it afresh.
+        quotick.set_retention(None).unwrap();
+        quotick
+            .insert(&Frame::new(1, Trade { size: 9, ..Trade::default() }))
+            .unwrap();
+
+        quotick.persist().unwrap();
+
+        assert_eq!(sizes(&quotick), vec![9, 2, 3]);
+        assert_eq!(sizes(&db.open::<Trade>("SYMBL").unwrap()), vec![9, 2, 3]);
+    }
+}
@@ -1,29 +1,86 @@
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::backing::encryption::KeyProvider;
+use super::backing::storage::{FsBackend, StorageBackend};
+use super::symbol::Symbol;
+use super::tier::{Tier, TierPolicy};
 
 #[derive(Debug, Clone)]
 pub struct QuotickPathBuilder {
+    symbol: Symbol,
     asset_path: PathBuf,
     base_path: PathBuf,
     epoch_path: PathBuf,
+    cold_epoch_path: Option<PathBuf>,
+    backend: Arc<dyn StorageBackend>,
+    keys: Option<Arc<dyn KeyProvider>>,
 }
 
 impl QuotickPathBuilder {
     #[inline(always)]
     pub fn new(
-        asset: &str,
+        asset: &Symbol,
         path: impl AsRef<Path>,
     ) -> QuotickPathBuilder {
         let base_path = path.as_ref().to_path_buf();
-        let asset_path = base_path.join(asset);
+        let asset_path = base_path.join(asset.dir_name());
         let epoch_path = asset_path.join("epoch");
 
         QuotickPathBuilder {
+            symbol: asset.clone(),
             base_path: base_path.to_path_buf(),
             asset_path: asset_path.to_path_buf(),
             epoch_path: epoch_path.to_path_buf(),
+            cold_epoch_path: None,
+            backend: Arc::new(FsBackend),
+            keys: None,
         }
     }
 
+    /// Stores the paths built here in `backend`, instead of the
+    /// filesystem.
+    #[inline(always)]
+    pub fn with_backend(
+        mut self,
+        backend: Arc<dyn StorageBackend>,
+    ) -> QuotickPathBuilder {
+        self.backend = backend;
+
+        self
+    }
+
+    /// Encrypts the backing files opened for these paths with `keys`.
+    #[inline(always)]
+    pub fn with_keys(
+        mut self,
+        keys: Option<Arc<dyn KeyProvider>>,
+    ) -> QuotickPathBuilder {
+        self.keys = keys;
+
+        self
+    }
+
+    /// Resolves epochs across the hot tier and the cold tier of `tiering`,
+    /// if any.
+    #[inline(always)]
+    pub fn with_tiering(
+        mut self,
+        tiering: Option<&TierPolicy>,
+    ) -> QuotickPathBuilder {
+        self.cold_epoch_path =
+            tiering.map(|tiering| {
+                tiering.cold_path
+                    .join(self.asset_path.strip_prefix(&self.base_path).unwrap_or(&self.asset_path))
+                    .join("epoch")
+            });
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn symbol(&self) -> &Symbol { &self.symbol }
+
     #[inline(always)]
     pub fn base_path(&self) -> PathBuf { self.base_path.to_path_buf() }
 
@@ -33,14 +90,47 @@ impl QuotickPathBuilder {
     #[inline(always)]
     pub fn epoch_path(&self) -> PathBuf { self.epoch_path.to_path_buf() }
 
+    #[inline(always)]
+    pub fn cold_epoch_path(&self) -> Option<PathBuf> { self.cold_epoch_path.clone() }
+
+    #[inline(always)]
+    pub fn backend(&self) -> &Arc<dyn StorageBackend> { &self.backend }
+
+    #[inline(always)]
+    pub fn keys(&self) -> Option<&Arc<dyn KeyProvider>> { self.keys.as_ref() }
+
+    /// The tier `epoch` is stored in; epochs that do not exist yet are hot.
+    #[inline(always)]
+    pub fn tier(
+        &self,
+        epoch: u64,
+    ) -> Tier {
+        match self.cold_index_backing_file(epoch) {
+            Some(cold) if !self.backend.is_file(&self.hot_index_backing_file(epoch)) && self.backend.is_file(&cold) => Tier::Cold,
+            _ => Tier::Hot,
+        }
+    }
+
+    /// The epoch file of `epoch` in the tier it is stored in.
     #[inline(always)]
     pub fn index_backing_file(
         &self,
         epoch: u64,
+    ) -> PathBuf {
+        match self.tier(epoch) {
+            Tier::Hot => self.hot_index_backing_file(epoch),
+            Tier::Cold => self.cold_index_backing_file(epoch).unwrap(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn hot_index_backing_file(
+        &self,
+        epoch: u64,
     ) -> PathBuf {
         self.epoch_path
             .join(
-                &format!(
+                format!(
                     "{}.qtf",
                     epoch,
                 ),
@@ -48,6 +138,61 @@ impl QuotickPathBuilder {
             .to_path_buf()
     }
 
+    #[inline(always)]
+    pub fn cold_index_backing_file(
+        &self,
+        epoch: u64,
+    ) -> Option<PathBuf> {
+        self.cold_epoch_path
+            .as_ref()
+            .map(|cold_epoch_path| {
+                cold_epoch_path
+                    .join(
+                        format!(
+                            "{}.qtf",
+                            epoch,
+                        ),
+                    )
+            })
+    }
+
+    /// Zero-copy archive of an epoch, see `ArchivedEpoch`. Archives
+    /// always live in the hot tier.
+    #[inline(always)]
+    pub fn archive_file(
+        &self,
+        epoch: u64,
+    ) -> PathBuf {
+        self.epoch_path
+            .join(
+                format!(
+                    "{}.qta",
+                    epoch,
+                ),
+            )
+            .to_path_buf()
+    }
+
+    #[inline(always)]
+    pub fn pack_path(&self) -> PathBuf { self.asset_path.join("pack") }
+
+    /// Pack of the epochs of `month`, an epoch of `EpochRule::Month`, see
+    /// `PackFile`.
+    #[inline(always)]
+    pub fn pack_file(
+        &self,
+        month: u64,
+    ) -> PathBuf {
+        self.pack_path()
+            .join(
+                format!(
+                    "{:04}-{:02}.qtp",
+                    1970 + month / 12,
+                    month % 12 + 1,
+                ),
+            )
+    }
+
     #[inline(always)]
     pub fn epoch_index_backing_file(
         &self,
@@ -58,4 +203,39 @@ impl QuotickPathBuilder {
             )
             .to_path_buf()
     }
+
+    #[inline(always)]
+    pub fn meta_backing_file(
+        &self,
+    ) -> PathBuf {
+        self.asset_path
+            .join(
+                "meta.qtf"
+            )
+            .to_path_buf()
+    }
+
+    /// Overwrite and duplicate counters of the symbol, see `EpochCounters`.
+    #[inline(always)]
+    pub fn counters_backing_file(
+        &self,
+    ) -> PathBuf {
+        self.asset_path
+            .join(
+                "counters.qtf"
+            )
+            .to_path_buf()
+    }
+
+    /// Retention policy of the symbol, see `RetentionPolicy`.
+    #[inline(always)]
+    pub fn retention_backing_file(
+        &self,
+    ) -> PathBuf {
+        self.asset_path
+            .join(
+                "retention.qtf"
+            )
+            .to_path_buf()
+    }
 }
@@ -0,0 +1,406 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PriceError {
+    Empty,
+    BadDigit,
+    TooManyDecimals,
+    Overflow,
+}
+
+/// A fixed-point decimal price with `SCALE` decimal places.
+///
+/// Stored as an `i64` count of `10^-SCALE` units, so every price with at
+/// most `SCALE` decimals is represented exactly. The default scale of 8
+/// covers satoshi-denominated crypto prices while still reaching past
+/// 92 billion. Serialized as the raw `i64`.
+///
+/// The arithmetic operators panic on overflow, in release builds, too;
+/// use the `checked_*` methods for prices that may get that large.
+///
+/// ```
+/// use quotick::price::Price;
+///
+/// let bid: Price = "100234.25".parse().unwrap();
+/// let ask = bid + Price::from_raw(25_000_000);
+///
+/// assert_eq!(ask.to_string(), "100234.50000000");
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+#[repr(transparent)]
+pub struct Price<const SCALE: u32 = 8> {
+    raw: i64,
+}
+
+impl<const SCALE: u32> Price<SCALE> {
+    pub const ZERO: Price<SCALE> = Price { raw: 0 };
+    pub const UNIT: i64 = 10i64.pow(SCALE);
+
+    #[inline(always)]
+    pub const fn from_raw(
+        raw: i64,
+    ) -> Price<SCALE> {
+        Price {
+            raw,
+        }
+    }
+
+    #[inline(always)]
+    pub const fn raw(&self) -> i64 {
+        self.raw
+    }
+
+    #[inline(always)]
+    pub const fn scale(&self) -> u32 {
+        SCALE
+    }
+
+    #[inline(always)]
+    pub fn from_int(
+        units: i64,
+    ) -> Option<Price<SCALE>> {
+        units
+            .checked_mul(Self::UNIT)
+            .map(Price::from_raw)
+    }
+
+    /// Converts a float, rounding to the nearest representable price.
+    ///
+    /// Used to migrate prices stored as `f32`/`f64`; the result is only as
+    /// exact as the float was.
+    #[inline(always)]
+    pub fn from_f64(
+        value: f64,
+    ) -> Option<Price<SCALE>> {
+        let raw = (value * Self::UNIT as f64).round();
+
+        // `i64::MAX as f64` rounds up to 2^63, which is out of range.
+        if !raw.is_finite() || raw < i64::MIN as f64 || raw >= i64::MAX as f64 {
+            return None;
+        }
+
+        Some(Price::from_raw(raw as i64))
+    }
+
+    #[inline(always)]
+    pub fn to_f64(&self) -> f64 {
+        self.raw as f64 / Self::UNIT as f64
+    }
+
+    /// Converts to a different scale, truncating surplus decimals.
+    #[inline(always)]
+    pub fn rescale<const TO: u32>(&self) -> Option<Price<TO>> {
+        if TO >= SCALE {
+            self.raw
+                .checked_mul(10i64.pow(TO - SCALE))
+                .map(Price::from_raw)
+        } else {
+            Some(Price::from_raw(self.raw / 10i64.pow(SCALE - TO)))
+        }
+    }
+
+    #[inline(always)]
+    pub fn checked_add(
+        self,
+        other: Price<SCALE>,
+    ) -> Option<Price<SCALE>> {
+        self.raw
+            .checked_add(other.raw)
+            .map(Price::from_raw)
+    }
+
+    #[inline(always)]
+    pub fn checked_sub(
+        self,
+        other: Price<SCALE>,
+    ) -> Option<Price<SCALE>> {
+        self.raw
+            .checked_sub(other.raw)
+            .map(Price::from_raw)
+    }
+
+    #[inline(always)]
+    pub fn checked_mul_int(
+        self,
+        factor: i64,
+    ) -> Option<Price<SCALE>> {
+        self.raw
+            .checked_mul(factor)
+            .map(Price::from_raw)
+    }
+
+    /// Divides by an integer, truncating surplus decimals.
+    #[inline(always)]
+    pub fn checked_div_int(
+        self,
+        divisor: i64,
+    ) -> Option<Price<SCALE>> {
+        self.raw
+            .checked_div(divisor)
+            .map(Price::from_raw)
+    }
+
+    #[inline(always)]
+    pub fn checked_neg(self) -> Option<Price<SCALE>> {
+        self.raw
+            .checked_neg()
+            .map(Price::from_raw)
+    }
+
+    /// Multiplies two prices, truncating surplus decimals.
+    #[inline(always)]
+    pub fn checked_mul(
+        self,
+        other: Price<SCALE>,
+    ) -> Option<Price<SCALE>> {
+        let raw = self.raw as i128 * other.raw as i128 / Self::UNIT as i128;
+
+        if raw < i64::MIN as i128 || raw > i64::MAX as i128 {
+            return None;
+        }
+
+        Some(Price::from_raw(raw as i64))
+    }
+
+    /// Divides two prices, truncating surplus decimals.
+    #[inline(always)]
+    pub fn checked_div(
+        self,
+        other: Price<SCALE>,
+    ) -> Option<Price<SCALE>> {
+        if other.raw == 0 {
+            return None;
+        }
+
+        let raw = self.raw as i128 * Self::UNIT as i128 / other.raw as i128;
+
+        if raw < i64::MIN as i128 || raw > i64::MAX as i128 {
+            return None;
+        }
+
+        Some(Price::from_raw(raw as i64))
+    }
+
+    /// Panics for the smallest price, which has no positive counterpart.
+    #[inline(always)]
+    pub fn abs(&self) -> Price<SCALE> {
+        Price::from_raw(self.raw.checked_abs().expect("price overflow"))
+    }
+}
+
+impl<const SCALE: u32> Add for Price<SCALE> {
+    type Output = Price<SCALE>;
+
+    #[inline(always)]
+    fn add(self, other: Price<SCALE>) -> Price<SCALE> {
+        self.checked_add(other).expect("price overflow")
+    }
+}
+
+impl<const SCALE: u32> AddAssign for Price<SCALE> {
+    #[inline(always)]
+    fn add_assign(&mut self, other: Price<SCALE>) {
+        *self = *self + other;
+    }
+}
+
+impl<const SCALE: u32> Sub for Price<SCALE> {
+    type Output = Price<SCALE>;
+
+    #[inline(always)]
+    fn sub(self, other: Price<SCALE>) -> Price<SCALE> {
+        self.checked_sub(other).expect("price overflow")
+    }
+}
+
+impl<const SCALE: u32> SubAssign for Price<SCALE> {
+    #[inline(always)]
+    fn sub_assign(&mut self, other: Price<SCALE>) {
+        *self = *self - other;
+    }
+}
+
+impl<const SCALE: u32> Neg for Price<SCALE> {
+    type Output = Price<SCALE>;
+
+    #[inline(always)]
+    fn neg(self) -> Price<SCALE> {
+        self.checked_neg().expect("price overflow")
+    }
+}
+
+impl<const SCALE: u32> Mul<i64> for Price<SCALE> {
+    type Output = Price<SCALE>;
+
+    #[inline(always)]
+    fn mul(self, factor: i64) -> Price<SCALE> {
+        self.checked_mul_int(factor).expect("price overflow")
+    }
+}
+
+impl<const SCALE: u32> Div<i64> for Price<SCALE> {
+    type Output = Price<SCALE>;
+
+    #[inline(always)]
+    fn div(self, divisor: i64) -> Price<SCALE> {
+        self.checked_div_int(divisor).expect("price overflow or division by zero")
+    }
+}
+
+impl<const SCALE: u32> Sum for Price<SCALE> {
+    #[inline(always)]
+    fn sum<I: Iterator<Item=Price<SCALE>>>(iter: I) -> Price<SCALE> {
+        iter.fold(
+            Price::ZERO,
+            |sum, price| sum + price,
+        )
+    }
+}
+
+impl<const SCALE: u32> fmt::Display for Price<SCALE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.raw < 0 { "-" } else { "" };
+        let raw = self.raw.unsigned_abs();
+        let unit = Self::UNIT as u64;
+
+        if SCALE == 0 {
+            return write!(f, "{}{}", sign, raw);
+        }
+
+        write!(
+            f,
+            "{}{}.{:0width$}",
+            sign,
+            raw / unit,
+            raw % unit,
+            width = SCALE as usize,
+        )
+    }
+}
+
+impl<const SCALE: u32> fmt::Debug for Price<SCALE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Price({})", self)
+    }
+}
+
+impl<const SCALE: u32> FromStr for Price<SCALE> {
+    type Err = PriceError;
+
+    /// Parses a decimal string such as `-123.45` exactly.
+    fn from_str(value: &str) -> Result<Price<SCALE>, PriceError> {
+        let (negative, digits) =
+            match value.strip_prefix('-') {
+                Some(digits) => (true, digits),
+                None => (false, value.strip_prefix('+').unwrap_or(value)),
+            };
+
+        let mut parts = digits.splitn(2, '.');
+
+        let int_part = parts.next().unwrap_or("");
+        let frac_part = parts.next().unwrap_or("");
+
+        if int_part.is_empty() && frac_part.is_empty() {
+            return Err(PriceError::Empty);
+        }
+
+        if frac_part.len() > SCALE as usize {
+            return Err(PriceError::TooManyDecimals);
+        }
+
+        let parse_digits =
+            |digits: &str|
+                digits
+                    .bytes()
+                    .try_fold(
+                        0i64,
+                        |acc, digit| {
+                            if !digit.is_ascii_digit() {
+                                return Err(PriceError::BadDigit);
+                            }
+
+                            acc.checked_mul(10)
+                                .and_then(|acc| acc.checked_add((digit - b'0') as i64))
+                                .ok_or(PriceError::Overflow)
+                        },
+                    );
+
+        let frac =
+            parse_digits(frac_part)?
+                .checked_mul(10i64.pow(SCALE - frac_part.len() as u32))
+                .ok_or(PriceError::Overflow)?;
+
+        let raw =
+            parse_digits(int_part)?
+                .checked_mul(Self::UNIT)
+                .and_then(|raw| raw.checked_add(frac))
+                .ok_or(PriceError::Overflow)?;
+
+        Ok(Price::from_raw(if negative { -raw } else { raw }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_and_formats_exactly() {
+        let price: Price = "123456.78901234".parse().unwrap();
+
+        assert_eq!(price.raw(), 12_345_678_901_234);
+        assert_eq!(price.to_string(), "123456.78901234");
+
+        assert_eq!("-0.5".parse::<Price<2>>().unwrap().to_string(), "-0.50");
+        assert_eq!(".25".parse::<Price<2>>().unwrap().raw(), 25);
+        assert_eq!("7".parse::<Price<0>>().unwrap().to_string(), "7");
+
+        assert_eq!("1.234".parse::<Price<2>>(), Err(PriceError::TooManyDecimals));
+        assert_eq!("1,5".parse::<Price<2>>(), Err(PriceError::BadDigit));
+        assert_eq!("".parse::<Price<2>>(), Err(PriceError::Empty));
+        assert_eq!("99999999999999".parse::<Price>(), Err(PriceError::Overflow));
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a: Price<4> = "100000.0001".parse().unwrap();
+        let b: Price<4> = "0.0002".parse().unwrap();
+
+        assert_eq!((a + b).to_string(), "100000.0003");
+        assert_eq!((a - b).to_string(), "99999.9999");
+        assert_eq!((b * 3).to_string(), "0.0006");
+        assert_eq!(a.checked_mul(Price::from_int(2).unwrap()).unwrap().to_string(), "200000.0002");
+        assert_eq!(Price::<4>::from_int(1).unwrap().checked_div(Price::from_int(3).unwrap()).unwrap().raw(), 3333);
+        assert_eq!(vec![a, b, -b].into_iter().sum::<Price<4>>(), a);
+
+        assert_eq!(a.rescale::<2>().unwrap().to_string(), "100000.00");
+        assert_eq!(b.rescale::<6>().unwrap().raw(), 200);
+
+        let max = Price::<4>::from_raw(i64::MAX);
+
+        assert_eq!(max.checked_add(b), None);
+        assert_eq!(Price::<4>::from_raw(i64::MIN).checked_neg(), None);
+        assert_eq!(a.checked_div_int(0), None);
+        assert!(std::panic::catch_unwind(|| max + b).is_err());
+    }
+
+    #[test]
+    fn migrates_floats() {
+        // f32 cannot represent 100234.26, the nearest float is 100234.2578125.
+        assert_eq!(Price::<2>::from_f64(100_234.26f32 as f64).unwrap().to_string(), "100234.26");
+        assert_eq!(Price::<2>::from_f64(f64::NAN), None);
+        assert_eq!(Price::<0>::from_f64(i64::MAX as f64), None);
+        assert_eq!(Price::<0>::from_f64(i64::MIN as f64).unwrap().raw(), i64::MIN);
+
+        let price: Price<2> = Price::from_f64(1.1).unwrap();
+
+        assert_eq!(bincode::deserialize::<Price<2>>(&bincode::serialize(&price).unwrap()).unwrap(), price);
+        assert_eq!(bincode::serialize(&price).unwrap(), 110i64.to_le_bytes());
+    }
+}
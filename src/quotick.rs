@@ -1,25 +1,63 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
 use std::path::Path;
 use std::slice::Iter;
+use std::sync::Arc;
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use super::Frame;
 
-use super::backing::backing_file::BackingFile;
+use super::archive::{ArchiveError, ArchivedEpoch, FixedLayout};
+use super::backing::backing_file::{decode_raw, encode_raw, encode_versioned, BackingFile};
+use super::backing::encryption::KeyProvider;
+use super::columns::{ColumnSink, Columnar};
+use super::dyn_tick::{DynError, DynQuotick};
 use super::epoch::Epoch;
-use super::epoch::EpochError;
+use super::epoch::{EpochError, FrameIndex};
+use super::epoch_index::EpochIndex;
+use super::export::{ExportError, ExportHeader, ExportWriter};
+use super::frame::FrameRef;
+use super::meta::{MetaError, SymbolMeta, META_FORMAT_VERSION};
+use super::options::QuotickOptions;
+use super::pack::{pack_month, packed_len, read_packed, PackError, PackFile};
 use super::path_builder::QuotickPathBuilder;
+use super::radix_trie::TrieCommon;
+use super::retention::{self, OpenSymbol, RetentionPolicy, RetentionReport};
+use super::schema::SchemaUpgrades;
+use super::sealed::{SealedEpoch, SealedError};
+use super::stats::{EpochCounters, EpochStats, QuotickStats};
+use super::symbol::{Symbol, SymbolError};
+use super::tier::{Tier, TieringReport};
+use super::timestamp::{TimeWindow, Timestamp};
 use super::Tick;
 
 #[derive(Debug)]
 pub enum QuotickError {
     Epoch(EpochError),
+    Dyn(DynError),
+    InvalidSymbol(SymbolError),
+    Meta(MetaError),
     BackingFileFailure,
+    MetaFileFailure,
     BadFrameEpoch,
     BadFrameTick,
+    Archive(ArchiveError),
+    Pack(PackError),
+    Export(ExportError),
+    Sealed(SealedError),
+    EpochNotFound(u64),
+    /// The epoch is sealed, see `Quotick::seal`.
+    EpochSealed(u64),
+    /// Tiering was requested without a `TierPolicy` in the options.
+    NoColdTier,
+    /// The frame time lies outside the configured `TimeWindow`.
+    ImplausibleTime(u64),
     Inconsistency,
+    SymbolNotFound,
 }
 
 impl From<EpochError> for QuotickError {
@@ -29,23 +67,190 @@ impl From<EpochError> for QuotickError {
     }
 }
 
+impl From<ArchiveError> for QuotickError {
+    #[inline(always)]
+    fn from(err: ArchiveError) -> Self {
+        QuotickError::Archive(err)
+    }
+}
+
+impl From<PackError> for QuotickError {
+    #[inline(always)]
+    fn from(err: PackError) -> Self {
+        QuotickError::Pack(err)
+    }
+}
+
+impl From<ExportError> for QuotickError {
+    #[inline(always)]
+    fn from(err: ExportError) -> Self {
+        QuotickError::Export(err)
+    }
+}
+
+impl From<SealedError> for QuotickError {
+    #[inline(always)]
+    fn from(err: SealedError) -> Self {
+        QuotickError::Sealed(err)
+    }
+}
+
+impl From<DynError> for QuotickError {
+    #[inline(always)]
+    fn from(err: DynError) -> Self {
+        QuotickError::Dyn(err)
+    }
+}
+
+impl From<MetaError> for QuotickError {
+    #[inline(always)]
+    fn from(err: MetaError) -> Self {
+        QuotickError::Meta(err)
+    }
+}
+
+impl From<SymbolError> for QuotickError {
+    #[inline(always)]
+    fn from(err: SymbolError) -> Self {
+        QuotickError::InvalidSymbol(err)
+    }
+}
+
 pub fn init_paths(
     path_builder: &QuotickPathBuilder,
-) {
-    std::fs::create_dir_all(
-        path_builder
-            .epoch_path(),
-    );
+) -> Result<(), QuotickError> {
+    path_builder
+        .backend()
+        .create_dir_all(
+            &path_builder
+                .epoch_path(),
+        )
+        .map_err(|_| QuotickError::BackingFileFailure)
+}
+
+/// Reads the metadata of a symbol.
+pub fn read_meta(
+    meta_backing: &mut BackingFile<SymbolMeta>,
+) -> Result<SymbolMeta, QuotickError> {
+    let (version, buf) =
+        meta_backing.try_read_raw()
+            .map_err(|_| QuotickError::MetaFileFailure)?;
+
+    if version != META_FORMAT_VERSION {
+        return Err(MetaError::UnsupportedFormat(version).into());
+    }
+
+    bincode::deserialize::<SymbolMeta>(&buf)
+        .map_err(|_| QuotickError::MetaFileFailure)
+}
+
+pub fn write_meta(
+    meta_backing: &mut BackingFile<SymbolMeta>,
+    meta: &SymbolMeta,
+) -> Result<(), QuotickError> {
+    meta_backing
+        .write_versioned(
+            meta,
+            META_FORMAT_VERSION,
+        )
+        .map_err(|_| QuotickError::MetaFileFailure)
+}
+
+#[inline(always)]
+fn open_meta_backing(
+    path_builder: &QuotickPathBuilder,
+) -> Result<BackingFile<SymbolMeta>, QuotickError> {
+    BackingFile::<SymbolMeta>::with_backend(
+        path_builder.backend().clone(),
+        path_builder.keys().cloned(),
+        path_builder.meta_backing_file(),
+    )
+        .map_err(|_| QuotickError::MetaFileFailure)
+}
+
+/// Reads the metadata of a symbol, initializing it for `T` if the symbol
+/// has none yet, and verifies that it matches `T`.
+///
+/// A symbol written with an older schema of `T` keeps it recorded until
+/// `Quotick::migrate` has rewritten its epochs, so `DynQuotick` never
+/// reads old epochs with the new fields.
+pub fn load_meta<T: Tick>(
+    path_builder: &QuotickPathBuilder,
+) -> Result<SymbolMeta, QuotickError> {
+    let mut meta_backing = open_meta_backing(path_builder)?;
+
+    let is_empty =
+        meta_backing.is_empty()
+            .map_err(|_| QuotickError::MetaFileFailure)?;
+
+    if is_empty {
+        let meta = SymbolMeta::for_tick::<T>();
+
+        write_meta(
+            &mut meta_backing,
+            &meta,
+        )?;
+
+        return Ok(meta);
+    }
+
+    let meta = read_meta(&mut meta_backing)?;
+
+    meta.check::<T>()?;
+
+    Ok(meta)
+}
+
+/// Outcome of `Quotick::compact` and `Quotick::compact_all`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CompactionReport {
+    /// Epochs rewritten, including empty ones that were removed.
+    pub epochs: usize,
+    /// Frames left in the compacted epochs.
+    pub frames: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+impl CompactionReport {
+    #[inline(always)]
+    pub fn bytes_reclaimed(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+
+    #[inline(always)]
+    fn add(
+        &mut self,
+        other: CompactionReport,
+    ) {
+        self.epochs += other.epochs;
+        self.frames += other.frames;
+        self.bytes_before += other.bytes_before;
+        self.bytes_after += other.bytes_after;
+    }
 }
 
 pub struct Quotick<T: Tick + Serialize + DeserializeOwned> {
-    epoch_index_backing: BackingFile<Vec<u64>>,
+    meta: SymbolMeta,
+
+    epoch_index_backing: BackingFile<EpochIndex>,
     pub(crate) epoch_index: Vec<u64>,
+    sealed: Vec<u64>,
+
+    counters_backing: BackingFile<BTreeMap<u64, EpochCounters>>,
+    counters: BTreeMap<u64, EpochCounters>,
+    counters_tainted: bool,
 
     curr_epoch: (u64, Option<Epoch<T>>),
 
     path_builder: QuotickPathBuilder,
 
+    options: QuotickOptions,
+
+    upgrades: SchemaUpgrades<T>,
+
+    open_symbol: Arc<OpenSymbol>,
+
     _phantom: PhantomData<T>,
 }
 
@@ -55,35 +260,105 @@ impl<T: Tick + Serialize + DeserializeOwned> Quotick<T> {
         asset: &str,
         base_path: impl AsRef<Path>,
     ) -> Result<Quotick<T>, QuotickError> {
+        Quotick::with_options(
+            asset,
+            base_path,
+            QuotickOptions::default(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn with_options(
+        asset: &str,
+        base_path: impl AsRef<Path>,
+        options: QuotickOptions,
+    ) -> Result<Quotick<T>, QuotickError> {
+        let symbol = Symbol::new(asset)?;
+
         let path_builder =
             QuotickPathBuilder::new(
-                &asset,
+                &symbol,
                 base_path,
-            );
+            )
+                .with_tiering(options.tiering.as_ref())
+                .with_backend(options.backend.clone())
+                .with_keys(options.encryption.clone());
+
+        if !options.create_if_missing
+            && !path_builder.backend().is_file(&path_builder.epoch_index_backing_file()) {
+            return Err(QuotickError::SymbolNotFound);
+        }
+
+        // waits for `QuotickDb::enforce_retention` to finish with the
+        // symbol, before its index is read.
+        let open_symbol = OpenSymbol::register(&path_builder);
 
         init_paths(
             &path_builder,
-        );
+        )?;
+
+        let meta =
+            load_meta::<T>(
+                &path_builder,
+            )?;
 
         let mut epoch_index_backing =
-            BackingFile::<Vec<u64>>::new(
+            BackingFile::<EpochIndex>::with_backend(
+                path_builder.backend().clone(),
+                path_builder.keys().cloned(),
                 path_builder.epoch_index_backing_file(),
             )
                 .map_err(|_| QuotickError::BackingFileFailure)?;
 
-        let epoch_index =
-            epoch_index_backing.try_read()
-                .unwrap_or_else(|_| Vec::new());
+        epoch_index_backing
+            .set_compression_level(
+                options.compression_level,
+            );
+
+        let EpochIndex { epochs: epoch_index, sealed } =
+            EpochIndex::read(&mut epoch_index_backing)
+                .unwrap_or_default();
+
+        let mut counters_backing =
+            BackingFile::<BTreeMap<u64, EpochCounters>>::with_backend(
+                path_builder.backend().clone(),
+                path_builder.keys().cloned(),
+                path_builder.counters_backing_file(),
+            )
+                .map_err(|_| QuotickError::BackingFileFailure)?;
+
+        let counters =
+            counters_backing.try_read()
+                .unwrap_or_default();
+
+        let mut upgrades = SchemaUpgrades::new();
+
+        T::register_upgrades(
+            &mut upgrades,
+        );
 
         Ok(
             Quotick {
+                meta,
+
                 epoch_index_backing,
                 epoch_index,
+                sealed,
+
+                counters_backing,
+                counters,
+                counters_tainted: false,
 
                 curr_epoch: (0, None),
 
                 path_builder,
 
+                options,
+
+                upgrades,
+
+                open_symbol,
+
                 _phantom: PhantomData,
             },
         )
@@ -95,8 +370,18 @@ impl<T: Tick + Serialize + DeserializeOwned> Quotick<T> {
         frame: &Frame<T>,
         force_overwrite: bool,
     ) -> Result<(), QuotickError> {
+        if let Some(window) = &self.options.time_window {
+            if !window.contains(frame.time()) {
+                return Err(QuotickError::ImplausibleTime(frame.time()));
+            }
+        }
+
         let frame_epoch = frame.epoch();
 
+        if self.is_sealed(frame_epoch) {
+            return Err(QuotickError::EpochSealed(frame_epoch));
+        }
+
         if self.needs_epoch_update(frame_epoch) {
             self.load_epoch(
                 frame_epoch,
@@ -106,19 +391,33 @@ impl<T: Tick + Serialize + DeserializeOwned> Quotick<T> {
         let curr_epoch =
             &mut self.curr_epoch;
 
-        let ref mut frame_set =
+        let frame_set =
             curr_epoch.1
                 .as_mut()
                 .ok_or(QuotickError::BadFrameTick)?;
 
-        frame_set
-            .insert(
-                frame,
-                force_overwrite,
-            )
-            .map_err(|err|
-                QuotickError::Epoch(err)
-            )
+        let exists = frame_set.get(frame.time()).is_some();
+
+        let res =
+            frame_set
+                .insert(
+                    frame,
+                    force_overwrite,
+                )
+                .map_err(QuotickError::Epoch);
+
+        if exists {
+            let counters = self.counters.entry(frame_epoch).or_default();
+
+            match res {
+                Ok(_) => counters.overwrites += 1,
+                Err(_) => counters.duplicates += 1,
+            }
+
+            self.counters_tainted = true;
+        }
+
+        res
     }
 
     #[inline(always)]
@@ -132,8 +431,9 @@ impl<T: Tick + Serialize + DeserializeOwned> Quotick<T> {
         )
     }
 
+    /// Whether inserting into `epoch` loads it first, i.e. does file I/O.
     #[inline(always)]
-    fn needs_epoch_update(
+    pub(crate) fn needs_epoch_update(
         &self,
         epoch: u64,
     ) -> bool {
@@ -157,6 +457,8 @@ impl<T: Tick + Serialize + DeserializeOwned> Quotick<T> {
                     Epoch::new(
                         epoch,
                         self.path_builder.clone(),
+                        &self.options,
+                        &self.upgrades,
                     )?,
                 ),
             );
@@ -191,90 +493,1325 @@ impl<T: Tick + Serialize + DeserializeOwned> Quotick<T> {
         Ok(())
     }
 
+    /// Writes the epoch index, the loaded epoch and the counters.
+    ///
+    /// Also enforces retention if `QuotickDb::enforce_retention` ran while
+    /// the symbol was open.
     #[inline(always)]
     pub fn persist(&mut self) -> Result<(), QuotickError> {
-        let epoch_index = &mut self.epoch_index;
+        if self.open_symbol.take_retention_due() {
+            self.enforce_retention()?;
+        }
+
+        let epoch_index =
+            EpochIndex {
+                epochs: self.epoch_index.clone(),
+                sealed: self.sealed.clone(),
+            };
+
         let curr_epoch = &mut self.curr_epoch;
 
-        self.epoch_index_backing
-            .write_all(
-                &epoch_index,
-            );
+        epoch_index
+            .write(
+                &mut self.epoch_index_backing,
+            )
+            .map_err(|_| QuotickError::BackingFileFailure)?;
+
+        if let Some(epoch) = curr_epoch.1.as_mut() {
+            epoch.persist()?;
+        }
 
-        if let Some(ref mut epoch) = curr_epoch.1 {
-            epoch.persist();
+        if self.counters_tainted {
+            self.counters_backing
+                .write_all(&self.counters)
+                .map_err(|_| QuotickError::BackingFileFailure)?;
+
+            self.counters_tainted = false;
         }
 
         Ok(())
     }
 
+    /// Registers an upgrade from ticks stored with schema `version`,
+    /// in addition to those registered by `Tick::register_upgrades`.
     #[inline(always)]
-    pub fn oldest_frame(&self) -> Option<Frame<T>> {
-        let mut epoch = self.epochs().next()?;
+    pub fn register_upgrade<O, F>(
+        &mut self,
+        version: u32,
+        upgrade: F,
+    )
+        where
+            O: DeserializeOwned,
+            F: Fn(O) -> T + Send + Sync + 'static,
+    {
+        self.upgrades
+            .register(
+                version,
+                upgrade,
+            );
+    }
 
-        let res = epoch.frames().next();
+    /// Rewrites every epoch stored with an older schema version using the
+    /// current layout of `T`, except for sealed ones. Returns the number
+    /// of migrated epochs.
+    ///
+    /// Once no epoch is left in an older schema, the symbol metadata
+    /// records the current one; sealed epochs must be unsealed to be
+    /// migrated.
+    #[inline(always)]
+    pub fn migrate(&mut self) -> Result<usize, QuotickError> {
+        self.persist()?;
 
-        res
+        let mut migrated = 0;
+        let mut outdated = 0;
+
+        for &epoch in &self.epoch_index {
+            let sealed = self.sealed.binary_search(&epoch).is_ok();
+
+            let mut epoch =
+                Epoch::<T>::new(
+                    epoch,
+                    self.path_builder.clone(),
+                    &self.options,
+                    &self.upgrades,
+                )?;
+
+            if epoch.schema_version() == T::schema_version() {
+                continue;
+            }
+
+            if sealed {
+                outdated += 1;
+                continue;
+            }
+
+            epoch.rewrite();
+            epoch.persist()?;
+
+            migrated += 1;
+        }
+
+        // the current epoch may be stale now.
+        self.curr_epoch = (0, None);
+
+        if outdated == 0 && self.meta.is_outdated::<T>() {
+            let mut meta = self.meta.clone();
+
+            meta.upgrade::<T>();
+
+            write_meta(
+                &mut open_meta_backing(&self.path_builder)?,
+                &meta,
+            )?;
+
+            self.meta = meta;
+        }
+
+        Ok(migrated)
     }
 
-    #[inline(always)]
-    pub fn newest_frame(&self) -> Option<Frame<T>> {
-        let mut epoch =
-            Epoch::new(
-                self.epoch_index.last().copied()?,
-                self.path_builder.clone(),
-            ).ok()?;
+    /// Rewrites `epoch` in time order with the current schema and
+    /// compression level, and swaps it in place of the old file by an
+    /// atomic rename. Epochs left without frames are removed from disk
+    /// and from the epoch index. Sealed epochs are never rewritten.
+    pub fn compact(
+        &mut self,
+        epoch: u64,
+    ) -> Result<CompactionReport, QuotickError> {
+        if self.epoch_index.binary_search(&epoch).is_err() {
+            return Err(QuotickError::EpochNotFound(epoch));
+        }
+
+        if self.is_sealed(epoch) {
+            return Err(QuotickError::EpochSealed(epoch));
+        }
+
+        self.persist()?;
+
+        // the loaded epoch would keep writing to the replaced file.
+        if self.curr_epoch.0 == epoch {
+            self.curr_epoch = (0, None);
+        }
+
+        let report = self.compact_epoch(epoch)?;
 
-        epoch.frames().last()
+        self.persist()?;
+
+        Ok(report)
     }
 
-    #[inline(always)]
-    pub fn epochs(&self) -> EpochIter<T> {
-        EpochIter::<T>::new(
-            self.epoch_index.iter(),
-            self.path_builder.clone(),
+    /// Compacts every epoch that is not sealed, see `compact`, and rebuilds
+    /// the epoch index from the epoch files on disk, picking up epochs
+    /// missing from it.
+    pub fn compact_all(&mut self) -> Result<CompactionReport, QuotickError> {
+        self.persist()?;
+
+        self.curr_epoch = (0, None);
+
+        let mut epochs = self.epoch_index.clone();
+
+        let entries =
+            self.path_builder
+                .backend()
+                .list(&self.path_builder.epoch_path())
+                .map_err(|_| QuotickError::BackingFileFailure)?;
+
+        for entry in entries {
+            let epoch =
+                entry.file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.strip_suffix(".qtf"))
+                    .and_then(|epoch| epoch.parse::<u64>().ok());
+
+            if let Some(epoch) = epoch {
+                if let Err(pos) = epochs.binary_search(&epoch) {
+                    epochs.insert(pos, epoch);
+                }
+            }
+        }
+
+        self.epoch_index = epochs.clone();
+
+        let mut report = CompactionReport::default();
+
+        for epoch in epochs {
+            if !self.is_sealed(epoch) {
+                report.add(self.compact_epoch(epoch)?);
+            }
+        }
+
+        self.persist()?;
+
+        Ok(report)
+    }
+
+    fn compact_epoch(
+        &mut self,
+        epoch: u64,
+    ) -> Result<CompactionReport, QuotickError> {
+        let path = self.path_builder.index_backing_file(epoch);
+        let backend = self.path_builder.backend().clone();
+
+        let file_size =
+            |path: &Path|
+                backend.len(path)
+                    .unwrap_or(0);
+
+        let bytes_before = file_size(&path);
+
+        let loaded =
+            Epoch::<T>::new(
+                epoch,
+                self.path_builder.clone(),
+                &self.options,
+                &self.upgrades,
+            )?;
+
+        let frames = loaded.len();
+
+        if frames == 0 {
+            drop(loaded);
+
+            let _ = backend.delete(&path);
+
+            // archives always live on the filesystem, see `ArchivedEpoch`.
+            if backend.is_local() {
+                let _ = std::fs::remove_file(self.path_builder.archive_file(epoch));
+            }
+
+            if let Ok(pos) = self.epoch_index.binary_search(&epoch) {
+                self.epoch_index.remove(pos);
+            }
+        } else {
+            loaded.write_to(
+                &path,
+                self.options.compression_level_for(self.path_builder.tier(epoch)),
+            )?;
+        }
+
+        Ok(
+            CompactionReport {
+                epochs: 1,
+                frames,
+                bytes_before,
+                bytes_after: file_size(&path),
+            },
         )
     }
-}
 
-impl<T: Tick + Serialize + DeserializeOwned> Drop for Quotick<T> {
-    #[inline(always)]
-    fn drop(&mut self) {
-        self.persist();
+    /// Seals `epoch`: compacts it, see `compact`, rewrites it with an
+    /// index of its frames, see `SealedEpoch`, and marks it as sealed in
+    /// the epoch index. Sealed epochs reject inserts with
+    /// `QuotickError::EpochSealed` and are never rewritten by `persist`,
+    /// `compact` or `migrate`, so they can be backed up once and for all.
+    pub fn seal(
+        &mut self,
+        epoch: u64,
+    ) -> Result<(), QuotickError> {
+        if self.is_sealed(epoch) {
+            return Ok(());
+        }
+
+        self.compact(epoch)?;
+
+        // compacting removes epochs without frames.
+        if self.epoch_index.binary_search(&epoch).is_err() {
+            return Err(QuotickError::EpochNotFound(epoch));
+        }
+
+        let mut sealed = Err(SealedError::Corrupt);
+
+        self.with_epoch(
+            epoch,
+            |loaded| sealed = SealedEpoch::encode(loaded),
+        )?;
+
+        let bytes =
+            encode_raw(
+                &sealed?,
+                T::schema_version(),
+                self.options.compression_level_for(self.path_builder.tier(epoch)),
+                self.path_builder.keys().map(Arc::as_ref),
+            )
+                .map_err(|_| QuotickError::BackingFileFailure)?;
+
+        self.path_builder
+            .backend()
+            .replace(&self.path_builder.index_backing_file(epoch), &bytes)
+            .map_err(|_| QuotickError::BackingFileFailure)?;
+
+        if let Err(pos) = self.sealed.binary_search(&epoch) {
+            self.sealed.insert(pos, epoch);
+        }
+
+        self.persist()
     }
-}
 
-pub struct EpochIter<'a, T: Tick + Serialize + DeserializeOwned> {
-    epoch_iter: Iter<'a, u64>,
-    curr_epoch: Option<Epoch<T>>,
-    path_builder: QuotickPathBuilder,
-}
+    /// Makes a sealed epoch writable again.
+    pub fn unseal(
+        &mut self,
+        epoch: u64,
+    ) -> Result<(), QuotickError> {
+        if let Ok(pos) = self.sealed.binary_search(&epoch) {
+            self.sealed.remove(pos);
 
-impl<'a, T: Tick + Serialize + DeserializeOwned> EpochIter<'a, T> {
-    #[inline(always)]
-    pub fn new(
-        epoch_iter: Iter<'a, u64>,
-        path_builder: QuotickPathBuilder,
-    ) -> Self {
-        EpochIter {
-            epoch_iter,
-            curr_epoch: None,
-            path_builder,
+            // sealed epoch files may be hard linked into backups, which
+            // writes in place would modify as well.
+            let backend = self.path_builder.backend();
+            let path = self.path_builder.index_backing_file(epoch);
+
+            if backend.is_file(&path) {
+                backend.read(&path)
+                    .and_then(|bytes| backend.replace(&path, &bytes))
+                    .map_err(|_| QuotickError::BackingFileFailure)?;
+            }
         }
+
+        self.persist()
     }
-}
 
-impl<'a, T: 'a + Tick + Serialize + DeserializeOwned> Iterator for EpochIter<'a, T> {
-    type Item = Epoch<T>;
+    #[inline(always)]
+    pub fn is_sealed(
+        &self,
+        epoch: u64,
+    ) -> bool {
+        self.sealed.binary_search(&epoch).is_ok()
+    }
 
+    /// Sealed epochs, ascending.
     #[inline(always)]
-    fn next(&mut self) -> Option<Self::Item> {
-        let epoch = *self.epoch_iter.next()?;
+    pub fn sealed_epochs(&self) -> &[u64] {
+        &self.sealed
+    }
 
-        Epoch::new(
-            epoch,
+    /// Opens sealed `epoch` through the index of its epoch file, which
+    /// reads single frames and time ranges without loading the whole
+    /// epoch, see `SealedEpoch`.
+    pub fn sealed_epoch(
+        &self,
+        epoch: u64,
+    ) -> Result<SealedEpoch<T>, QuotickError> {
+        if !self.is_sealed(epoch) {
+            return Err(SealedError::NotSealed.into());
+        }
+
+        let backend = self.path_builder.backend();
+        let path = self.path_builder.index_backing_file(epoch);
+
+        let bytes =
+            if backend.is_file(&path) {
+                backend.read(&path)
+                    .map_err(|_| QuotickError::BackingFileFailure)?
+            } else {
+                read_packed(&self.path_builder, &self.meta.epoch_rule, epoch)?
+                    .ok_or(QuotickError::EpochNotFound(epoch))?
+            };
+
+        let (schema_version, buf) =
+            decode_raw(&bytes, self.path_builder.keys().map(Arc::as_ref))
+                .map_err(|_| QuotickError::BackingFileFailure)?;
+
+        if schema_version != T::schema_version() {
+            return Err(SealedError::SchemaVersionMismatch(schema_version).into());
+        }
+
+        Ok(SealedEpoch::decode(epoch, buf)?)
+    }
+
+    /// Moves the files of sealed epochs into the pack of their month, see
+    /// `PackFile`, and removes them. Epochs are read from the pack as if
+    /// they still had files of their own. Returns the number of epochs
+    /// packed.
+    pub fn pack_sealed(&mut self) -> Result<usize, QuotickError> {
+        self.persist()?;
+
+        if self.is_sealed(self.curr_epoch.0) {
+            self.curr_epoch = (0, None);
+        }
+
+        let backend = self.path_builder.backend().clone();
+
+        let mut months = BTreeMap::<u64, Vec<u64>>::new();
+
+        for &epoch in &self.sealed {
+            if backend.is_file(&self.path_builder.index_backing_file(epoch)) {
+                months
+                    .entry(pack_month(&self.meta.epoch_rule, epoch))
+                    .or_default()
+                    .push(epoch);
+            }
+        }
+
+        if months.is_empty() {
+            return Ok(0);
+        }
+
+        backend.create_dir_all(&self.path_builder.pack_path())
+            .map_err(|_| QuotickError::BackingFileFailure)?;
+
+        let mut packed = 0;
+
+        for (month, epochs) in months {
+            let path = self.path_builder.pack_file(month);
+
+            let mut files =
+                if backend.is_file(&path) {
+                    PackFile::open(backend.clone(), &path)?.read_all()?
+                } else {
+                    Vec::new()
+                };
+
+            // entries of expired epochs, or of epochs packed again.
+            let epoch_index = &self.epoch_index;
+
+            files.retain(|(epoch, _)| {
+                epoch_index.binary_search(epoch).is_ok() && !epochs.contains(epoch)
+            });
+
+            for &epoch in &epochs {
+                let bytes =
+                    backend.read(&self.path_builder.index_backing_file(epoch))
+                        .map_err(|_| QuotickError::BackingFileFailure)?;
+
+                files.push((epoch, bytes));
+            }
+
+            PackFile::write(backend.as_ref(), &path, &files)?;
+
+            for &epoch in &epochs {
+                backend.delete(&self.path_builder.index_backing_file(epoch))
+                    .map_err(|_| QuotickError::BackingFileFailure)?;
+            }
+
+            packed += epochs.len();
+        }
+
+        Ok(packed)
+    }
+
+    /// Recompresses `epoch` for the cold tier of `QuotickOptions::tiering`
+    /// and moves it there. Epochs already cold are left alone.
+    pub fn move_to_cold(
+        &mut self,
+        epoch: u64,
+    ) -> Result<TieringReport, QuotickError> {
+        let tiering =
+            self.options.tiering
+                .clone()
+                .ok_or(QuotickError::NoColdTier)?;
+
+        if self.epoch_index.binary_search(&epoch).is_err() {
+            return Err(QuotickError::EpochNotFound(epoch));
+        }
+
+        if self.path_builder.tier(epoch) == Tier::Cold {
+            return Ok(TieringReport::default());
+        }
+
+        self.persist()?;
+
+        // the loaded epoch would keep writing to the hot file.
+        if self.curr_epoch.0 == epoch {
+            self.curr_epoch = (0, None);
+        }
+
+        let backend = self.path_builder.backend().clone();
+        let hot_path = self.path_builder.hot_index_backing_file(epoch);
+
+        // packed epochs stay in their pack.
+        if !backend.is_file(&hot_path) {
+            return Ok(TieringReport::default());
+        }
+
+        let cold_path =
+            self.path_builder
+                .cold_index_backing_file(epoch)
+                .ok_or(QuotickError::NoColdTier)?;
+
+        if let Some(cold_epoch_path) = self.path_builder.cold_epoch_path() {
+            backend.create_dir_all(&cold_epoch_path)
+                .map_err(|_| QuotickError::BackingFileFailure)?;
+        }
+
+        let loaded =
+            Epoch::<T>::new(
+                epoch,
+                self.path_builder.clone(),
+                &self.options,
+                &self.upgrades,
+            )?;
+
+        // the hot file is only removed once the cold one is in place, so
+        // readers find the epoch in either tier throughout.
+        loaded.write_to(
+            &cold_path,
+            tiering.compression_level,
+        )?;
+
+        drop(loaded);
+
+        let file_size =
+            |path: &Path|
+                backend.len(path)
+                    .unwrap_or(0);
+
+        let bytes_hot = file_size(&hot_path);
+
+        backend.delete(&hot_path)
+            .map_err(|_| QuotickError::BackingFileFailure)?;
+
+        Ok(
+            TieringReport {
+                epochs_moved: 1,
+                bytes_hot,
+                bytes_cold: file_size(&cold_path),
+            },
+        )
+    }
+
+    /// Moves every sealed epoch that ended more than
+    /// `TierPolicy::cold_after` ago to the cold tier, see `move_to_cold`.
+    /// Epochs still open for writes stay hot.
+    pub fn apply_tiering(&mut self) -> Result<TieringReport, QuotickError> {
+        let tiering =
+            self.options.tiering
+                .clone()
+                .ok_or(QuotickError::NoColdTier)?;
+
+        let cutoff =
+            Timestamp::now()
+                .as_nanos()
+                .saturating_sub(u64::try_from(tiering.cold_after.as_nanos()).unwrap_or(u64::MAX));
+
+        let candidates = self.sealed.clone();
+
+        let mut report = TieringReport::default();
+
+        for epoch in candidates {
+            if self.meta.epoch_rule.end(epoch) > cutoff {
+                break;
+            }
+
+            report.add(self.move_to_cold(epoch)?);
+        }
+
+        Ok(report)
+    }
+
+    /// The retention policy of this symbol, if any.
+    #[inline(always)]
+    pub fn retention(&self) -> Result<Option<RetentionPolicy>, QuotickError> {
+        retention::read_policy(&self.path_builder)
+    }
+
+    /// Stores or clears the retention policy of this symbol. Nothing is
+    /// removed until the policy is enforced.
+    #[inline(always)]
+    pub fn set_retention(
+        &mut self,
+        policy: Option<RetentionPolicy>,
+    ) -> Result<(), QuotickError> {
+        retention::write_policy(
+            &self.path_builder,
+            policy.as_ref(),
+        )
+    }
+
+    /// Removes the epochs expired under the retention policy of this
+    /// symbol, oldest first, from the epoch index and from disk.
+    pub fn enforce_retention(&mut self) -> Result<RetentionReport, QuotickError> {
+        let policy =
+            match self.retention()? {
+                Some(policy) => policy,
+                None => return Ok(RetentionReport::default()),
+            };
+
+        self.persist()?;
+
+        let report =
+            retention::enforce(
+                &self.path_builder,
+                &policy,
+                &self.meta.epoch_rule,
+                &mut self.epoch_index,
+                Timestamp::now().as_nanos(),
+            )?;
+
+        let epoch_index = &self.epoch_index;
+
+        self.sealed.retain(|epoch| epoch_index.binary_search(epoch).is_ok());
+
+        if report.epochs_removed > 0 {
+            self.counters.retain(|epoch, _| epoch_index.binary_search(epoch).is_ok());
+            self.counters_tainted = true;
+        }
+
+        // the loaded epoch may have been removed.
+        if self.epoch_index.binary_search(&self.curr_epoch.0).is_err() {
+            self.curr_epoch = (0, None);
+        }
+
+        self.persist()?;
+
+        Ok(report)
+    }
+
+    #[inline(always)]
+    pub fn oldest_frame(&self) -> Option<Frame<T>> {
+        let epoch = self.epochs().next()?;
+
+        let res =
+            epoch.iter()
+                .next()
+                .map(|frame| frame.to_frame());
+
+        res
+    }
+
+    #[inline(always)]
+    pub fn newest_frame(&self) -> Option<Frame<T>> {
+        let epoch =
+            Epoch::new(
+                self.epoch_index.last().copied()?,
+                self.path_builder.clone(),
+                &self.options,
+                &self.upgrades,
+            ).ok()?;
+
+        epoch.iter()
+            .last()
+            .map(|frame| frame.to_frame())
+    }
+
+    /// Storage statistics of `epoch`. Frames not persisted yet are
+    /// counted, but not in the compressed size.
+    pub fn epoch_stats(
+        &self,
+        epoch: u64,
+    ) -> Result<EpochStats, QuotickError> {
+        if self.epoch_index.binary_search(&epoch).is_err() {
+            return Err(QuotickError::EpochNotFound(epoch));
+        }
+
+        let mut stats = EpochStats::default();
+
+        self.with_epoch(
+            epoch,
+            |loaded| stats = loaded.stats(),
+        )?;
+
+        let path = self.path_builder.index_backing_file(epoch);
+
+        stats.compressed_bytes =
+            match self.path_builder.backend().len(&path) {
+                Ok(len) => len,
+                Err(_) => packed_len(&self.path_builder, &self.meta.epoch_rule, epoch).unwrap_or(0),
+            };
+
+        stats.counters =
+            self.counters
+                .get(&epoch)
+                .copied()
+                .unwrap_or_default();
+
+        Ok(stats)
+    }
+
+    /// Storage statistics of all epochs, see `epoch_stats`.
+    pub fn stats(&self) -> Result<QuotickStats, QuotickError> {
+        let epochs =
+            self.epoch_index
+                .iter()
+                .map(|&epoch| self.epoch_stats(epoch))
+                .collect::<Result<_, _>>()?;
+
+        Ok(
+            QuotickStats {
+                epochs,
+            },
+        )
+    }
+
+    /// Calls `f` with epoch `epoch`, using the loaded epoch if it is the
+    /// current one so that frames not persisted yet are included.
+    fn with_epoch<F: FnOnce(&Epoch<T>)>(
+        &self,
+        epoch: u64,
+        f: F,
+    ) -> Result<(), QuotickError> {
+        match &self.curr_epoch {
+            (curr, Some(loaded)) if *curr == epoch => f(loaded),
+            _ => {
+                f(
+                    &Epoch::new(
+                        epoch,
+                        self.path_builder.clone(),
+                        &self.options,
+                        &self.upgrades,
+                    )?,
+                )
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Calls `f` for every frame of the symbol, oldest first, without
+    /// cloning ticks. Includes frames not persisted yet.
+    pub fn for_each_frame<F: FnMut(FrameRef<'_, T>)>(
+        &self,
+        mut f: F,
+    ) -> Result<(), QuotickError> {
+        for &epoch in self.epoch_index.iter() {
+            self.with_epoch(
+                epoch,
+                |epoch| epoch.for_each_frame(&mut f),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Pushes all frames with times in `range` into `sink`, oldest first,
+    /// and returns how many there were.
+    pub fn read_columns_into<S: ColumnSink<T>>(
+        &self,
+        range: impl RangeBounds<u64>,
+        sink: &mut S,
+    ) -> Result<usize, QuotickError> {
+        let (start, end) =
+            match time_bounds(range) {
+                Some(bounds) => bounds,
+                None => return Ok(0),
+            };
+
+        let mut count = 0;
+
+        for &epoch in self.epochs_within(start, end) {
+            self.with_epoch(
+                epoch,
+                |epoch| {
+                    sink.reserve(epoch.len());
+
+                    epoch.iter()
+                        .skip_while(|frame| frame.time() < start)
+                        .take_while(|frame| end.is_none_or(|end| frame.time() <= end))
+                        .for_each(|frame| {
+                            sink.push(frame.time(), frame.tick());
+                            count += 1;
+                        });
+                },
+            )?;
+        }
+
+        Ok(count)
+    }
+
+    /// Epochs of the index that may hold frames between `start` and
+    /// `end`, inclusive.
+    #[inline(always)]
+    pub(crate) fn epochs_within(
+        &self,
+        start: u64,
+        end: Option<u64>,
+    ) -> impl Iterator<Item=&u64> + '_ {
+        let rule = T::epoch_rule();
+
+        let first_epoch = rule.epoch(start);
+        let last_epoch = end.map(|end| rule.epoch(end));
+
+        self.epoch_index
+            .iter()
+            .skip_while(move |&&epoch| epoch < first_epoch)
+            .take_while(move |&&epoch| last_epoch.is_none_or(|last| epoch <= last))
+    }
+
+    /// Clones the frames of `epoch` with times between `start` and `end`,
+    /// inclusive. Epochs no longer in the index have none.
+    #[cfg(feature = "async")]
+    pub(crate) fn epoch_frames_within(
+        &self,
+        epoch: u64,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<Vec<Frame<T>>, QuotickError> {
+        let mut frames = Vec::new();
+
+        if self.epoch_index.binary_search(&epoch).is_err() {
+            return Ok(frames);
+        }
+
+        self.with_epoch(
+            epoch,
+            |epoch| {
+                frames.extend(
+                    epoch.iter()
+                        .skip_while(|frame| frame.time() < start)
+                        .take_while(|frame| end.is_none_or(|end| frame.time() <= end))
+                        .map(|frame| frame.to_frame()),
+                );
+            },
+        )?;
+
+        Ok(frames)
+    }
+
+    /// Writes the frames with times in `range` to a single file at
+    /// `dest`, along with the metadata of the symbol, see `ExportFile`.
+    /// Includes frames not persisted yet. Epochs without frames in
+    /// `range` are left out.
+    ///
+    /// For symbols opened with `QuotickOptions::encryption`, epochs are
+    /// written encrypted with the current key, so only databases holding
+    /// that key can import them, see `export_unencrypted`.
+    #[inline(always)]
+    pub fn export(
+        &self,
+        range: impl RangeBounds<u64>,
+        dest: impl AsRef<Path>,
+    ) -> Result<ExportHeader, QuotickError> {
+        self.export_with(
+            range,
+            dest,
+            self.path_builder.keys().map(Arc::as_ref),
+        )
+    }
+
+    /// Like `export`, but writes the epochs unencrypted, so that databases
+    /// with other keys can import them. Encrypt the file at rest by other
+    /// means where needed.
+    #[inline(always)]
+    pub fn export_unencrypted(
+        &self,
+        range: impl RangeBounds<u64>,
+        dest: impl AsRef<Path>,
+    ) -> Result<ExportHeader, QuotickError> {
+        self.export_with(
+            range,
+            dest,
+            None,
+        )
+    }
+
+    fn export_with(
+        &self,
+        range: impl RangeBounds<u64>,
+        dest: impl AsRef<Path>,
+        keys: Option<&dyn KeyProvider>,
+    ) -> Result<ExportHeader, QuotickError> {
+        let bounds = time_bounds(range);
+
+        let mut writer = ExportWriter::create(self.path_builder.backend().as_ref(), dest)?;
+
+        if let Some((start, end)) = bounds {
+            for &epoch in self.epochs_within(start, end) {
+                let mut frame_index = None;
+
+                self.with_epoch(
+                    epoch,
+                    |loaded| {
+                        frame_index =
+                            Some(
+                                loaded.iter()
+                                    .skip_while(|frame| frame.time() < start)
+                                    .take_while(|frame| end.is_none_or(|end| frame.time() <= end))
+                                    .map(|frame| (frame.time(), frame.tick().clone()))
+                                    .collect::<FrameIndex<T>>(),
+                            );
+                    },
+                )?;
+
+                let frame_index = frame_index.unwrap_or_default();
+
+                if frame_index.is_empty() {
+                    continue;
+                }
+
+                let bytes =
+                    encode_versioned(
+                        &frame_index,
+                        T::schema_version(),
+                        self.options.compression_level,
+                        keys,
+                    )
+                        .map_err(|_| QuotickError::BackingFileFailure)?;
+
+                writer.push(epoch, frame_index.len() as u64, &bytes)?;
+            }
+        }
+
+        let mut meta = self.meta.clone();
+
+        // epoch files are written in the current schema version.
+        meta.upgrade::<T>();
+
+        Ok(
+            writer.finish(
+                ExportHeader {
+                    symbol: self.path_builder.symbol().as_str().to_string(),
+                    meta,
+                    bounds,
+                    created: Timestamp::now().as_nanos(),
+                    encrypted: keys.is_some(),
+                    epochs: Vec::new(),
+                },
+            )?,
+        )
+    }
+
+    #[inline(always)]
+    pub fn epochs(&self) -> EpochIter<'_, T> {
+        EpochIter::<T>::new(
+            self.epoch_index.iter(),
             self.path_builder.clone(),
+            self.options.clone(),
+            self.upgrades.clone(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn epoch_index(&self) -> &[u64] {
+        &self.epoch_index
+    }
+
+    #[inline(always)]
+    pub fn path_builder(&self) -> &QuotickPathBuilder {
+        &self.path_builder
+    }
+
+    /// Sets or clears the plausibility check of frame times for this symbol.
+    #[inline(always)]
+    pub fn set_time_window(
+        &mut self,
+        time_window: Option<TimeWindow>,
+    ) {
+        self.options.time_window = time_window;
+    }
+
+    #[inline(always)]
+    pub fn options(&self) -> &QuotickOptions {
+        &self.options
+    }
+
+    #[inline(always)]
+    pub fn meta(&self) -> &SymbolMeta {
+        &self.meta
+    }
+
+    /// Opens `symbol`, stored alongside this one with the same options,
+    /// without knowing its tick type. Use `DynQuotick::open` where no
+    /// `Quotick` is at hand.
+    #[inline(always)]
+    pub fn open_dyn(
+        &self,
+        symbol: &str,
+    ) -> Result<DynQuotick, QuotickError> {
+        DynQuotick::with_options(
+            symbol,
+            self.path_builder.base_path(),
+            &self.options,
+        )
+    }
+}
+
+impl<T: Columnar + Serialize + DeserializeOwned> Quotick<T> {
+    /// Reads all frames with times in `range` as contiguous columns.
+    ///
+    /// ```ignore
+    /// let quotes = quotick.read_columns(day_start..day_end)?;
+    ///
+    /// let spread =
+    ///     quotes.ask_price.iter()
+    ///         .zip(quotes.bid_price.iter())
+    ///         .map(|(ask, bid)| *ask - *bid)
+    ///         .sum::<Price>();
+    /// ```
+    #[inline(always)]
+    pub fn read_columns(
+        &self,
+        range: impl RangeBounds<u64>,
+    ) -> Result<T::Columns, QuotickError> {
+        let mut columns = T::Columns::default();
+
+        self.read_columns_into(
+            range,
+            &mut columns,
+        )?;
+
+        Ok(columns)
+    }
+}
+
+impl<T: FixedLayout + Serialize + DeserializeOwned> Quotick<T> {
+    /// Opens the zero-copy archive of `epoch`, building it from the epoch
+    /// file if there is none or the existing one does not validate.
+    ///
+    /// Persists pending inserts first, so the archive reflects them.
+    /// Fails for symbols opened with `QuotickOptions::encryption` or a
+    /// backend that is not `is_local`.
+    pub fn archived_epoch(
+        &mut self,
+        epoch: u64,
+    ) -> Result<ArchivedEpoch<T>, QuotickError> {
+        if self.path_builder.keys().is_some() {
+            return Err(ArchiveError::Encrypted.into());
+        }
+
+        if !self.path_builder.backend().is_local() {
+            return Err(ArchiveError::NotLocal.into());
+        }
+
+        if self.epoch_index.binary_search(&epoch).is_err() {
+            return Err(QuotickError::EpochNotFound(epoch));
+        }
+
+        self.persist()?;
+
+        let path = self.path_builder.archive_file(epoch);
+
+        match ArchivedEpoch::open(&path, epoch) {
+            Ok(archive) => return Ok(archive),
+            Err(ArchiveError::UnsupportedLayout) => return Err(ArchiveError::UnsupportedLayout.into()),
+            // archives are derived data, rebuild them.
+            Err(_) => {}
+        }
+
+        let mut written = Ok(());
+
+        self.with_epoch(
+            epoch,
+            |loaded| written = ArchivedEpoch::write(&path, loaded),
+        )?;
+
+        written?;
+
+        Ok(ArchivedEpoch::open(&path, epoch)?)
+    }
+
+    /// Builds the archives of all epochs, see `archived_epoch`, and returns
+    /// how many were (re)built.
+    pub fn archive_all(&mut self) -> Result<usize, QuotickError> {
+        if self.path_builder.keys().is_some() {
+            return Err(ArchiveError::Encrypted.into());
+        }
+
+        if !self.path_builder.backend().is_local() {
+            return Err(ArchiveError::NotLocal.into());
+        }
+
+        self.persist()?;
+
+        let mut built = 0;
+
+        for epoch in self.epoch_index.clone() {
+            let path = self.path_builder.archive_file(epoch);
+
+            if ArchivedEpoch::<T>::open(&path, epoch).is_ok() {
+                continue;
+            }
+
+            self.archived_epoch(epoch)?;
+
+            built += 1;
+        }
+
+        Ok(built)
+    }
+}
+
+impl<T: Tick + Serialize + DeserializeOwned> Drop for Quotick<T> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        // errors can't be reported here, persist explicitly to see them.
+        let _ = self.persist();
+    }
+}
+
+/// Inclusive start and end of `range`, if it is not empty.
+#[inline(always)]
+pub(crate) fn time_bounds(
+    range: impl RangeBounds<u64>,
+) -> Option<(u64, Option<u64>)> {
+    let start =
+        match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(1)?,
+            Bound::Unbounded => 0,
+        };
+
+    let end =
+        match range.end_bound() {
+            Bound::Included(&end) => Some(end),
+            Bound::Excluded(&end) => Some(end.checked_sub(1)?),
+            Bound::Unbounded => None,
+        };
+
+    if end.is_some_and(|end| end < start) {
+        return None;
+    }
+
+    Some((start, end))
+}
+
+pub struct EpochIter<'a, T: Tick + Serialize + DeserializeOwned> {
+    epoch_iter: Iter<'a, u64>,
+    path_builder: QuotickPathBuilder,
+    options: QuotickOptions,
+    upgrades: SchemaUpgrades<T>,
+}
+
+impl<'a, T: Tick + Serialize + DeserializeOwned> EpochIter<'a, T> {
+    #[inline(always)]
+    pub fn new(
+        epoch_iter: Iter<'a, u64>,
+        path_builder: QuotickPathBuilder,
+        options: QuotickOptions,
+        upgrades: SchemaUpgrades<T>,
+    ) -> Self {
+        EpochIter {
+            epoch_iter,
+            path_builder,
+            options,
+            upgrades,
+        }
+    }
+}
+
+impl<'a, T: 'a + Tick + Serialize + DeserializeOwned> Iterator for EpochIter<'a, T> {
+    type Item = Epoch<T>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let epoch = *self.epoch_iter.next()?;
+
+        Epoch::new(
+            epoch,
+            self.path_builder.clone(),
+            &self.options,
+            &self.upgrades,
         )
             .ok()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::super::backing::encryption::StaticKey;
+    use super::super::test_util::TempPath;
+    use super::super::tick::Trade;
+    use super::*;
+
+    #[test]
+    fn compaction_rewrites_and_reindexes_epochs() {
+        let base_path = TempPath::new("compact");
+        let day = 86_400_000_000_000;
+
+        let options =
+            QuotickOptions {
+                compression_level: 0,
+                ..Default::default()
+            };
+
+        let mut quotick = Quotick::<Trade>::with_options("SYMBL", &base_path, options).unwrap();
+
+        for idx in (0..1000u64).rev() {
+            quotick
+                .insert(&Frame::new(idx, Trade { size: idx % 7, ..Default::default() }))
+                .unwrap();
+        }
+
+        quotick
+            .insert(&Frame::new(day, Trade::default()))
+            .unwrap();
+
+        quotick.persist().unwrap();
+
+        let path_builder = quotick.path_builder().clone();
+
+        drop(quotick);
+
+        // an epoch file the index does not know about.
+        std::fs::copy(path_builder.index_backing_file(1), path_builder.index_backing_file(5)).unwrap();
+
+        // compacted with the default compression level.
+        let mut quotick = Quotick::<Trade>::new("SYMBL", &base_path).unwrap();
+
+        let report = quotick.compact(0).unwrap();
+
+        assert_eq!(report.frames, 1000);
+        assert!(report.bytes_reclaimed() > 0);
+
+        quotick
+            .insert(&Frame::new(1000, Trade::default()))
+            .unwrap();
+
+        let report = quotick.compact_all().unwrap();
+
+        assert_eq!(report.epochs, 3);
+        assert_eq!(report.frames, 1003);
+        assert_eq!(quotick.epoch_index(), &[0, 1, 5]);
+        // epoch 5 holds a copy of the frames of epoch 1.
+        assert_eq!(quotick.newest_frame().unwrap().time(), day);
+
+        assert!(matches!(quotick.compact(2), Err(QuotickError::EpochNotFound(2))));
+    }
+
+    #[test]
+    fn sealed_epochs_are_immutable() {
+        let base_path = TempPath::new("seal");
+        let day = 86_400_000_000_000;
+
+        let mut quotick = Quotick::<Trade>::new("SYMBL", &base_path).unwrap();
+
+        for time in [day + 2, 1, day, 0] {
+            quotick
+                .insert(&Frame::new(time, Trade { size: time, ..Default::default() }))
+                .unwrap();
+        }
+
+        quotick.seal(0).unwrap();
+
+        assert!(quotick.is_sealed(0));
+        assert!(matches!(quotick.insert(&Frame::new(2, Trade::default())), Err(QuotickError::EpochSealed(0))));
+        assert!(matches!(quotick.compact(0), Err(QuotickError::EpochSealed(0))));
+        assert!(matches!(quotick.seal(7), Err(QuotickError::EpochNotFound(7))));
+
+        let path = quotick.path_builder().index_backing_file(0);
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+        quotick.compact_all().unwrap();
+        quotick.persist().unwrap();
+
+        drop(quotick);
+
+        assert_eq!(std::fs::metadata(&path).unwrap().modified().unwrap(), modified);
+
+        // the seal is part of the epoch index.
+        let mut quotick = Quotick::<Trade>::new("SYMBL", &base_path).unwrap();
+
+        assert_eq!(quotick.sealed_epochs(), &[0]);
+        assert_eq!(quotick.epochs().next().unwrap().len(), 2);
+
+        quotick.unseal(0).unwrap();
+
+        quotick
+            .insert(&Frame::new(2, Trade::default()))
+            .unwrap();
+
+        quotick.persist().unwrap();
+
+        assert_eq!(quotick.epochs().next().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn sealed_epochs_are_indexed() {
+        let base_path = TempPath::new("sealed_index");
+        let day = 86_400_000_000_000;
+
+        let options =
+            QuotickOptions {
+                encryption: Some(Arc::new(StaticKey::new([7; 32]))),
+                ..Default::default()
+            };
+
+        let mut quotick = Quotick::<Trade>::with_options("SYMBL", &base_path, options).unwrap();
+
+        for time in (0..100u64).rev().map(|idx| idx * 10).chain([day]) {
+            quotick
+                .insert(&Frame::new(time, Trade { size: time, ..Default::default() }))
+                .unwrap();
+        }
+
+        assert!(matches!(quotick.sealed_epoch(0), Err(QuotickError::Sealed(SealedError::NotSealed))));
+
+        quotick.seal(0).unwrap();
+
+        let sealed = quotick.sealed_epoch(0).unwrap();
+
+        assert_eq!(sealed.len(), 100);
+        assert_eq!(sealed.times()[99], 990);
+        assert_eq!(sealed.get(420).unwrap().unwrap().tick().size, 420);
+        assert!(sealed.get(421).unwrap().is_none());
+
+        let frames =
+            sealed.range(15..=50)
+                .map(|frame| frame.unwrap().time())
+                .collect::<Vec<_>>();
+
+        assert_eq!(frames, vec![20, 30, 40, 50]);
+
+        // readers unaware of the index read the epoch as usual.
+        let mut epochs = quotick.epochs();
+
+        assert_eq!(epochs.next().unwrap().len(), 100);
+
+        drop(epochs);
+
+        let frames =
+            quotick.open_dyn("SYMBL").unwrap()
+                .frames()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+
+        assert_eq!(frames.len(), 101);
+
+        // the index is kept in the pack of the month.
+        assert_eq!(quotick.pack_sealed().unwrap(), 1);
+        assert!(!quotick.path_builder().index_backing_file(0).exists());
+        assert_eq!(quotick.sealed_epoch(0).unwrap().get(990).unwrap().unwrap().tick().size, 990);
+
+        quotick.unseal(0).unwrap();
+
+        quotick
+            .insert(&Frame::new(995, Trade::default()))
+            .unwrap();
+
+        quotick.persist().unwrap();
+
+        assert_eq!(quotick.epochs().next().unwrap().len(), 101);
+
+        quotick.seal(0).unwrap();
+
+        assert_eq!(quotick.sealed_epoch(0).unwrap().len(), 101);
+    }
+}
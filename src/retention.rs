@@ -0,0 +1,404 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::backing::backing_file::BackingFile;
+use super::epoch_rule::EpochRule;
+use super::pack::{pack_month, packed_len, PackFile};
+use super::path_builder::QuotickPathBuilder;
+use super::quotick::QuotickError;
+
+/// How much history a symbol keeps.
+///
+/// Stored per symbol in `retention.qtf` and enforced by
+/// `Quotick::enforce_retention`, `QuotickDb::enforce_retention` or the
+/// background `RetentionJob`, which remove whole epochs, oldest first.
+/// The newest epoch is removed by `max_age` only, never to meet
+/// `max_bytes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Remove epochs that ended longer than this ago.
+    pub max_age: Option<Duration>,
+    /// Remove the oldest epochs while the epoch files and packs of the
+    /// symbol take up more than this.
+    pub max_bytes: Option<u64>,
+}
+
+impl RetentionPolicy {
+    #[inline(always)]
+    pub fn max_age(
+        max_age: Duration,
+    ) -> RetentionPolicy {
+        RetentionPolicy {
+            max_age: Some(max_age),
+            max_bytes: None,
+        }
+    }
+
+    #[inline(always)]
+    pub fn max_bytes(
+        max_bytes: u64,
+    ) -> RetentionPolicy {
+        RetentionPolicy {
+            max_age: None,
+            max_bytes: Some(max_bytes),
+        }
+    }
+
+    /// Epochs to remove, given the sorted `(epoch, bytes)` of a symbol
+    /// partitioned by `rule`, at time `now`.
+    pub fn expired(
+        &self,
+        epochs: &[(u64, u64)],
+        rule: &EpochRule,
+        now: u64,
+    ) -> Vec<u64> {
+        let cutoff =
+            self.max_age
+                .map(|max_age| now.saturating_sub(max_age.as_nanos() as u64));
+
+        let mut total =
+            epochs
+                .iter()
+                .map(|(_, bytes)| bytes)
+                .sum::<u64>();
+
+        epochs
+            .iter()
+            .enumerate()
+            .take_while(|&(idx, &(epoch, bytes))| {
+                // the newest epoch may still be written to, so only age
+                // removes it.
+                let newest = idx + 1 == epochs.len();
+
+                let too_old = cutoff.is_some_and(|cutoff| rule.end(epoch) <= cutoff);
+                let too_big = !newest && self.max_bytes.is_some_and(|max_bytes| total > max_bytes);
+
+                if too_old || too_big {
+                    total -= bytes;
+                }
+
+                too_old || too_big
+            })
+            .map(|(_, &(epoch, _))| epoch)
+            .collect()
+    }
+}
+
+/// Outcome of enforcing a `RetentionPolicy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RetentionReport {
+    pub epochs_removed: usize,
+    pub bytes_removed: u64,
+}
+
+impl RetentionReport {
+    #[inline(always)]
+    pub(crate) fn add(
+        &mut self,
+        other: RetentionReport,
+    ) {
+        self.epochs_removed += other.epochs_removed;
+        self.bytes_removed += other.bytes_removed;
+    }
+}
+
+pub(crate) fn read_policy(
+    path_builder: &QuotickPathBuilder,
+) -> Result<Option<RetentionPolicy>, QuotickError> {
+    let path = path_builder.retention_backing_file();
+
+    if !path_builder.backend().is_file(&path) {
+        return Ok(None);
+    }
+
+    BackingFile::<RetentionPolicy>::with_backend(path_builder.backend().clone(), path_builder.keys().cloned(), path)
+        .and_then(|mut backing| backing.try_read())
+        .map(Some)
+        .map_err(|_| QuotickError::BackingFileFailure)
+}
+
+pub(crate) fn write_policy(
+    path_builder: &QuotickPathBuilder,
+    policy: Option<&RetentionPolicy>,
+) -> Result<(), QuotickError> {
+    let path = path_builder.retention_backing_file();
+
+    match policy {
+        Some(policy) => {
+            BackingFile::<RetentionPolicy>::with_backend(path_builder.backend().clone(), path_builder.keys().cloned(), path)
+                .and_then(|mut backing| backing.write_all(policy))
+                .map_err(|_| QuotickError::BackingFileFailure)
+        }
+        None if path_builder.backend().is_file(&path) => {
+            path_builder.backend().delete(&path)
+                .map_err(|_| QuotickError::BackingFileFailure)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Removes the epochs of `epoch_index` expired under `policy` from the
+/// index and from storage, including their pack entries. The caller
+/// persists the index.
+pub(crate) fn enforce(
+    path_builder: &QuotickPathBuilder,
+    policy: &RetentionPolicy,
+    rule: &EpochRule,
+    epoch_index: &mut Vec<u64>,
+    now: u64,
+) -> Result<RetentionReport, QuotickError> {
+    let backend = path_builder.backend();
+
+    let file_size =
+        |epoch: u64|
+            backend.len(&path_builder.index_backing_file(epoch))
+                .ok();
+
+    // packed epochs count with the size of their entry.
+    let epochs =
+        epoch_index
+            .iter()
+            .map(|&epoch| {
+                let bytes =
+                    file_size(epoch)
+                        .or_else(|| packed_len(path_builder, rule, epoch))
+                        .unwrap_or(0);
+
+                (epoch, bytes)
+            })
+            .collect::<Vec<_>>();
+
+    let expired = policy.expired(&epochs, rule, now);
+
+    let mut report = RetentionReport::default();
+    let mut months = Vec::new();
+
+    for epoch in expired {
+        report.epochs_removed += 1;
+        // packs are counted once they are removed, below.
+        report.bytes_removed += file_size(epoch).unwrap_or(0);
+
+        let _ = backend.delete(&path_builder.index_backing_file(epoch));
+
+        // archives always live on the filesystem, see `ArchivedEpoch`.
+        if backend.is_local() {
+            let _ = fs::remove_file(path_builder.archive_file(epoch));
+        }
+
+        if let Ok(pos) = epoch_index.binary_search(&epoch) {
+            epoch_index.remove(pos);
+        }
+
+        months.push(pack_month(rule, epoch));
+    }
+
+    // packs are rewritten without the entries of removed epochs, which
+    // `Epoch` would otherwise read again once the epoch is written to.
+    // Entries shadowed by an epoch file of their own go, too, and packs
+    // left without entries are removed.
+    months.dedup();
+
+    for month in months {
+        let path = path_builder.pack_file(month);
+
+        if !backend.is_file(&path) {
+            continue;
+        }
+
+        let pack = PackFile::open(backend.clone(), &path)?;
+
+        let live =
+            |epoch: u64| {
+                epoch_index.binary_search(&epoch).is_ok() && file_size(epoch).is_none()
+            };
+
+        if pack.entries().iter().all(|entry| live(entry.epoch)) {
+            continue;
+        }
+
+        let mut files = pack.read_all()?;
+
+        files.retain(|(epoch, _)| live(*epoch));
+
+        let len = backend.len(&path).unwrap_or(0);
+
+        if files.is_empty() {
+            backend.delete(&path)
+                .map_err(|_| QuotickError::BackingFileFailure)?;
+
+            report.bytes_removed += len;
+        } else {
+            PackFile::write(backend.as_ref(), &path, &files)?;
+
+            report.bytes_removed += len.saturating_sub(backend.len(&path).unwrap_or(len));
+        }
+    }
+
+    Ok(report)
+}
+
+/// Symbols with an open `Quotick` in this process, by asset path.
+static OPEN_SYMBOLS: Mutex<BTreeMap<PathBuf, Weak<OpenSymbol>>> = Mutex::new(BTreeMap::new());
+
+/// Registration of an open `Quotick`, through which `QuotickDb` hands
+/// retention over to the handle instead of changing its files under it.
+#[derive(Debug, Default)]
+pub(crate) struct OpenSymbol {
+    retention_due: AtomicBool,
+}
+
+impl OpenSymbol {
+    /// Registers a `Quotick` of the symbol at `path_builder`, shared with
+    /// other handles of the symbol. Blocks while `with_closed_symbol`
+    /// works on the symbol.
+    pub(crate) fn register(
+        path_builder: &QuotickPathBuilder,
+    ) -> Arc<OpenSymbol> {
+        let mut open = OPEN_SYMBOLS.lock().unwrap_or_else(|err| err.into_inner());
+
+        open.retain(|_, symbol| symbol.strong_count() > 0);
+
+        if let Some(symbol) = open.get(&path_builder.asset_path()).and_then(Weak::upgrade) {
+            return symbol;
+        }
+
+        let symbol = Arc::new(OpenSymbol::default());
+
+        open.insert(path_builder.asset_path(), Arc::downgrade(&symbol));
+
+        symbol
+    }
+
+    /// Whether retention was handed over since the last call.
+    #[inline(always)]
+    pub(crate) fn take_retention_due(&self) -> bool {
+        self.retention_due.swap(false, Ordering::AcqRel)
+    }
+}
+
+/// Runs `f` on the files of the symbol at `path_builder` unless it is
+/// open in this process, in which case its handles enforce retention on
+/// their next `Quotick::persist` and `None` is returned. The symbol can't
+/// be opened while `f` runs.
+pub(crate) fn with_closed_symbol<R>(
+    path_builder: &QuotickPathBuilder,
+    f: impl FnOnce() -> R,
+) -> Option<R> {
+    let open = OPEN_SYMBOLS.lock().unwrap_or_else(|err| err.into_inner());
+
+    match open.get(&path_builder.asset_path()).and_then(Weak::upgrade) {
+        Some(symbol) => {
+            symbol.retention_due.store(true, Ordering::Release);
+
+            None
+        }
+        None => Some(f()),
+    }
+}
+
+/// Periodically enforces the retention policies of all symbols of a
+/// `QuotickDb`, see `QuotickDb::spawn_retention_job`. Stops when dropped.
+pub struct RetentionJob {
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl RetentionJob {
+    #[inline(always)]
+    pub(crate) fn new(
+        interval: Duration,
+        mut run: impl FnMut() + Send + 'static,
+    ) -> RetentionJob {
+        let (stop, stopped) = channel::<()>();
+
+        let handle =
+            std::thread::spawn(move || {
+                loop {
+                    run();
+
+                    match stopped.recv_timeout(interval) {
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        _ => break,
+                    }
+                }
+            });
+
+        RetentionJob {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    /// Stops the job, waiting for a running enforcement to finish.
+    #[inline(always)]
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    #[inline(always)]
+    fn shutdown(&mut self) {
+        drop(self.stop.take());
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for RetentionJob {
+    #[inline(always)]
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DAY: u64 = 86_400_000_000_000;
+
+    #[test]
+    fn expires_by_age_and_size() {
+        let epochs = [(10, 100), (11, 100), (12, 100), (13, 100)];
+        let now = 14 * DAY;
+
+        assert_eq!(
+            RetentionPolicy::max_age(Duration::from_secs(2 * 86_400)).expired(&epochs, &EpochRule::Day, now),
+            vec![10, 11],
+        );
+
+        assert_eq!(
+            RetentionPolicy::max_bytes(250).expired(&epochs, &EpochRule::Day, now),
+            vec![10, 11],
+        );
+
+        // the newest epoch stays however big, but not however old.
+        assert_eq!(
+            RetentionPolicy::max_bytes(0).expired(&epochs, &EpochRule::Day, 100 * DAY),
+            vec![10, 11, 12],
+        );
+
+        assert_eq!(
+            RetentionPolicy::max_age(Duration::from_secs(86_400)).expired(&epochs, &EpochRule::Day, 100 * DAY),
+            vec![10, 11, 12, 13],
+        );
+
+        let policy =
+            RetentionPolicy {
+                max_age: Some(Duration::from_secs(4 * 86_400)),
+                max_bytes: Some(350),
+            };
+
+        assert_eq!(policy.expired(&epochs, &EpochRule::Day, now), vec![10]);
+        assert!(RetentionPolicy::default().expired(&epochs, &EpochRule::Day, now).is_empty());
+    }
+}
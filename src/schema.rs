@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+
+use super::epoch::{EpochError, FrameIndex};
+
+type UpgradeFn<T> = dyn Fn(&[u8]) -> Result<FrameIndex<T>, EpochError> + Send + Sync;
+
+/// Upgrade functions converting epochs written with an older schema
+/// version of a tick type into the current `T`.
+///
+/// bincode is positional, so every change to the fields of a tick type
+/// requires bumping `Tick::schema_version` and registering a function
+/// that reads the previous layout:
+///
+/// ```ignore
+/// upgrades.register(1, |old: TradeV1| Trade { size: old.size, price: old.price, venue: 0 });
+/// ```
+pub struct SchemaUpgrades<T> {
+    upgrades: HashMap<u32, Arc<UpgradeFn<T>>>,
+}
+
+impl<T> SchemaUpgrades<T> {
+    #[inline(always)]
+    pub fn new() -> SchemaUpgrades<T> {
+        SchemaUpgrades {
+            upgrades: HashMap::new(),
+        }
+    }
+
+    /// Registers the conversion from ticks stored with `version`, which
+    /// deserialize as `O`, to the current tick type.
+    #[inline(always)]
+    pub fn register<O, F>(
+        &mut self,
+        version: u32,
+        upgrade: F,
+    )
+        where
+            O: DeserializeOwned,
+            F: Fn(O) -> T + Send + Sync + 'static,
+    {
+        self.try_register(
+            version,
+            move |old: O| Ok::<T, Infallible>(upgrade(old)),
+        );
+    }
+
+    /// Like `register`, for conversions that can fail. Reading an epoch
+    /// with a tick that doesn't convert fails with
+    /// `EpochError::UpgradeFailed`, and so does `Quotick::migrate`.
+    #[inline(always)]
+    pub fn try_register<O, E, F>(
+        &mut self,
+        version: u32,
+        upgrade: F,
+    )
+        where
+            O: DeserializeOwned,
+            F: Fn(O) -> Result<T, E> + Send + Sync + 'static,
+    {
+        self.upgrades
+            .insert(
+                version,
+                Arc::new(
+                    move |buf: &[u8]| {
+                        // a serialized trie is a map, which bincode lays
+                        // out exactly like a sequence of key-value tuples.
+                        let old_frames =
+                            bincode::deserialize::<Vec<(u64, O)>>(
+                                buf,
+                            )
+                                .map_err(|_| EpochError::IndexFileFailure)?;
+
+                        old_frames
+                            .into_iter()
+                            .map(|(time, tick)| {
+                                upgrade(tick)
+                                    .map(|tick| (time, tick))
+                                    .map_err(|_| EpochError::UpgradeFailed(version))
+                            })
+                            .collect()
+                    },
+                ),
+            );
+    }
+
+    #[inline(always)]
+    pub fn contains(
+        &self,
+        version: u32,
+    ) -> bool {
+        self.upgrades.contains_key(&version)
+    }
+
+    #[inline(always)]
+    pub fn upgrade(
+        &self,
+        version: u32,
+        buf: &[u8],
+    ) -> Result<FrameIndex<T>, EpochError> {
+        let upgrade =
+            self.upgrades
+                .get(&version)
+                .ok_or(EpochError::UnsupportedSchemaVersion(version))?;
+
+        upgrade(buf)
+    }
+}
+
+impl<T> Clone for SchemaUpgrades<T> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        SchemaUpgrades {
+            upgrades: self.upgrades.clone(),
+        }
+    }
+}
+
+impl<T> Default for SchemaUpgrades<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        SchemaUpgrades::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_derive::{Deserialize, Serialize};
+
+    use super::super::test_util::TempPath;
+    use super::super::{Frame, Quotick, Tick};
+    use super::*;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct TradeV0 {
+        price: u32,
+    }
+
+    impl Tick for TradeV0 {
+        fn type_name() -> &'static str {
+            "test::Trade"
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct TradeV1 {
+        price: u32,
+        size: u64,
+    }
+
+    impl Tick for TradeV1 {
+        fn type_name() -> &'static str {
+            "test::Trade"
+        }
+
+        fn schema_version() -> u32 {
+            1
+        }
+
+        fn register_upgrades(upgrades: &mut SchemaUpgrades<Self>) {
+            upgrades.register(0, |old: TradeV0| TradeV1 { price: old.price, size: 1 });
+        }
+    }
+
+    #[test]
+    fn old_epochs_are_upgraded_and_migrated() {
+        let base_path = TempPath::new("schema");
+
+        {
+            let mut quotick = Quotick::<TradeV0>::new("SYMBL", &base_path).unwrap();
+
+            quotick.insert(&Frame::new(1, TradeV0 { price: 5 })).unwrap();
+            quotick.insert(&Frame::new(86_400_000_000_000, TradeV0 { price: 6 })).unwrap();
+            quotick.seal(0).unwrap();
+        }
+
+        let mut quotick = Quotick::<TradeV1>::new("SYMBL", &base_path).unwrap();
+
+        // the metadata describes the stored epochs until they are migrated.
+        assert_eq!(quotick.meta().schema_version, 0);
+        assert_eq!(quotick.newest_frame().unwrap().tick(), &TradeV1 { price: 6, size: 1 });
+
+        // sealed epochs aren't rewritten, so the metadata stays behind.
+        assert_eq!(quotick.migrate().unwrap(), 1);
+        assert_eq!(quotick.meta().schema_version, 0);
+
+        quotick.unseal(0).unwrap();
+
+        assert_eq!(quotick.migrate().unwrap(), 1);
+        assert_eq!(quotick.migrate().unwrap(), 0);
+        assert_eq!(quotick.meta().schema_version, 1);
+
+        let versions =
+            quotick
+                .epochs()
+                .map(|epoch| epoch.schema_version())
+                .collect::<Vec<_>>();
+
+        assert_eq!(versions, vec![1, 1]);
+
+        drop(quotick);
+
+        assert_eq!(Quotick::<TradeV1>::new("SYMBL", &base_path).unwrap().meta().schema_version, 1);
+        assert!(Quotick::<TradeV0>::new("SYMBL", &base_path).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use std::convert::{TryFrom, TryInto};
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::epoch::Epoch;
+use super::frame::Frame;
+use super::Tick;
+
+const SEALED_MAGIC: &[u8] = b"QTSE";
+const SEALED_VERSION: u32 = 1;
+
+/// frame count, length of the frames, version, magic.
+const FOOTER_LEN: usize = 24;
+
+/// time, tick offset.
+const INDEX_ENTRY_LEN: usize = 16;
+
+#[derive(Debug)]
+pub enum SealedError {
+    /// The epoch is not sealed, see `Quotick::seal`.
+    NotSealed,
+    /// The epoch file holds no index, i.e. it was sealed by an older
+    /// version; unseal and seal it again.
+    MissingIndex,
+    UnsupportedVersion(u32),
+    /// The epoch was sealed with another schema version; unseal and
+    /// migrate it.
+    SchemaVersionMismatch(u32),
+    Corrupt,
+    /// A tick does not deserialize as `T`.
+    BadTick,
+}
+
+/// A sealed epoch, read through the index of its epoch file.
+///
+/// `Quotick::seal` writes the frames the way every epoch file stores
+/// them, followed by an index of their times and the offsets of their
+/// ticks, and a footer with the frame count, the format version and the
+/// magic. Readers unaware of the index, i.e. `Epoch` and `DynQuotick`,
+/// read the frames as usual; `SealedEpoch` looks times up by binary search
+/// and deserializes only the ticks it returns, without building the
+/// frame index of an `Epoch`.
+pub struct SealedEpoch<T> {
+    epoch: u64,
+    buf: Vec<u8>,
+    times: Vec<u64>,
+    /// Start of each tick in `buf`, followed by the end of the frames.
+    offsets: Vec<usize>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Tick + Serialize + DeserializeOwned> SealedEpoch<T> {
+    /// Encodes the frames of `epoch` in the sealed layout, to be written
+    /// as its epoch file.
+    pub(crate) fn encode(
+        epoch: &Epoch<T>,
+    ) -> Result<Vec<u8>, SealedError> {
+        let mut buf =
+            bincode::serialize(&epoch.frame_index)
+                .map_err(|_| SealedError::BadTick)?;
+
+        let frames_len = buf.len();
+
+        let mut times = Vec::with_capacity(epoch.len() * 8);
+        let mut offsets = Vec::with_capacity(epoch.len() * 8);
+
+        // the frame index is serialized as its length, followed by the time
+        // and the tick of every frame, sorted by time.
+        let mut offset = 8;
+
+        for frame in epoch.iter() {
+            offset += 8;
+
+            times.extend_from_slice(&frame.time().to_le_bytes());
+            offsets.extend_from_slice(&(offset as u64).to_le_bytes());
+
+            offset +=
+                bincode::serialized_size(frame.tick())
+                    .map_err(|_| SealedError::BadTick)? as usize;
+        }
+
+        if offset != frames_len {
+            return Err(SealedError::Corrupt);
+        }
+
+        buf.extend_from_slice(&times);
+        buf.extend_from_slice(&offsets);
+        buf.extend_from_slice(&(epoch.len() as u64).to_le_bytes());
+        buf.extend_from_slice(&(frames_len as u64).to_le_bytes());
+        buf.extend_from_slice(&SEALED_VERSION.to_le_bytes());
+        buf.extend_from_slice(SEALED_MAGIC);
+
+        Ok(buf)
+    }
+
+    /// Reads the index of the decoded contents of the epoch file of
+    /// `epoch`, validating it.
+    pub(crate) fn decode(
+        epoch: u64,
+        buf: Vec<u8>,
+    ) -> Result<SealedEpoch<T>, SealedError> {
+        if buf.len() < FOOTER_LEN || &buf[buf.len() - 4..] != SEALED_MAGIC {
+            return Err(SealedError::MissingIndex);
+        }
+
+        let footer = buf.len() - FOOTER_LEN;
+
+        let u64_at = |offset: usize| u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap());
+
+        let version = u32::from_le_bytes(buf[footer + 16..footer + 20].try_into().unwrap());
+
+        if version != SEALED_VERSION {
+            return Err(SealedError::UnsupportedVersion(version));
+        }
+
+        let len = usize::try_from(u64_at(footer)).map_err(|_| SealedError::Corrupt)?;
+        let frames_len = usize::try_from(u64_at(footer + 8)).map_err(|_| SealedError::Corrupt)?;
+
+        let expected_len =
+            len.checked_mul(INDEX_ENTRY_LEN)
+                .and_then(|index_len| index_len.checked_add(frames_len))
+                .and_then(|body| body.checked_add(FOOTER_LEN));
+
+        if expected_len != Some(buf.len()) || frames_len < 8 || u64_at(0) != len as u64 {
+            return Err(SealedError::Corrupt);
+        }
+
+        let times =
+            (0..len)
+                .map(|idx| u64_at(frames_len + idx * 8))
+                .collect::<Vec<_>>();
+
+        let mut offsets =
+            (0..len)
+                .map(|idx| u64_at(frames_len + len * 8 + idx * 8) as usize)
+                .collect::<Vec<_>>();
+
+        offsets.push(frames_len + 8);
+
+        // every tick follows its time and ends where the next time starts.
+        let valid =
+            times.windows(2).all(|pair| pair[0] < pair[1])
+                && offsets.first().is_some_and(|&first| first >= 16)
+                && offsets.windows(2).all(|pair| pair[0] <= pair[1].saturating_sub(8));
+
+        if !valid {
+            return Err(SealedError::Corrupt);
+        }
+
+        Ok(
+            SealedEpoch {
+                epoch,
+                buf,
+                times,
+                offsets,
+                _phantom: PhantomData,
+            },
+        )
+    }
+
+    #[inline(always)]
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    /// Frame times, ascending.
+    #[inline(always)]
+    pub fn times(&self) -> &[u64] {
+        &self.times
+    }
+
+    #[inline(always)]
+    pub fn get(
+        &self,
+        time: u64,
+    ) -> Result<Option<Frame<T>>, SealedError> {
+        match self.times.binary_search(&time) {
+            Ok(idx) => self.frame(idx).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Frames with times in `range`, located by binary search.
+    #[inline(always)]
+    pub fn range(
+        &self,
+        range: impl RangeBounds<u64>,
+    ) -> impl Iterator<Item=Result<Frame<T>, SealedError>> + '_ {
+        let times = &self.times;
+
+        let start =
+            match range.start_bound() {
+                Bound::Included(start) => times.partition_point(|time| time < start),
+                Bound::Excluded(start) => times.partition_point(|time| time <= start),
+                Bound::Unbounded => 0,
+            };
+
+        let end =
+            match range.end_bound() {
+                Bound::Included(end) => times.partition_point(|time| time <= end),
+                Bound::Excluded(end) => times.partition_point(|time| time < end),
+                Bound::Unbounded => times.len(),
+            };
+
+        (start..end.max(start))
+            .map(move |idx| self.frame(idx))
+    }
+
+    #[inline(always)]
+    fn frame(
+        &self,
+        idx: usize,
+    ) -> Result<Frame<T>, SealedError> {
+        let tick = &self.buf[self.offsets[idx]..self.offsets[idx + 1] - 8];
+
+        bincode::deserialize::<T>(tick)
+            .map(|tick| Frame::new(self.times[idx], tick))
+            .map_err(|_| SealedError::BadTick)
+    }
+}
@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use serde_derive::{Deserialize, Serialize};
+
+/// Inserts that hit an existing frame time in an epoch, kept per symbol
+/// in `counters.qtf`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct EpochCounters {
+    /// Frames replaced by `Quotick::insert_opt` with `force_overwrite`.
+    pub overwrites: u64,
+    /// Frames rejected with `EpochError::FrameConflict`.
+    pub duplicates: u64,
+}
+
+/// Storage statistics of an epoch, see `Quotick::epoch_stats`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct EpochStats {
+    pub epoch: u64,
+    pub frames: usize,
+    /// Size of the epoch file, or of its entry in a pack.
+    pub compressed_bytes: u64,
+    /// Size of the frames before compression.
+    pub uncompressed_bytes: u64,
+    pub first_time: Option<u64>,
+    pub last_time: Option<u64>,
+    pub counters: EpochCounters,
+}
+
+impl EpochStats {
+    /// Uncompressed over compressed size; 0 for epochs not written yet.
+    #[inline(always)]
+    pub fn compression_ratio(&self) -> f64 {
+        ratio(self.uncompressed_bytes, self.compressed_bytes)
+    }
+
+    /// Time between the first and the last frame.
+    #[inline(always)]
+    pub fn time_span(&self) -> Option<Duration> {
+        span(self.first_time, self.last_time)
+    }
+
+    /// Mean time between consecutive frames.
+    #[inline(always)]
+    pub fn mean_inter_arrival(&self) -> Option<Duration> {
+        inter_arrival(self.time_span(), self.frames)
+    }
+}
+
+/// Storage statistics of a symbol, see `Quotick::stats`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct QuotickStats {
+    /// Per epoch statistics, oldest first.
+    pub epochs: Vec<EpochStats>,
+}
+
+impl QuotickStats {
+    #[inline(always)]
+    pub fn frames(&self) -> usize {
+        self.epochs.iter().map(|epoch| epoch.frames).sum()
+    }
+
+    #[inline(always)]
+    pub fn compressed_bytes(&self) -> u64 {
+        self.epochs.iter().map(|epoch| epoch.compressed_bytes).sum()
+    }
+
+    #[inline(always)]
+    pub fn uncompressed_bytes(&self) -> u64 {
+        self.epochs.iter().map(|epoch| epoch.uncompressed_bytes).sum()
+    }
+
+    #[inline(always)]
+    pub fn compression_ratio(&self) -> f64 {
+        ratio(self.uncompressed_bytes(), self.compressed_bytes())
+    }
+
+    #[inline(always)]
+    pub fn first_time(&self) -> Option<u64> {
+        self.epochs.iter().find_map(|epoch| epoch.first_time)
+    }
+
+    #[inline(always)]
+    pub fn last_time(&self) -> Option<u64> {
+        self.epochs.iter().rev().find_map(|epoch| epoch.last_time)
+    }
+
+    #[inline(always)]
+    pub fn time_span(&self) -> Option<Duration> {
+        span(self.first_time(), self.last_time())
+    }
+
+    /// Mean time between consecutive frames, across epochs.
+    #[inline(always)]
+    pub fn mean_inter_arrival(&self) -> Option<Duration> {
+        inter_arrival(self.time_span(), self.frames())
+    }
+
+    #[inline(always)]
+    pub fn overwrites(&self) -> u64 {
+        self.epochs.iter().map(|epoch| epoch.counters.overwrites).sum()
+    }
+
+    #[inline(always)]
+    pub fn duplicates(&self) -> u64 {
+        self.epochs.iter().map(|epoch| epoch.counters.duplicates).sum()
+    }
+}
+
+#[inline(always)]
+fn ratio(
+    uncompressed: u64,
+    compressed: u64,
+) -> f64 {
+    if compressed == 0 {
+        return 0.0;
+    }
+
+    uncompressed as f64 / compressed as f64
+}
+
+#[inline(always)]
+fn span(
+    first: Option<u64>,
+    last: Option<u64>,
+) -> Option<Duration> {
+    Some(Duration::from_nanos(last?.saturating_sub(first?)))
+}
+
+#[inline(always)]
+fn inter_arrival(
+    span: Option<Duration>,
+    frames: usize,
+) -> Option<Duration> {
+    if frames < 2 {
+        return None;
+    }
+
+    Some(Duration::from_nanos((span?.as_nanos() / (frames as u128 - 1)) as u64))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::quotick::Quotick;
+    use super::super::test_util::TempPath;
+    use super::super::tick::Trade;
+    use super::super::Frame;
+    use super::*;
+
+    #[test]
+    fn reports_sizes_spans_and_counters() {
+        let base_path = TempPath::new("stats");
+        let day = 86_400_000_000_000;
+
+        let mut quotick = Quotick::<Trade>::new("SYMBL", &base_path).unwrap();
+
+        for time in [0, 10, 20, day] {
+            quotick
+                .insert(&Frame::new(time, Trade::default()))
+                .unwrap();
+        }
+
+        quotick
+            .insert_opt(&Frame::new(10, Trade { size: 1, ..Trade::default() }), true)
+            .unwrap();
+
+        assert!(quotick.insert(&Frame::new(20, Trade::default())).is_err());
+
+        quotick.persist().unwrap();
+
+        let epoch = quotick.epoch_stats(0).unwrap();
+
+        assert_eq!(epoch.frames, 3);
+        assert_eq!(epoch.time_span(), Some(Duration::from_nanos(20)));
+        assert_eq!(epoch.mean_inter_arrival(), Some(Duration::from_nanos(10)));
+        assert_eq!(epoch.counters, EpochCounters { overwrites: 1, duplicates: 1 });
+        assert!(epoch.compressed_bytes > 0);
+        assert!(epoch.uncompressed_bytes > 0);
+
+        drop(quotick);
+
+        // counters survive reopening.
+        let quotick = Quotick::<Trade>::new("SYMBL", &base_path).unwrap();
+
+        let stats = quotick.stats().unwrap();
+
+        assert_eq!(stats.epochs.len(), 2);
+        assert_eq!(stats.frames(), 4);
+        assert_eq!(stats.time_span(), Some(Duration::from_nanos(day)));
+        assert_eq!(stats.mean_inter_arrival(), Some(Duration::from_nanos(day / 3)));
+        assert_eq!(stats.overwrites(), 1);
+        assert_eq!(stats.duplicates(), 1);
+        assert!(stats.compression_ratio() > 0.0);
+        assert_eq!(stats.epochs[1].mean_inter_arrival(), None);
+    }
+}
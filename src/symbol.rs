@@ -0,0 +1,207 @@
+use std::fmt;
+
+/// Longest directory name we are willing to produce; most filesystems
+/// cap a single path component at 255 bytes.
+const MAX_DIR_NAME_LEN: usize = 255;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolError {
+    Empty,
+    TooLong,
+    ControlCharacter,
+    PathEscape,
+    BadEncoding,
+}
+
+/// A validated ticker symbol.
+///
+/// Arbitrary tickers (`BRK/A`, `ES=F`, `BTC-USD`, ..) are mapped to a
+/// single, safe directory name: ASCII letters, digits, `-`, `_` and
+/// non-leading `.` are kept as-is, every other byte is percent-encoded.
+/// The mapping is reversible, see `Symbol::from_dir_name`.
+///
+/// Symbols that look like attempts to leave the database root, such as
+/// `../../etc` or `/tmp/x`, are rejected rather than encoded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol {
+    name: String,
+    dir_name: String,
+}
+
+impl Symbol {
+    #[inline(always)]
+    pub fn new(
+        name: &str,
+    ) -> Result<Symbol, SymbolError> {
+        if name.is_empty() {
+            return Err(SymbolError::Empty);
+        }
+
+        if name.chars().any(|c| c.is_control()) {
+            return Err(SymbolError::ControlCharacter);
+        }
+
+        let escapes =
+            name
+                .split(['/', '\\'])
+                .any(|component|
+                    component.is_empty()
+                        || component == "."
+                        || component == ".."
+                );
+
+        if escapes {
+            return Err(SymbolError::PathEscape);
+        }
+
+        let dir_name = encode(name);
+
+        if dir_name.len() > MAX_DIR_NAME_LEN {
+            return Err(SymbolError::TooLong);
+        }
+
+        Ok(
+            Symbol {
+                name: name.to_string(),
+                dir_name,
+            },
+        )
+    }
+
+    /// Recovers a symbol from a directory name produced by `dir_name`.
+    #[inline(always)]
+    pub fn from_dir_name(
+        dir_name: &str,
+    ) -> Result<Symbol, SymbolError> {
+        let name = decode(dir_name)?;
+        let symbol = Symbol::new(&name)?;
+
+        // only accept the canonical encoding, so that every symbol
+        // maps to exactly one directory.
+        if symbol.dir_name != dir_name {
+            return Err(SymbolError::BadEncoding);
+        }
+
+        Ok(symbol)
+    }
+
+    #[inline(always)]
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    #[inline(always)]
+    pub fn dir_name(&self) -> &str {
+        &self.dir_name
+    }
+}
+
+impl fmt::Display for Symbol {
+    #[inline(always)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+#[inline(always)]
+fn is_safe(
+    byte: u8,
+    pos: usize,
+) -> bool {
+    match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' => true,
+        b'.' => pos > 0,
+        _ => false,
+    }
+}
+
+fn encode(
+    name: &str,
+) -> String {
+    let mut encoded = String::with_capacity(name.len());
+
+    for (pos, &byte) in name.as_bytes().iter().enumerate() {
+        if is_safe(byte, pos) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    encoded
+}
+
+fn decode(
+    dir_name: &str,
+) -> Result<String, SymbolError> {
+    let bytes = dir_name.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        if bytes[pos] != b'%' {
+            decoded.push(bytes[pos]);
+            pos += 1;
+
+            continue;
+        }
+
+        let hex =
+            dir_name
+                .get(pos + 1..pos + 3)
+                .ok_or(SymbolError::BadEncoding)?;
+
+        decoded.push(
+            u8::from_str_radix(hex, 16)
+                .map_err(|_| SymbolError::BadEncoding)?,
+        );
+
+        pos += 3;
+    }
+
+    String::from_utf8(decoded)
+        .map_err(|_| SymbolError::BadEncoding)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn plain_symbols_are_unchanged() {
+        for name in &["AAPL", "BTC-USD", "SPY_W", "BRK.A"] {
+            assert_eq!(Symbol::new(name).unwrap().dir_name(), *name);
+        }
+    }
+
+    #[test]
+    fn odd_symbols_roundtrip() {
+        for name in &["BRK/A", "ES=F", "^GSPC", ".DJI", "EUR/USD:FX", "日経"] {
+            let symbol = Symbol::new(name).unwrap();
+
+            assert!(!symbol.dir_name().contains('/'));
+            assert!(!symbol.dir_name().starts_with('.'));
+            assert_eq!(Symbol::from_dir_name(symbol.dir_name()).unwrap(), symbol);
+        }
+
+        assert_eq!(Symbol::new("BRK/A").unwrap().dir_name(), "BRK%2FA");
+    }
+
+    #[test]
+    fn escapes_are_rejected() {
+        for name in &["../../etc", "/tmp/x", "..", ".", "A//B", "A/", "..\\x"] {
+            assert_eq!(Symbol::new(name), Err(SymbolError::PathEscape));
+        }
+
+        assert_eq!(Symbol::new(""), Err(SymbolError::Empty));
+        assert_eq!(Symbol::new("A\nB"), Err(SymbolError::ControlCharacter));
+        assert!(Symbol::new(&"A".repeat(256)).is_err());
+    }
+
+    #[test]
+    fn non_canonical_dir_names_are_rejected() {
+        assert_eq!(Symbol::from_dir_name("%41APL"), Err(SymbolError::BadEncoding));
+        assert_eq!(Symbol::from_dir_name("BRK%2"), Err(SymbolError::BadEncoding));
+        assert!(Symbol::from_dir_name("..%2Fetc").is_err());
+    }
+}
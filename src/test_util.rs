@@ -0,0 +1,69 @@
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::quotick::Quotick;
+use super::tick::Trade;
+
+/// A fresh, not yet existing path below the system temp dir, removed
+/// along with everything below it when dropped.
+///
+/// Bind it before the handles using it, so it is dropped after them.
+pub struct TempPath {
+    path: PathBuf,
+}
+
+impl TempPath {
+    pub fn new(
+        name: &str,
+    ) -> TempPath {
+        let nanos =
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos();
+
+        TempPath {
+            path: std::env::temp_dir()
+                .join(format!("quotick-{}-{}-{}", name, std::process::id(), nanos)),
+        }
+    }
+}
+
+impl Deref for TempPath {
+    type Target = Path;
+
+    #[inline(always)]
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    #[inline(always)]
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ =
+            match self.path.is_dir() {
+                true => fs::remove_dir_all(&self.path),
+                false => fs::remove_file(&self.path),
+            };
+    }
+}
+
+/// Sizes of the trades of `quotick`, oldest first.
+pub fn sizes(
+    quotick: &Quotick<Trade>,
+) -> Vec<u64> {
+    let mut sizes = Vec::new();
+
+    quotick.for_each_frame(|frame| sizes.push(frame.tick().size)).unwrap();
+
+    sizes
+}
@@ -0,0 +1,119 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::super::archive::FixedLayout;
+use super::super::epoch_rule::EpochRule;
+use super::super::price::Price;
+use super::{FieldKind, Tick, TickField};
+
+/// An OHLCV bar; the frame time is the start of the bar.
+///
+/// Bars are far sparser than ticks, so they are stored in monthly epochs.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[repr(C)]
+pub struct Bar {
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    pub volume: u64,
+    /// Volume weighted average price, zero if `volume` is.
+    pub vwap: Price,
+    pub trades: u64,
+}
+
+impl Bar {
+    /// A bar opened by a single trade.
+    #[inline(always)]
+    pub fn new(
+        price: Price,
+        size: u64,
+    ) -> Bar {
+        Bar {
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: size,
+            vwap: price,
+            trades: 1,
+        }
+    }
+
+    /// Adds a trade to the bar.
+    #[inline(always)]
+    pub fn update(
+        &mut self,
+        price: Price,
+        size: u64,
+    ) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+
+        let volume = self.volume + size;
+
+        if volume > 0 {
+            let notional =
+                self.vwap.raw() as i128 * self.volume as i128
+                    + price.raw() as i128 * size as i128;
+
+            self.vwap = Price::from_raw((notional / volume as i128) as i64);
+        }
+
+        self.volume = volume;
+        self.trades += 1;
+    }
+}
+
+// Safety: `repr(C)` of five `Price`s, each a transparent `i64`, and two
+// `u64` counts: 56 bytes, 8 byte aligned, without padding or invalid bit
+// patterns.
+unsafe impl FixedLayout for Bar {}
+
+impl Tick for Bar {
+    #[inline(always)]
+    fn epoch_rule() -> EpochRule {
+        EpochRule::Month
+    }
+
+    #[inline(always)]
+    fn type_name() -> &'static str {
+        "quotick::Bar"
+    }
+
+    #[inline(always)]
+    fn fields() -> Vec<TickField> {
+        vec![
+            TickField::new("open", FieldKind::Price(8)),
+            TickField::new("high", FieldKind::Price(8)),
+            TickField::new("low", FieldKind::Price(8)),
+            TickField::new("close", FieldKind::Price(8)),
+            TickField::new("volume", FieldKind::U64),
+            TickField::new("vwap", FieldKind::Price(8)),
+            TickField::new("trades", FieldKind::U64),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn aggregates_trades() {
+        let price = |price: &str| price.parse::<Price>().unwrap();
+
+        let mut bar = Bar::new(price("10"), 100);
+
+        bar.update(price("12"), 100);
+        bar.update(price("9"), 200);
+
+        assert_eq!(bar.open, price("10"));
+        assert_eq!(bar.high, price("12"));
+        assert_eq!(bar.low, price("9"));
+        assert_eq!(bar.close, price("9"));
+        assert_eq!(bar.volume, 400);
+        assert_eq!(bar.vwap, price("10"));
+        assert_eq!(bar.trades, 3);
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::super::price::Price;
+use super::{FieldKind, Tick, TickField, Venue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+impl Side {
+    #[inline(always)]
+    pub fn field_kind() -> FieldKind {
+        FieldKind::enumeration(&["Bid", "Ask"])
+    }
+}
+
+/// New state of one price level. A `size` of `0` removes the level.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LevelChange {
+    pub side: Side,
+    pub price: Price,
+    pub size: u64,
+    pub order_count: u32,
+}
+
+impl LevelChange {
+    #[inline(always)]
+    pub fn field_kind() -> FieldKind {
+        FieldKind::Struct(
+            vec![
+                TickField::new("side", Side::field_kind()),
+                TickField::new("price", FieldKind::Price(8)),
+                TickField::new("size", FieldKind::U64),
+                TickField::new("order_count", FieldKind::U32),
+            ],
+        )
+    }
+}
+
+/// Level 2 order book update.
+///
+/// A frame holds every level changed at its timestamp, since frames are
+/// keyed by time and a single message usually touches several levels.
+/// If `snapshot` is set, the book is cleared before applying `changes`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct BookDelta {
+    pub sequence: u64,
+    pub venue: Venue,
+    pub snapshot: bool,
+    pub changes: Vec<LevelChange>,
+}
+
+impl Tick for BookDelta {
+    #[inline(always)]
+    fn type_name() -> &'static str {
+        "quotick::BookDelta"
+    }
+
+    #[inline(always)]
+    fn fields() -> Vec<TickField> {
+        vec![
+            TickField::new("sequence", FieldKind::U64),
+            TickField::new("venue", Venue::field_kind()),
+            TickField::new("snapshot", FieldKind::Bool),
+            TickField::new("changes", FieldKind::Seq(Box::new(LevelChange::field_kind()))),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Level {
+    pub size: u64,
+    pub order_count: u32,
+}
+
+/// An order book rebuilt from `BookDelta`s.
+#[derive(Debug, Clone, Default)]
+pub struct OrderBook {
+    bids: BTreeMap<Price, Level>,
+    asks: BTreeMap<Price, Level>,
+    sequence: u64,
+}
+
+impl OrderBook {
+    #[inline(always)]
+    pub fn new() -> OrderBook {
+        OrderBook::default()
+    }
+
+    #[inline(always)]
+    pub fn apply(
+        &mut self,
+        delta: &BookDelta,
+    ) {
+        if delta.snapshot {
+            self.bids.clear();
+            self.asks.clear();
+        }
+
+        for change in delta.changes.iter() {
+            let levels =
+                match change.side {
+                    Side::Bid => &mut self.bids,
+                    Side::Ask => &mut self.asks,
+                };
+
+            if change.size == 0 {
+                levels.remove(&change.price);
+            } else {
+                levels.insert(
+                    change.price,
+                    Level {
+                        size: change.size,
+                        order_count: change.order_count,
+                    },
+                );
+            }
+        }
+
+        self.sequence = delta.sequence;
+    }
+
+    /// Sequence number of the last applied delta.
+    #[inline(always)]
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    #[inline(always)]
+    pub fn best_bid(&self) -> Option<(Price, Level)> {
+        self.bids
+            .iter()
+            .next_back()
+            .map(|(&price, &level)| (price, level))
+    }
+
+    #[inline(always)]
+    pub fn best_ask(&self) -> Option<(Price, Level)> {
+        self.asks
+            .iter()
+            .next()
+            .map(|(&price, &level)| (price, level))
+    }
+
+    /// Bid levels, best first.
+    #[inline(always)]
+    pub fn bids(&self) -> impl Iterator<Item=(&Price, &Level)> {
+        self.bids.iter().rev()
+    }
+
+    /// Ask levels, best first.
+    #[inline(always)]
+    pub fn asks(&self) -> impl Iterator<Item=(&Price, &Level)> {
+        self.asks.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn change(side: Side, price: &str, size: u64) -> LevelChange {
+        LevelChange {
+            side,
+            price: price.parse().unwrap(),
+            size,
+            order_count: 1,
+        }
+    }
+
+    #[test]
+    fn applies_deltas() {
+        let mut book = OrderBook::new();
+
+        book.apply(
+            &BookDelta {
+                sequence: 1,
+                snapshot: true,
+                changes: vec![
+                    change(Side::Bid, "99.5", 100),
+                    change(Side::Bid, "99.75", 200),
+                    change(Side::Ask, "100", 300),
+                    change(Side::Ask, "100.25", 400),
+                ],
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(book.best_bid().unwrap().0, "99.75".parse().unwrap());
+        assert_eq!(book.best_ask().unwrap().1.size, 300);
+
+        book.apply(
+            &BookDelta {
+                sequence: 2,
+                changes: vec![
+                    change(Side::Bid, "99.75", 0),
+                    change(Side::Ask, "100", 50),
+                ],
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(book.best_bid().unwrap().0, "99.5".parse().unwrap());
+        assert_eq!(book.best_ask().unwrap().1.size, 50);
+        assert_eq!(book.asks().count(), 2);
+        assert_eq!(book.sequence(), 2);
+
+        let delta =
+            BookDelta {
+                sequence: 3,
+                snapshot: true,
+                changes: vec![change(Side::Ask, "101", 1)],
+                ..Default::default()
+            };
+
+        assert_eq!(bincode::deserialize::<BookDelta>(&bincode::serialize(&delta).unwrap()).unwrap(), delta);
+
+        book.apply(&delta);
+
+        assert!(book.best_bid().is_none());
+        assert_eq!(book.asks().count(), 1);
+    }
+}
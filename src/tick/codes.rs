@@ -0,0 +1,104 @@
+use std::fmt;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::FieldKind;
+
+/// Exchange or venue a tick originated from.
+///
+/// The numbering is up to the feed; for US equities the SIP participant
+/// ids (`b'N'` for NYSE, `b'Q'` for Nasdaq, ...) fit without a lookup
+/// table. `0` means unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Venue(pub u8);
+
+impl Venue {
+    pub const UNKNOWN: Venue = Venue(0);
+
+    #[inline(always)]
+    pub fn field_kind() -> FieldKind {
+        FieldKind::U8
+    }
+}
+
+/// Consolidated tape a US equity trade or quote was reported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Tape {
+    #[default]
+    Unknown,
+    /// NYSE listed
+    A,
+    /// NYSE Arca, NYSE American and regional listed
+    B,
+    /// Nasdaq listed
+    C,
+}
+
+impl Tape {
+    #[inline(always)]
+    pub fn field_kind() -> FieldKind {
+        FieldKind::enumeration(&["Unknown", "A", "B", "C"])
+    }
+}
+
+/// Up to four sale condition codes of a trade or quote, as sent by the
+/// feed. Unused slots are `0`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Conditions(pub [u8; 4]);
+
+impl Conditions {
+    pub const NONE: Conditions = Conditions([0; 4]);
+
+    /// Takes the first four non-zero codes.
+    #[inline(always)]
+    pub fn new(
+        codes: &[u8],
+    ) -> Conditions {
+        let mut conditions = Conditions::NONE;
+
+        codes
+            .iter()
+            .filter(|&&code| code != 0)
+            .take(4)
+            .enumerate()
+            .for_each(|(idx, &code)| conditions.0[idx] = code);
+
+        conditions
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> impl Iterator<Item=u8> + '_ {
+        self.0
+            .iter()
+            .copied()
+            .filter(|&code| code != 0)
+    }
+
+    #[inline(always)]
+    pub fn contains(
+        &self,
+        code: u8,
+    ) -> bool {
+        code != 0 && self.0.contains(&code)
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    #[inline(always)]
+    pub fn field_kind() -> FieldKind {
+        FieldKind::Array(Box::new(FieldKind::U8), 4)
+    }
+}
+
+impl fmt::Debug for Conditions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.iter().map(|code| code as char))
+            .finish()
+    }
+}
@@ -0,0 +1,250 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::epoch_rule::EpochRule;
+use super::meta::fingerprint;
+use super::schema::SchemaUpgrades;
+
+pub mod bar;
+pub mod book;
+pub mod codes;
+pub mod option;
+pub mod quote;
+pub mod trade;
+
+pub use bar::Bar;
+pub use book::{BookDelta, LevelChange, OrderBook, Side};
+pub use codes::{Conditions, Tape, Venue};
+pub use option::{Greeks, OptionQuote, OptionRight};
+pub use quote::{ConsolidatedQuote, Quote, QuoteV0};
+pub use trade::{ConsolidatedTrade, Trade, TradeV0};
+
+/// Serialized type of a single tick field.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum FieldKind {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    String,
+    /// A `Price` with the given scale.
+    Price(u32),
+    /// A `chrono::NaiveDate`, serialized as `YYYY-MM-DD`.
+    Date,
+    /// A fieldless enum, serialized as the index of the variant.
+    Enum(Vec<String>),
+    /// A fixed size array.
+    Array(Box<FieldKind>, usize),
+    /// A length prefixed sequence, i.e. a `Vec`.
+    Seq(Box<FieldKind>),
+    /// A nested struct.
+    Struct(Vec<TickField>),
+    Other(String),
+}
+
+/// Describes one field of a tick type in declaration (= serialization) order.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TickField {
+    pub name: String,
+    pub kind: FieldKind,
+}
+
+impl TickField {
+    #[inline(always)]
+    pub fn new(
+        name: &str,
+        kind: FieldKind,
+    ) -> TickField {
+        TickField {
+            name: name.to_string(),
+            kind,
+        }
+    }
+}
+
+impl FieldKind {
+    #[inline(always)]
+    pub fn enumeration(
+        variants: &[&str],
+    ) -> FieldKind {
+        FieldKind::Enum(
+            variants
+                .iter()
+                .map(|variant| variant.to_string())
+                .collect(),
+        )
+    }
+}
+
+pub trait Tick: Clone + std::fmt::Debug {
+    /// Partitioning of the tick type into epochs.
+    #[inline(always)]
+    fn epoch_rule() -> EpochRule {
+        EpochRule::Day
+    }
+
+    /// Name of the tick type as recorded in the symbol metadata.
+    #[inline(always)]
+    fn type_name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Fields of the tick type in serialization order, if known.
+    #[inline(always)]
+    fn fields() -> Vec<TickField> {
+        Vec::new()
+    }
+
+    /// Identifies the serialized layout of the tick type.
+    ///
+    /// The default is derived from `fields` if the type describes them,
+    /// otherwise from the type name and its in-memory size, which catches
+    /// most, but not all, layout changes.
+    #[inline(always)]
+    fn schema_fingerprint() -> u64 {
+        let fields = Self::fields();
+
+        if fields.is_empty() {
+            return fingerprint(
+                format!(
+                    "{}:{}:{}",
+                    Self::type_name(),
+                    std::mem::size_of::<Self>(),
+                    std::mem::align_of::<Self>(),
+                )
+                    .as_bytes(),
+            );
+        }
+
+        fingerprint(
+            fields
+                .iter()
+                .map(|field| format!("{}:{:?}", field.name, field.kind))
+                .collect::<Vec<_>>()
+                .join(",")
+                .as_bytes(),
+        )
+    }
+
+    /// Version of the serialized layout; bump it whenever fields change
+    /// and register an upgrade from the previous layout.
+    #[inline(always)]
+    fn schema_version() -> u32 {
+        0
+    }
+
+    /// Registers conversions from older schema versions, see
+    /// `SchemaUpgrades`. Called whenever a symbol is opened.
+    #[inline(always)]
+    fn register_upgrades(_upgrades: &mut SchemaUpgrades<Self>) {}
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+
+    use chrono::NaiveDate;
+
+    use super::super::epoch::EpochError;
+    use super::super::price::PriceError;
+    use super::super::quotick::Quotick;
+    use super::super::test_util::TempPath;
+    use super::super::Frame;
+    use super::*;
+
+    #[test]
+    fn consolidated_ticks_round_trip() {
+        let base_path = TempPath::new("tick");
+
+        let trade =
+            ConsolidatedTrade {
+                id: 42,
+                size: 100,
+                price: "123.45".parse().unwrap(),
+                venue: Venue(b'Q'),
+                conditions: Conditions::new(b"@FI"),
+                tape: Tape::C,
+                participant_time: 1_000,
+                sip_time: 1_250,
+            };
+
+        let option =
+            OptionQuote {
+                right: OptionRight::Put,
+                strike: "120".parse().unwrap(),
+                expiration: NaiveDate::from_ymd_opt(2021, 3, 19).unwrap(),
+                bid_price: "1.2".parse().unwrap(),
+                bid_size: 10,
+                ask_price: "1.3".parse().unwrap(),
+                ask_size: 20,
+                underlying_price: "119".parse().unwrap(),
+                implied_volatility: 0.25,
+                greeks: Greeks {
+                    delta: -0.55,
+                    ..Default::default()
+                },
+            };
+
+        {
+            let mut trades = Quotick::<ConsolidatedTrade>::new("SYMBL", &base_path).unwrap();
+            let mut options = Quotick::<OptionQuote>::new("SYMBL 210319P120", &base_path).unwrap();
+
+            trades.insert(&Frame::new(1_250, trade.clone())).unwrap();
+            options.insert(&Frame::new(1_250, option.clone())).unwrap();
+        }
+
+        let trades = Quotick::<ConsolidatedTrade>::new("SYMBL", &base_path).unwrap();
+        let options = Quotick::<OptionQuote>::new("SYMBL 210319P120", &base_path).unwrap();
+
+        let stored = trades.newest_frame().unwrap();
+
+        assert_eq!(stored.tick(), &trade);
+        assert!(stored.tick().conditions.contains(b'F'));
+        assert_eq!(stored.tick().conditions.iter().count(), 3);
+
+        let stored = options.newest_frame().unwrap();
+
+        assert_eq!(stored.tick(), &option);
+        assert_eq!(stored.tick().intrinsic_value().to_string(), "1.00000000");
+        assert_eq!(stored.tick().mid().to_string(), "1.25000000");
+
+        assert_ne!(ConsolidatedQuote::schema_fingerprint(), Quote::schema_fingerprint());
+    }
+
+    #[test]
+    fn v0_prices_convert_exactly_or_fail() {
+        let trade = Trade::try_from(TradeV0 { size: 3, price: 0.1 }).unwrap();
+
+        assert_eq!(trade.price, "0.1".parse().unwrap());
+        assert_eq!(trade.price.to_string(), "0.10000000");
+
+        let quote = Quote::try_from(QuoteV0 { size: 1, ask_price: 100.25, bid_price: 99.7 }).unwrap();
+
+        assert_eq!(quote.ask_price, "100.25".parse().unwrap());
+        assert_eq!(quote.bid_price, "99.7".parse().unwrap());
+
+        for price in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(Trade::try_from(TradeV0 { size: 1, price }), Err(PriceError::BadDigit));
+            assert_eq!(Quote::try_from(QuoteV0 { size: 1, ask_price: 1.0, bid_price: price }), Err(PriceError::BadDigit));
+        }
+
+        assert_eq!(Trade::try_from(TradeV0 { size: 1, price: 1e30 }), Err(PriceError::Overflow));
+
+        // epochs with prices that don't convert fail to upgrade.
+        let mut upgrades = SchemaUpgrades::<Trade>::new();
+
+        Trade::register_upgrades(&mut upgrades);
+
+        let good = bincode::serialize(&vec![(1u64, TradeV0 { size: 1, price: 2.5 })]).unwrap();
+        let bad = bincode::serialize(&vec![(1u64, TradeV0 { size: 1, price: f32::NAN })]).unwrap();
+
+        assert_eq!(upgrades.upgrade(0, &good).unwrap().get(&1), Some(&Trade { size: 1, price: "2.5".parse().unwrap() }));
+        assert!(matches!(upgrades.upgrade(0, &bad), Err(EpochError::UpgradeFailed(0))));
+    }
+}
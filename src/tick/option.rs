@@ -0,0 +1,106 @@
+use chrono::NaiveDate;
+use serde_derive::{Deserialize, Serialize};
+
+use super::super::price::Price;
+use super::{FieldKind, Tick, TickField};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum OptionRight {
+    #[default]
+    Call,
+    Put,
+}
+
+impl OptionRight {
+    #[inline(always)]
+    pub fn field_kind() -> FieldKind {
+        FieldKind::enumeration(&["Call", "Put"])
+    }
+}
+
+/// Sensitivities of an option's price, as computed by the data vendor.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Greeks {
+    pub delta: f64,
+    pub gamma: f64,
+    pub theta: f64,
+    pub vega: f64,
+    pub rho: f64,
+}
+
+impl Greeks {
+    #[inline(always)]
+    pub fn field_kind() -> FieldKind {
+        FieldKind::Struct(
+            vec![
+                TickField::new("delta", FieldKind::F64),
+                TickField::new("gamma", FieldKind::F64),
+                TickField::new("theta", FieldKind::F64),
+                TickField::new("vega", FieldKind::F64),
+                TickField::new("rho", FieldKind::F64),
+            ],
+        )
+    }
+}
+
+/// A quote of a single option contract.
+///
+/// The contract terms are repeated on every quote so that a symbol can
+/// hold a whole chain, i.e. all contracts of an underlying.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct OptionQuote {
+    pub right: OptionRight,
+    pub strike: Price,
+    pub expiration: NaiveDate,
+    pub bid_price: Price,
+    pub bid_size: u64,
+    pub ask_price: Price,
+    pub ask_size: u64,
+    pub underlying_price: Price,
+    pub implied_volatility: f64,
+    pub greeks: Greeks,
+}
+
+impl OptionQuote {
+    #[inline(always)]
+    pub fn mid(&self) -> Price {
+        Price::from_raw(
+            ((self.bid_price.raw() as i128 + self.ask_price.raw() as i128) / 2) as i64,
+        )
+    }
+
+    /// Value of exercising now at `underlying_price`, never negative.
+    #[inline(always)]
+    pub fn intrinsic_value(&self) -> Price {
+        let value =
+            match self.right {
+                OptionRight::Call => self.underlying_price - self.strike,
+                OptionRight::Put => self.strike - self.underlying_price,
+            };
+
+        value.max(Price::ZERO)
+    }
+}
+
+impl Tick for OptionQuote {
+    #[inline(always)]
+    fn type_name() -> &'static str {
+        "quotick::OptionQuote"
+    }
+
+    #[inline(always)]
+    fn fields() -> Vec<TickField> {
+        vec![
+            TickField::new("right", OptionRight::field_kind()),
+            TickField::new("strike", FieldKind::Price(8)),
+            TickField::new("expiration", FieldKind::Date),
+            TickField::new("bid_price", FieldKind::Price(8)),
+            TickField::new("bid_size", FieldKind::U64),
+            TickField::new("ask_price", FieldKind::Price(8)),
+            TickField::new("ask_size", FieldKind::U64),
+            TickField::new("underlying_price", FieldKind::Price(8)),
+            TickField::new("implied_volatility", FieldKind::F64),
+            TickField::new("greeks", Greeks::field_kind()),
+        ]
+    }
+}
@@ -0,0 +1,132 @@
+use std::convert::TryFrom;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::super::archive::FixedLayout;
+use super::super::price::{Price, PriceError};
+use super::super::schema::SchemaUpgrades;
+use super::{Conditions, FieldKind, Tape, Tick, TickField, Venue};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[repr(C)]
+pub struct Quote {
+    pub size: u64,
+    pub ask_price: Price,
+    pub bid_price: Price,
+}
+
+/// Layout of `Quote` before prices moved to `Price` (schema version 0).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct QuoteV0 {
+    pub size: u64,
+    pub ask_price: f32,
+    pub bid_price: f32,
+}
+
+/// A top of book quote as reported on a consolidated feed, with separate
+/// sizes and venues for both sides.
+///
+/// See `ConsolidatedTrade` for `participant_time` and `sip_time`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ConsolidatedQuote {
+    pub bid_price: Price,
+    pub bid_size: u64,
+    pub bid_venue: Venue,
+    pub ask_price: Price,
+    pub ask_size: u64,
+    pub ask_venue: Venue,
+    pub conditions: Conditions,
+    pub tape: Tape,
+    pub participant_time: u64,
+    pub sip_time: u64,
+}
+
+impl ConsolidatedQuote {
+    #[inline(always)]
+    pub fn spread(&self) -> Price {
+        self.ask_price - self.bid_price
+    }
+
+    #[inline(always)]
+    pub fn mid(&self) -> Price {
+        Price::from_raw(
+            ((self.bid_price.raw() as i128 + self.ask_price.raw() as i128) / 2) as i64,
+        )
+    }
+
+    #[inline(always)]
+    pub fn is_crossed(&self) -> bool {
+        self.bid_price > self.ask_price
+    }
+}
+
+/// Converts the shortest decimal representation of the `f32` prices, see
+/// `Trade`'s conversion from `TradeV0`.
+impl TryFrom<QuoteV0> for Quote {
+    type Error = PriceError;
+
+    #[inline(always)]
+    fn try_from(quote: QuoteV0) -> Result<Self, PriceError> {
+        Ok(
+            Quote {
+                size: quote.size,
+                ask_price: quote.ask_price.to_string().parse()?,
+                bid_price: quote.bid_price.to_string().parse()?,
+            },
+        )
+    }
+}
+
+// Safety: `repr(C)` of a `u64` followed by two `Price`s, each a
+// transparent `i64`: 24 bytes, 8 byte aligned, without padding or invalid
+// bit patterns.
+unsafe impl FixedLayout for Quote {}
+
+impl Tick for Quote {
+    #[inline(always)]
+    fn type_name() -> &'static str {
+        "quotick::Quote"
+    }
+
+    #[inline(always)]
+    fn fields() -> Vec<TickField> {
+        vec![
+            TickField::new("size", FieldKind::U64),
+            TickField::new("ask_price", FieldKind::Price(8)),
+            TickField::new("bid_price", FieldKind::Price(8)),
+        ]
+    }
+
+    #[inline(always)]
+    fn schema_version() -> u32 {
+        1
+    }
+
+    #[inline(always)]
+    fn register_upgrades(upgrades: &mut SchemaUpgrades<Self>) {
+        upgrades.try_register(0, |quote: QuoteV0| Quote::try_from(quote));
+    }
+}
+
+impl Tick for ConsolidatedQuote {
+    #[inline(always)]
+    fn type_name() -> &'static str {
+        "quotick::ConsolidatedQuote"
+    }
+
+    #[inline(always)]
+    fn fields() -> Vec<TickField> {
+        vec![
+            TickField::new("bid_price", FieldKind::Price(8)),
+            TickField::new("bid_size", FieldKind::U64),
+            TickField::new("bid_venue", Venue::field_kind()),
+            TickField::new("ask_price", FieldKind::Price(8)),
+            TickField::new("ask_size", FieldKind::U64),
+            TickField::new("ask_venue", Venue::field_kind()),
+            TickField::new("conditions", Conditions::field_kind()),
+            TickField::new("tape", Tape::field_kind()),
+            TickField::new("participant_time", FieldKind::U64),
+            TickField::new("sip_time", FieldKind::U64),
+        ]
+    }
+}
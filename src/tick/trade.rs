@@ -0,0 +1,116 @@
+use std::convert::TryFrom;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::super::archive::FixedLayout;
+use super::super::price::{Price, PriceError};
+use super::super::schema::SchemaUpgrades;
+use super::{Conditions, FieldKind, Tape, Tick, TickField, Venue};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[repr(C)]
+pub struct Trade {
+    pub size: u64,
+    pub price: Price,
+}
+
+/// Layout of `Trade` before prices moved to `Price` (schema version 0).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct TradeV0 {
+    pub size: u64,
+    pub price: f32,
+}
+
+/// A trade as reported on a consolidated feed.
+///
+/// `participant_time` is when the venue executed the trade,
+/// `sip_time` when the consolidator published it; both in nanoseconds
+/// since the unix epoch. Which of the two the frame is keyed by is up to
+/// the caller, the other one is kept here.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ConsolidatedTrade {
+    pub id: u64,
+    pub size: u64,
+    pub price: Price,
+    pub venue: Venue,
+    pub conditions: Conditions,
+    pub tape: Tape,
+    pub participant_time: u64,
+    pub sip_time: u64,
+}
+
+/// Converts the shortest decimal representation of the `f32` price, so
+/// `0.1` stays `0.1`. NaN, infinite and out of range prices fail.
+impl TryFrom<TradeV0> for Trade {
+    type Error = PriceError;
+
+    #[inline(always)]
+    fn try_from(trade: TradeV0) -> Result<Self, PriceError> {
+        Ok(
+            Trade {
+                size: trade.size,
+                price: trade.price.to_string().parse()?,
+            },
+        )
+    }
+}
+
+impl From<&ConsolidatedTrade> for Trade {
+    #[inline(always)]
+    fn from(trade: &ConsolidatedTrade) -> Self {
+        Trade {
+            size: trade.size,
+            price: trade.price,
+        }
+    }
+}
+
+// Safety: `repr(C)` of a `u64` and a `Price`, i.e. a transparent `i64`:
+// 16 bytes, 8 byte aligned, without padding or invalid bit patterns.
+unsafe impl FixedLayout for Trade {}
+
+impl Tick for Trade {
+    #[inline(always)]
+    fn type_name() -> &'static str {
+        "quotick::Trade"
+    }
+
+    #[inline(always)]
+    fn fields() -> Vec<TickField> {
+        vec![
+            TickField::new("size", FieldKind::U64),
+            TickField::new("price", FieldKind::Price(8)),
+        ]
+    }
+
+    #[inline(always)]
+    fn schema_version() -> u32 {
+        1
+    }
+
+    #[inline(always)]
+    fn register_upgrades(upgrades: &mut SchemaUpgrades<Self>) {
+        upgrades.try_register(0, |trade: TradeV0| Trade::try_from(trade));
+    }
+}
+
+impl Tick for ConsolidatedTrade {
+    #[inline(always)]
+    fn type_name() -> &'static str {
+        "quotick::ConsolidatedTrade"
+    }
+
+    #[inline(always)]
+    fn fields() -> Vec<TickField> {
+        vec![
+            TickField::new("id", FieldKind::U64),
+            TickField::new("size", FieldKind::U64),
+            TickField::new("price", FieldKind::Price(8)),
+            TickField::new("venue", Venue::field_kind()),
+            TickField::new("conditions", Conditions::field_kind()),
+            TickField::new("tape", Tape::field_kind()),
+            TickField::new("participant_time", FieldKind::U64),
+            TickField::new("sip_time", FieldKind::U64),
+        ]
+    }
+}
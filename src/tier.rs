@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Where an epoch file lives, see `TierPolicy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+    Hot,
+    Cold,
+}
+
+/// Moves epochs off the primary storage once they are old enough.
+///
+/// The hot tier is the base path of the database, typically NVMe. Sealed
+/// epochs that ended more than `cold_after` ago are recompressed at
+/// `compression_level` and moved below `cold_path`, e.g. a large HDD
+/// mount, by `Quotick::apply_tiering`. Epochs still open for writes stay
+/// hot.
+/// `QuotickPathBuilder` resolves epochs across both tiers, so readers do
+/// not need to know where an epoch is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TierPolicy {
+    /// Base path of the cold tier, laid out like the hot one.
+    pub cold_path: PathBuf,
+
+    pub cold_after: Duration,
+
+    /// Deflate level (0 - 10) used for cold epochs. There is no separate
+    /// high-ratio codec, cold epochs are deflate at level 10 by default,
+    /// the highest miniz supports.
+    pub compression_level: u8,
+}
+
+impl TierPolicy {
+    #[inline(always)]
+    pub fn new(
+        cold_path: impl Into<PathBuf>,
+        cold_after: Duration,
+    ) -> TierPolicy {
+        TierPolicy {
+            cold_path: cold_path.into(),
+            cold_after,
+            compression_level: 10,
+        }
+    }
+}
+
+/// Outcome of `Quotick::move_to_cold` and `Quotick::apply_tiering`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TieringReport {
+    pub epochs_moved: usize,
+    /// Size of the moved epochs in the hot tier.
+    pub bytes_hot: u64,
+    /// Size of the moved epochs in the cold tier, after recompression.
+    pub bytes_cold: u64,
+}
+
+impl TieringReport {
+    #[inline(always)]
+    pub(crate) fn add(
+        &mut self,
+        other: TieringReport,
+    ) {
+        self.epochs_moved += other.epochs_moved;
+        self.bytes_hot += other.bytes_hot;
+        self.bytes_cold += other.bytes_cold;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::options::QuotickOptions;
+    use super::super::quotick::Quotick;
+    use super::super::test_util::{sizes, TempPath};
+    use super::super::tick::Trade;
+    use super::super::Frame;
+    use super::*;
+
+    const DAY: u64 = 86_400_000_000_000;
+
+    #[test]
+    fn moves_old_epochs_to_the_cold_tier() {
+        let cold_path = TempPath::new("cold");
+        let base_path = TempPath::new("hot");
+
+        let options =
+            QuotickOptions {
+                tiering: Some(TierPolicy::new(&*cold_path, Duration::from_secs(86_400))),
+                ..QuotickOptions::default()
+            };
+
+        let mut quotick = Quotick::<Trade>::with_options("SYMBL", &base_path, options.clone()).unwrap();
+
+        for day in 0..4 {
+            quotick
+                .insert(&Frame::new(day * DAY, Trade { size: day, ..Trade::default() }))
+                .unwrap();
+        }
+
+        // only sealed epochs move, epoch 2 is old enough but still open.
+        quotick.seal(0).unwrap();
+        quotick.seal(1).unwrap();
+
+        let report = quotick.apply_tiering().unwrap();
+
+        assert_eq!(report.epochs_moved, 2);
+        assert!(report.bytes_cold > 0);
+
+        let path_builder = quotick.path_builder().clone();
+
+        assert_eq!(path_builder.tier(0), Tier::Cold);
+        assert_eq!(path_builder.tier(2), Tier::Hot);
+        assert_eq!(path_builder.tier(3), Tier::Hot);
+        assert!(!path_builder.hot_index_backing_file(0).exists());
+        assert!(path_builder.cold_index_backing_file(0).unwrap().exists());
+
+        // readers resolve epochs across both tiers.
+        assert_eq!(sizes(&quotick), vec![0, 1, 2, 3]);
+
+        // writes to a cold epoch stay in the cold tier.
+        quotick.unseal(0).unwrap();
+
+        quotick
+            .insert(&Frame::new(DAY / 2, Trade::default()))
+            .unwrap();
+
+        drop(quotick);
+
+        assert_eq!(path_builder.tier(0), Tier::Cold);
+
+        let quotick = Quotick::<Trade>::with_options("SYMBL", &base_path, options).unwrap();
+
+        assert_eq!(quotick.oldest_frame().unwrap().time(), 0);
+        assert_eq!(quotick.epochs().next().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,314 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde_derive::{Deserialize, Serialize};
+
+use super::epoch_rule::{datetime_to_nanos, nanos_to_datetime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TimeUnit {
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl TimeUnit {
+    #[inline(always)]
+    pub const fn nanos(&self) -> u64 {
+        match self {
+            TimeUnit::Seconds => 1_000_000_000,
+            TimeUnit::Millis => 1_000_000,
+            TimeUnit::Micros => 1_000,
+            TimeUnit::Nanos => 1,
+        }
+    }
+
+    /// Guesses the unit of a unix timestamp from its magnitude, assuming
+    /// it lies between 1973 and 2286. Meant for probing unknown data, not
+    /// for converting it.
+    #[inline(always)]
+    pub fn detect(
+        value: u64,
+    ) -> TimeUnit {
+        match value {
+            0..=9_999_999_999 => TimeUnit::Seconds,
+            10_000_000_000..=9_999_999_999_999 => TimeUnit::Millis,
+            10_000_000_000_000..=9_999_999_999_999_999 => TimeUnit::Micros,
+            _ => TimeUnit::Nanos,
+        }
+    }
+}
+
+/// A point in time in nanoseconds since the unix epoch, the unit `Frame`
+/// times are stored in.
+///
+/// Constructing it from a vendor timestamp names the unit explicitly, so
+/// that milliseconds do not end up filed as nanoseconds in 1970.
+///
+/// ```
+/// use quotick::timestamp::{Timestamp, TimeUnit};
+///
+/// let time = Timestamp::from_millis(1_614_865_020_000).unwrap();
+///
+/// assert_eq!(time, Timestamp::from_unit(1_614_865_020_000_000, TimeUnit::Micros).unwrap());
+/// assert_eq!(time.to_string(), "2021-03-04T13:37:00.000000000Z");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    pub const UNIX_EPOCH: Timestamp = Timestamp(0);
+
+    #[inline(always)]
+    pub const fn from_nanos(
+        nanos: u64,
+    ) -> Timestamp {
+        Timestamp(nanos)
+    }
+
+    /// `None` if the result does not fit nanoseconds in a `u64`, i.e.
+    /// lies after 2554.
+    #[inline(always)]
+    pub fn from_unit(
+        value: u64,
+        unit: TimeUnit,
+    ) -> Option<Timestamp> {
+        value
+            .checked_mul(unit.nanos())
+            .map(Timestamp)
+    }
+
+    #[inline(always)]
+    pub fn from_secs(
+        secs: u64,
+    ) -> Option<Timestamp> {
+        Timestamp::from_unit(secs, TimeUnit::Seconds)
+    }
+
+    #[inline(always)]
+    pub fn from_millis(
+        millis: u64,
+    ) -> Option<Timestamp> {
+        Timestamp::from_unit(millis, TimeUnit::Millis)
+    }
+
+    #[inline(always)]
+    pub fn from_micros(
+        micros: u64,
+    ) -> Option<Timestamp> {
+        Timestamp::from_unit(micros, TimeUnit::Micros)
+    }
+
+    /// Seconds with a fractional part, as used by some JSON feeds;
+    /// rounded to the nearest microsecond, the precision an `f64` keeps.
+    #[inline(always)]
+    pub fn from_secs_f64(
+        secs: f64,
+    ) -> Option<Timestamp> {
+        let micros = (secs * 1e6).round();
+
+        if !micros.is_finite() || micros < 0.0 || micros > u64::MAX as f64 {
+            return None;
+        }
+
+        Timestamp::from_micros(micros as u64)
+    }
+
+    #[inline(always)]
+    pub fn from_datetime(
+        datetime: NaiveDateTime,
+    ) -> Timestamp {
+        Timestamp(datetime_to_nanos(datetime))
+    }
+
+    #[inline(always)]
+    pub fn now() -> Timestamp {
+        Timestamp(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos() as u64)
+                .unwrap_or(0),
+        )
+    }
+
+    #[inline(always)]
+    pub const fn as_nanos(&self) -> u64 {
+        self.0
+    }
+
+    /// Converts to `unit`, truncating.
+    #[inline(always)]
+    pub const fn as_unit(
+        &self,
+        unit: TimeUnit,
+    ) -> u64 {
+        self.0 / unit.nanos()
+    }
+
+    #[inline(always)]
+    pub const fn as_secs(&self) -> u64 {
+        self.as_unit(TimeUnit::Seconds)
+    }
+
+    #[inline(always)]
+    pub const fn as_millis(&self) -> u64 {
+        self.as_unit(TimeUnit::Millis)
+    }
+
+    #[inline(always)]
+    pub const fn as_micros(&self) -> u64 {
+        self.as_unit(TimeUnit::Micros)
+    }
+
+    #[inline(always)]
+    pub fn to_datetime(&self) -> NaiveDateTime {
+        nanos_to_datetime(self.0)
+    }
+
+    #[inline(always)]
+    pub fn checked_add(
+        &self,
+        duration: Duration,
+    ) -> Option<Timestamp> {
+        self.0
+            .checked_add(u64::try_from(duration.as_nanos()).ok()?)
+            .map(Timestamp)
+    }
+
+    #[inline(always)]
+    pub fn saturating_sub(
+        &self,
+        other: Timestamp,
+    ) -> Duration {
+        Duration::from_nanos(self.0.saturating_sub(other.0))
+    }
+}
+
+impl From<Timestamp> for u64 {
+    #[inline(always)]
+    fn from(time: Timestamp) -> Self {
+        time.0
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_datetime().format("%Y-%m-%dT%H:%M:%S%.9fZ"))
+    }
+}
+
+/// Range of frame times considered plausible when inserting.
+///
+/// Catches timestamps in the wrong unit, which land in 1970 (seconds or
+/// milliseconds taken as nanoseconds) or centuries ahead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeWindow {
+    /// Earliest accepted time.
+    pub earliest: Timestamp,
+    /// How far past the current wall clock time a frame may lie.
+    pub max_ahead: Duration,
+}
+
+impl TimeWindow {
+    #[inline(always)]
+    pub fn new(
+        earliest: Timestamp,
+        max_ahead: Duration,
+    ) -> TimeWindow {
+        TimeWindow {
+            earliest,
+            max_ahead,
+        }
+    }
+
+    #[inline(always)]
+    pub fn contains(
+        &self,
+        time: u64,
+    ) -> bool {
+        let latest =
+            Timestamp::now()
+                .checked_add(self.max_ahead)
+                .unwrap_or(Timestamp(u64::MAX));
+
+        self.earliest.0 <= time && time <= latest.0
+    }
+}
+
+impl Default for TimeWindow {
+    /// From 1990 until a day from now.
+    #[inline(always)]
+    fn default() -> Self {
+        TimeWindow {
+            earliest: Timestamp::from_datetime(
+                NaiveDate::from_ymd_opt(1990, 1, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+            ),
+            max_ahead: Duration::from_secs(86_400),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::quotick::{Quotick, QuotickError};
+    use super::super::test_util::TempPath;
+    use super::super::tick::Trade;
+    use super::super::Frame;
+    use super::*;
+
+    #[test]
+    fn converts_units() {
+        let time = Timestamp::from_secs(1_614_865_020).unwrap();
+
+        assert_eq!(Timestamp::from_millis(time.as_millis()), Some(time));
+        assert_eq!(Timestamp::from_micros(time.as_micros()), Some(time));
+        assert_eq!(Timestamp::from_secs_f64(1_614_865_020.25).unwrap().as_millis(), 1_614_865_020_250);
+        assert_eq!(Timestamp::from_millis(u64::MAX), None);
+        assert_eq!(time.checked_add(Duration::from_secs(u64::MAX)), None);
+
+        assert_eq!(TimeUnit::detect(1_614_865_020), TimeUnit::Seconds);
+        assert_eq!(TimeUnit::detect(time.as_millis()), TimeUnit::Millis);
+        assert_eq!(TimeUnit::detect(time.as_micros()), TimeUnit::Micros);
+        assert_eq!(TimeUnit::detect(time.as_nanos()), TimeUnit::Nanos);
+    }
+
+    #[test]
+    fn default_window() {
+        let window = TimeWindow::default();
+
+        assert!(window.contains(Timestamp::now().as_nanos()));
+        assert!(window.contains(Timestamp::from_secs(1_614_865_020).unwrap().as_nanos()));
+
+        // milliseconds taken as nanoseconds
+        assert!(!window.contains(1_614_865_020_000));
+        assert!(!window.contains(Timestamp::now().checked_add(Duration::from_secs(2 * 86_400)).unwrap().as_nanos()));
+    }
+
+    #[test]
+    fn rejects_implausible_frames() {
+        let base_path = TempPath::new("timestamp");
+        let mut quotick = Quotick::<Trade>::new("SYMBL", &base_path).unwrap();
+
+        let millis = 1_614_865_020_000;
+
+        quotick.insert(&Frame::new(millis, Trade::default())).unwrap();
+
+        quotick.set_time_window(Some(TimeWindow::default()));
+
+        match quotick.insert(&Frame::new(millis + 1, Trade::default())) {
+            Err(QuotickError::ImplausibleTime(time)) => assert_eq!(time, millis + 1),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        quotick
+            .insert(&Frame::at(Timestamp::from_millis(millis).unwrap(), Trade::default()))
+            .unwrap();
+    }
+}